@@ -0,0 +1,160 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::runner;
+
+/// Time each acceptance run may take before it counts as a hang
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sentences of the form `when run with input 5 it prints 25`; values may be quoted
+const CRITERION_PATTERN: &str = r#"(?i)when (?:it is |the program is )?run with (?:the )?input\s+(?:"(?P<quoted_input>[^"]*)"|(?P<input>[^\s,"]+))\s*,?\s*(?:then\s+)?it\s+(?:should\s+)?(?:prints?|outputs?|displays?)\s+(?:"(?P<quoted_output>[^"]*)"|(?P<output>[^\s"]+?))[.,;]?(?:\s|$)"#;
+
+static CRITERION: LazyLock<Regex> = LazyLock::new(|| Regex::new(CRITERION_PATTERN).expect("valid acceptance pattern"));
+
+/// An expectation about the program's behaviour written into the spec
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AcceptanceTest {
+    /// The spec text the expectation was taken from
+    pub sentence: String,
+    /// Text fed to the program's standard input
+    pub stdin: String,
+    /// Text the program's standard output must contain
+    pub expected: String,
+}
+
+/// Result of running a program against one acceptance test
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AcceptanceResult {
+    pub sentence: String,
+    pub stdin: String,
+    pub expected: String,
+    /// What the program actually wrote to standard output
+    pub actual: String,
+    /// What the program wrote to standard error
+    #[serde(default)]
+    pub stderr: String,
+    pub passed: bool,
+    pub outcome: String,
+    /// The model's explanation of a failure and a suggested fix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+/// Find the acceptance criteria written into a program description
+pub fn extract(description: &str) -> Vec<AcceptanceTest> {
+    CRITERION
+        .captures_iter(description)
+        .map(|captures| {
+            let input = captures.name("quoted_input").or_else(|| captures.name("input")).map_or("", |m| m.as_str());
+            let output = captures.name("quoted_output").or_else(|| captures.name("output")).map_or("", |m| m.as_str());
+            let mut stdin = unescape(input);
+            if !stdin.ends_with('\n') {
+                stdin.push('\n');
+            }
+            AcceptanceTest {
+                sentence: captures[0].trim().to_string(),
+                stdin,
+                expected: unescape(output),
+            }
+        })
+        .collect()
+}
+
+/// Build the prompt asking why a program failed one of its acceptance tests
+pub fn build_explanation_prompt(program_description: &str, code: &str, result: &AcceptanceResult) -> String {
+    format!(
+        r#"A program was built from the following NHLP (Natural High Level Programming Language) description:
+
+---
+NHLP PROGRAM:
+{}
+---
+
+GENERATED CODE:
+```
+{}
+```
+
+It failed this acceptance criterion from the description: {}
+
+Standard input:
+```
+{}
+```
+Expected standard output to contain:
+```
+{}
+```
+Actual standard output:
+```
+{}
+```
+Standard error:
+```
+{}
+```
+Result: {}
+
+In plain English, explain in a few sentences why the program's behaviour differs from the criterion,
+then suggest either a rewording of the description or a fix to the code. Do not repeat the code.
+"#,
+        program_description, code, result.sentence, result.stdin, result.expected, result.actual, result.stderr, result.outcome
+    )
+}
+
+/// Run the program against each acceptance test
+///
+/// A test passes when the program exits successfully within the time limit
+/// and prints the expected text as whole lines; see `prints`.
+pub fn run(executable: &str, tests: Vec<AcceptanceTest>) -> Result<Vec<AcceptanceResult>> {
+    tests
+        .into_iter()
+        .map(|test| {
+            let output = runner::run_captured(executable, &test.stdin, Some(RUN_TIMEOUT))?;
+            let passed = output.success() && prints(&output.stdout, &test.expected);
+            let outcome = if output.success() && !passed {
+                format!("expected a line {:?}", test.expected)
+            } else {
+                output.outcome()
+            };
+            Ok(AcceptanceResult {
+                sentence: test.sentence,
+                stdin: test.stdin,
+                expected: test.expected,
+                actual: output.stdout,
+                stderr: output.stderr,
+                passed,
+                outcome,
+                explanation: None,
+            })
+        })
+        .collect()
+}
+
+/// Whether the expected lines appear in the output as consecutive whole lines
+///
+/// Surrounding whitespace doesn't count, and the first line may follow a
+/// prompt ending in `:` or `?` that the program printed without a newline,
+/// so `Enter a number: 25` prints 25. `Enter 5 numbers:` doesn't print 5.
+fn prints(stdout: &str, expected: &str) -> bool {
+    let expected: Vec<&str> = expected.trim().lines().map(str::trim).collect();
+    let lines: Vec<&str> = stdout.lines().map(str::trim).collect();
+    let Some((first, rest)) = expected.split_first() else {
+        return stdout.trim().is_empty();
+    };
+    (0..lines.len()).any(|start| {
+        let line = lines[start];
+        let after_prompt = line.rfind([':', '?']).map(|end| line[end + 1..].trim());
+        (line == *first || after_prompt == Some(first))
+            && lines.len() - start > rest.len()
+            && rest.iter().zip(&lines[start + 1..]).all(|(expected, line)| expected == line)
+    })
+}
+
+/// Expand `\n` and `\t` escapes in a value quoted in the spec
+fn unescape(value: &str) -> String {
+    value.replace("\\n", "\n").replace("\\t", "\t")
+}
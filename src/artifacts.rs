@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Directory next to a spec holding its build artifacts
+pub const DIR_NAME: &str = ".nhlp-cache";
+
+/// Executables built from earlier compilations of a spec, so an unchanged
+/// program skips the toolchain as well as the model
+///
+/// Artifacts are keyed by a hash of the generated source and everything
+/// passed to the toolchain with it, so changing the code, target, or link
+/// flags rebuilds. Only the latest artifact of each program is kept.
+pub struct ArtifactCache {
+    dir: PathBuf,
+}
+
+impl ArtifactCache {
+    /// The cache in the `.nhlp-cache` directory next to `spec`
+    pub fn for_spec(spec: &Path) -> Self {
+        let parent = spec.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        Self { dir: parent.join(DIR_NAME) }
+    }
+    
+    /// Key identifying a build of `source` with the given toolchain arguments
+    pub fn key(source: &[u8], build: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in build {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(source);
+        format!("{:x}", hasher.finalize())
+    }
+    
+    /// Copy the artifact stored under `key` to `output_path`, returning whether there was one
+    pub fn restore(&self, program_name: &str, key: &str, output_path: &Path) -> bool {
+        let path = self.artifact_path(program_name, key);
+        if !path.is_file() {
+            return false;
+        }
+        match fs::copy(&path, output_path) {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Ignoring unusable build artifact {:?}: {}", path, e);
+                false
+            }
+        }
+    }
+    
+    /// Store the artifact at `output_path` under `key`, replacing the program's older artifacts
+    ///
+    /// The copy is written to a temporary file and renamed into place, so a
+    /// concurrent build never restores a partial executable.
+    pub fn store(&self, program_name: &str, key: &str, output_path: &Path) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create artifact directory: {:?}", self.dir))?;
+        let file = NamedTempFile::new_in(&self.dir)?;
+        fs::copy(output_path, file.path())
+            .with_context(|| format!("Failed to copy build artifact: {:?}", output_path))?;
+        let path = self.artifact_path(program_name, key);
+        file.persist(&path)
+            .with_context(|| format!("Failed to write build artifact: {:?}", path))?;
+        debug!("Stored build artifact {:?}", path);
+        
+        // Artifacts of earlier versions of the program can't be restored again
+        let prefix = format!("{}-", program_name);
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let stale = entry.path();
+            let is_older = stale != path
+                && stale.extension().is_some_and(|extension| extension == "bin")
+                && stale.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.strip_prefix(&prefix))
+                    .is_some_and(|rest| rest.len() == 64 && rest.bytes().all(|b| b.is_ascii_hexdigit()));
+            if is_older {
+                let _ = fs::remove_file(&stale);
+            }
+        }
+        Ok(())
+    }
+    
+    fn artifact_path(&self, program_name: &str, key: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.bin", program_name, key))
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::env;
+
+use crate::llm::{LlmError, MAX_OUTPUT_TOKENS};
+
+/// Environment variable selecting the provider when --llm-provider isn't given
+const PROVIDER_VAR: &str = "NHLP_LLM_PROVIDER";
+
+/// Where a local Ollama server listens unless OLLAMA_HOST says otherwise
+const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+/// The wire protocol of one LLM provider
+///
+/// Retries, fallback through the model ladder, cancellation, and egress
+/// accounting live in [`crate::llm::LlmClient`]; a backend only knows how to
+/// address the provider, shape a request, and read the reply.
+pub trait LlmBackend: Send + Sync {
+    /// Provider name recorded in compilation reports
+    fn name(&self) -> &'static str;
+
+    /// Model used when no ladder is configured
+    fn default_model(&self) -> &'static str;
+
+    /// Environment variable holding the API key, if the provider needs one
+    fn api_key_var(&self) -> Option<&'static str>;
+
+    /// URL that generates text with a model, without credentials
+    fn endpoint(&self, model: &str) -> String;
+
+    /// URL listing the available models, used to check that the provider is reachable
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn models_url(&self) -> String;
+
+    /// Headers authenticating a request
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)>;
+
+    /// Request body asking `model` to complete `prompt`
+    fn payload(&self, model: &str, prompt: &str) -> Value;
+
+    /// Pull the generated text out of a response body
+    fn extract_text(&self, response: &Value) -> Result<String>;
+
+    /// Whether requests stay on this machine
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Pick the backend named by `name`, NHLP_LLM_PROVIDER, or Gemini by default
+pub fn configured(name: Option<&str>) -> Result<Box<dyn LlmBackend>> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => env::var(PROVIDER_VAR).unwrap_or_else(|_| "gemini".to_string()),
+    };
+    match name.trim().to_ascii_lowercase().as_str() {
+        "gemini" => Ok(Box::new(Gemini)),
+        "openai" => Ok(Box::new(OpenAi)),
+        "anthropic" => Ok(Box::new(Anthropic)),
+        "ollama" => Ok(Box::new(Ollama::from_env())),
+        other => Err(anyhow::anyhow!(
+            "Unknown LLM provider: {} (expected gemini, openai, anthropic, or ollama)",
+            other
+        )),
+    }
+}
+
+/// Google's Gemini API
+pub struct Gemini;
+
+impl LlmBackend for Gemini {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gemini-2.0-flash"
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        Some("GEMINI_API_KEY")
+    }
+
+    fn endpoint(&self, model: &str) -> String {
+        format!("https://generativelanguage.googleapis.com/v1/models/{}:generateContent", model)
+    }
+
+    fn models_url(&self) -> String {
+        "https://generativelanguage.googleapis.com/v1/models".to_string()
+    }
+
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("x-goog-api-key", api_key.to_string())]
+    }
+
+    fn payload(&self, _model: &str, prompt: &str) -> Value {
+        json!({
+            "contents": [{
+                "parts": [{
+                    "text": prompt
+                }]
+            }],
+            "generationConfig": {
+                "temperature": 0.2,
+                "topP": 0.8,
+                "topK": 40,
+                "maxOutputTokens": MAX_OUTPUT_TOKENS
+            }
+        })
+    }
+
+    fn extract_text(&self, response: &Value) -> Result<String> {
+        let candidates = response.get("candidates")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| LlmError::ParseError("No candidates in response".to_string()))?;
+        let candidate = candidates.first()
+            .ok_or_else(|| LlmError::ParseError("Empty candidates array".to_string()))?;
+        
+        let parts = candidate.get("content")
+            .and_then(|content| content.get("parts"))
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| LlmError::ParseError("No parts in content".to_string()))?;
+        let part = parts.first()
+            .ok_or_else(|| LlmError::ParseError("Empty parts array".to_string()))?;
+        
+        text_field(part, "text")
+    }
+}
+
+/// OpenAI's chat completions API
+pub struct OpenAi;
+
+impl LlmBackend for OpenAi {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gpt-4o-mini"
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        Some("OPENAI_API_KEY")
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        "https://api.openai.com/v1/chat/completions".to_string()
+    }
+
+    fn models_url(&self) -> String {
+        "https://api.openai.com/v1/models".to_string()
+    }
+
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", api_key))]
+    }
+
+    fn payload(&self, model: &str, prompt: &str) -> Value {
+        json!({
+            "model": model,
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }],
+            "temperature": 0.2,
+            "top_p": 0.8,
+            "max_tokens": MAX_OUTPUT_TOKENS
+        })
+    }
+
+    fn extract_text(&self, response: &Value) -> Result<String> {
+        let choice = response.get("choices")
+            .and_then(|v| v.as_array())
+            .and_then(|choices| choices.first())
+            .ok_or_else(|| LlmError::ParseError("No choices in response".to_string()))?;
+        let message = choice.get("message")
+            .ok_or_else(|| LlmError::ParseError("No message in choice".to_string()))?;
+        
+        text_field(message, "content")
+    }
+}
+
+/// Anthropic's messages API
+pub struct Anthropic;
+
+impl LlmBackend for Anthropic {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "claude-3-5-haiku-latest"
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        Some("ANTHROPIC_API_KEY")
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        "https://api.anthropic.com/v1/messages".to_string()
+    }
+
+    fn models_url(&self) -> String {
+        "https://api.anthropic.com/v1/models".to_string()
+    }
+
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("x-api-key", api_key.to_string()),
+            ("anthropic-version", "2023-06-01".to_string()),
+        ]
+    }
+
+    fn payload(&self, model: &str, prompt: &str) -> Value {
+        json!({
+            "model": model,
+            "max_tokens": MAX_OUTPUT_TOKENS,
+            "temperature": 0.2,
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }]
+        })
+    }
+
+    fn extract_text(&self, response: &Value) -> Result<String> {
+        let block = response.get("content")
+            .and_then(|v| v.as_array())
+            .and_then(|blocks| blocks.iter().find(|block| block.get("type").and_then(|t| t.as_str()) == Some("text")))
+            .ok_or_else(|| LlmError::ParseError("No text block in response".to_string()))?;
+        
+        text_field(block, "text")
+    }
+}
+
+/// A local Ollama server
+pub struct Ollama {
+    host: String,
+}
+
+impl Ollama {
+    /// Address the server named by OLLAMA_HOST, or the default local one
+    fn from_env() -> Self {
+        let host = env::var("OLLAMA_HOST").unwrap_or_else(|_| DEFAULT_OLLAMA_HOST.to_string());
+        let host = if host.contains("://") { host } else { format!("http://{}", host) };
+        Self { host: host.trim_end_matches('/').to_string() }
+    }
+}
+
+impl LlmBackend for Ollama {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "llama3.1"
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        format!("{}/api/generate", self.host)
+    }
+
+    fn models_url(&self) -> String {
+        format!("{}/api/tags", self.host)
+    }
+
+    fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn payload(&self, model: &str, prompt: &str) -> Value {
+        json!({
+            "model": model,
+            "prompt": prompt,
+            "stream": false,
+            "options": {
+                "temperature": 0.2,
+                "top_p": 0.8,
+                "top_k": 40,
+                "num_predict": MAX_OUTPUT_TOKENS
+            }
+        })
+    }
+
+    fn extract_text(&self, response: &Value) -> Result<String> {
+        text_field(response, "response")
+    }
+
+    fn is_local(&self) -> bool {
+        ["localhost", "127.0.0.1", "::1"].contains(&host_name(&self.host))
+    }
+}
+
+/// The host part of a URL, without scheme, userinfo, port, path, or IPv6 brackets
+fn host_name(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    }
+}
+
+/// Read a string field of a response object
+fn text_field(object: &Value, field: &str) -> Result<String> {
+    object.get(field)
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| LlmError::ParseError(format!("No {} in response", field)).into())
+}
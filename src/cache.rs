@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use crate::llm::Completion;
+
+/// Environment variable overriding where responses are cached
+const CACHE_DIR_VAR: &str = "NHLP_CACHE_DIR";
+
+/// Current version of the cache entry format; entries written by other versions are ignored
+const SCHEMA_VERSION: u32 = 1;
+
+/// A model response stored on disk
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    schema_version: u32,
+    model: String,
+    tier: usize,
+    text: String,
+}
+
+/// Content-addressed store of model responses, so recompiling an unchanged
+/// program doesn't spend quota or wait on the provider again
+///
+/// Entries are keyed by a hash of the provider, the model ladder, and the
+/// exact prompt sent, so changing any of them misses the cache.
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// The cache in NHLP_CACHE_DIR, or the user's cache directory
+    pub fn open() -> Result<Self> {
+        Ok(Self::in_dir(default_dir()?))
+    }
+    
+    /// The cache in `dir`
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    
+    /// Directory the entries live in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    /// Key identifying a prompt sent to a provider's model ladder
+    pub fn key(provider: &str, ladder: &[String], prompt: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(provider.as_bytes());
+        hasher.update([0]);
+        hasher.update(ladder.join(",").as_bytes());
+        hasher.update([0]);
+        hasher.update(prompt.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+    
+    /// The cached response for `key`, if there is a readable one
+    pub fn get(&self, key: &str) -> Option<Completion> {
+        let json = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: Entry = match serde_json::from_str(&json) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Ignoring unreadable cache entry {}: {}", key, e);
+                return None;
+            }
+        };
+        if entry.schema_version != SCHEMA_VERSION {
+            debug!("Ignoring cache entry {} with schema version {}", key, entry.schema_version);
+            return None;
+        }
+        Some(Completion { text: entry.text, model: entry.model, tier: entry.tier, egress: Vec::new() })
+    }
+    
+    /// Store a response under `key`
+    ///
+    /// The entry is written to a temporary file and renamed into place, so
+    /// concurrent compilations never read a partial entry.
+    pub fn put(&self, key: &str, completion: &Completion) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory: {:?}", self.dir))?;
+        let entry = Entry {
+            schema_version: SCHEMA_VERSION,
+            model: completion.model.clone(),
+            tier: completion.tier,
+            text: completion.text.clone(),
+        };
+        let mut file = NamedTempFile::new_in(&self.dir)?;
+        file.write_all(serde_json::to_string(&entry)?.as_bytes())?;
+        let path = self.entry_path(key);
+        file.persist(&path)
+            .with_context(|| format!("Failed to write cache entry: {:?}", path))?;
+        Ok(())
+    }
+    
+    /// The text of every readable cached response, in key order
+    pub fn responses(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read cache directory: {:?}", self.dir)),
+        };
+        
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                keys.extend(path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string));
+            }
+        }
+        keys.sort();
+        Ok(keys.iter().filter_map(|key| self.get(key)).map(|completion| completion.text).collect())
+    }
+    
+    /// Delete every cached response, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read cache directory: {:?}", self.dir)),
+        };
+        
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove cache entry: {:?}", path))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+    
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// NHLP_CACHE_DIR, else the platform's per-user cache directory
+fn default_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(CACHE_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+    base.map(|base| base.join("nhlp").join("llm"))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine a cache directory; set {}", CACHE_DIR_VAR))
+}
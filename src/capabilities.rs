@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Categories of external interaction a generated program can perform
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    Network,
+    FileWrite,
+    ProcessSpawn,
+}
+
+impl Capability {
+    /// Source patterns that indicate this capability in generated C or Rust code
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Capability::Network => &[
+                "<sys/socket.h>", "<netdb.h>", "<arpa/inet.h>", "<winsock",
+                "socket(", "connect(", "std::net", "TcpStream", "TcpListener", "UdpSocket",
+            ],
+            Capability::FileWrite => &[
+                "fwrite(", "O_WRONLY", "O_RDWR", "O_CREAT", "remove(", "rename(", "unlink(", "mkdir(",
+                "File::create", "OpenOptions", "fs::write", "remove_file", "create_dir", "fs::rename",
+            ],
+            Capability::ProcessSpawn => &[
+                "system(", "popen(", "fork(", "execv", "execl", "CreateProcess",
+                "Command::new", "process::Command",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Network => "network",
+            Capability::FileWrite => "file-write",
+            Capability::ProcessSpawn => "process-spawn",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Scan generated code for external interactions
+pub fn scan(code: &str) -> BTreeSet<Capability> {
+    let mut found = BTreeSet::new();
+    
+    for line in code.lines() {
+        let line = line.trim();
+        
+        // Skip comments so explanations in the generated code don't count
+        if line.starts_with("//") || line.starts_with("/*") || line.starts_with('*') {
+            continue;
+        }
+        
+        for capability in [Capability::Network, Capability::FileWrite, Capability::ProcessSpawn] {
+            if capability.patterns().iter().any(|pattern| line.contains(pattern)) {
+                found.insert(capability);
+            }
+        }
+        
+        // fopen only writes when opened with a write or append mode
+        if line.contains("fopen(") && (line.contains("\"w") || line.contains("\"a") || line.contains("+\"")) {
+            found.insert(Capability::FileWrite);
+        }
+    }
+    
+    found
+}
+
+/// Format a set of capabilities as a comma-separated list
+pub fn describe(capabilities: &BTreeSet<Capability>) -> String {
+    capabilities.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,233 @@
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::acceptance;
+use crate::checkpoint;
+use crate::data_formats;
+use crate::doctor::{Check, Status};
+use crate::glossary::Glossary;
+use crate::lockfile::Lockfile;
+use crate::offline;
+use crate::patterns;
+use crate::project::Project;
+use crate::redact::Redactor;
+use crate::sorting;
+use crate::sourcemap::Span;
+use crate::verify::{self, Proof};
+
+/// Phrases that leave a decision to the model instead of stating it
+const VAGUE_PHRASES: [&str; 10] = [
+    "etc",
+    "and so on",
+    "something",
+    "somehow",
+    "appropriate",
+    "appropriately",
+    "as needed",
+    "if necessary",
+    "TODO",
+    "TBD",
+];
+
+static VAGUE: LazyLock<Regex> = LazyLock::new(|| {
+    let pattern = VAGUE_PHRASES.iter().map(|phrase| regex::escape(phrase)).collect::<Vec<_>>().join("|");
+    RegexBuilder::new(&format!(r"\b(?:{})\b", pattern))
+        .case_insensitive(true)
+        .build()
+        .expect("vague phrase pattern is valid")
+});
+
+/// Check a spec or project for everything that can go wrong before it is sent to the model
+///
+/// Nothing is translated or built: this reads the spec, its glossary and
+/// lockfile, and looks for missing acceptance criteria and wording the
+/// model would have to guess at.
+pub fn run(input_path: &Path, redact_patterns: &[String]) -> Vec<Check> {
+    let (spec_path, source) = match Project::manifest_for(input_path) {
+        Some(manifest) => match Project::load(&manifest) {
+            Ok(project) => (manifest, project.source()),
+            Err(e) => return vec![Check::problem("project", Status::Fail, format!("{:#}", e), "Fix the manifest")],
+        },
+        None => match fs::read_to_string(input_path) {
+            Ok(source) => (input_path.to_path_buf(), source),
+            Err(e) => return vec![Check::problem("spec", Status::Fail, format!("Failed to read {:?}: {}", input_path, e), "Check the path")],
+        },
+    };
+    
+    let mut checks = vec![check_spec(&source)];
+    checks.push(check_glossary(&spec_path, &source));
+    checks.push(check_redaction(&source, redact_patterns));
+    checks.push(check_acceptance(&source));
+    checks.push(check_checkpoint(&source));
+    checks.push(check_data_formats(&source));
+    checks.push(check_patterns(&source));
+    checks.push(check_sorting(&source));
+    checks.push(check_lockfile(&spec_path));
+    checks.push(check_ambiguity(&source));
+    checks.push(check_arithmetic(&source));
+    checks
+}
+
+/// Whether the spec has anything to translate
+fn check_spec(source: &str) -> Check {
+    let lines = source.lines().filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#')).count();
+    if lines == 0 {
+        return Check::problem("spec", Status::Fail, "the spec is empty", "Describe what the program should do");
+    }
+    Check::ok("spec", format!("{} line(s) of description", lines))
+}
+
+fn check_glossary(spec_path: &Path, source: &str) -> Check {
+    let path = Glossary::path_for(spec_path);
+    match Glossary::load(&path) {
+        Ok(glossary) if glossary.is_empty() => Check::ok("glossary", "none"),
+        Ok(glossary) => Check::ok("glossary", format!(
+            "{} of {} term(s) used by this program",
+            glossary.used_terms(source).len(),
+            glossary.len()
+        )),
+        Err(e) => Check::problem("glossary", Status::Fail, format!("{:#}", e), "Write one `term: meaning` definition per line"),
+    }
+}
+
+fn check_redaction(source: &str, redact_patterns: &[String]) -> Check {
+    match Redactor::new(redact_patterns) {
+        Ok(redactor) => {
+            let (_, redaction) = redactor.redact(source);
+            Check::ok("redaction", format!("{} sensitive value(s) will be masked before translation", redaction.len()))
+        }
+        Err(e) => Check::problem("redaction", Status::Fail, format!("{:#}", e), "Fix the --redact-pattern regular expression"),
+    }
+}
+
+fn check_acceptance(source: &str) -> Check {
+    let criteria = acceptance::extract(source).len();
+    if criteria == 0 {
+        return Check::problem(
+            "acceptance criteria",
+            Status::Warn,
+            "none found, so --check-acceptance has nothing to run",
+            "Add sentences like \"when run with input 5 it prints 25\"",
+        );
+    }
+    Check::ok("acceptance criteria", format!("{} found", criteria))
+}
+
+fn check_checkpoint(source: &str) -> Check {
+    match checkpoint::interval(source) {
+        Some(interval) => Check::ok("checkpointing", format!("every {}s, resuming after a restart", interval.as_secs())),
+        None => Check::ok("checkpointing", "off"),
+    }
+}
+
+fn check_data_formats(source: &str) -> Check {
+    let formats = data_formats::extract(source);
+    if formats.is_empty() {
+        return Check::ok("data formats", "none");
+    }
+    let described: Vec<String> = formats
+        .iter()
+        .map(|data| match data.fields.is_empty() {
+            true => format!("{} (no fields named; the model will infer them)", data.format),
+            false => format!("{} with {}", data.format, data.fields.join(", ")),
+        })
+        .collect();
+    Check::ok("data formats", described.join("; "))
+}
+
+fn check_patterns(source: &str) -> Check {
+    match patterns::extract(source) {
+        Ok(patterns) if patterns.is_empty() => Check::ok("patterns", "none"),
+        Ok(patterns) => {
+            let described: Vec<String> = patterns.iter().map(|pattern| pattern.regex.clone()).collect();
+            Check::ok("patterns", described.join("; "))
+        }
+        Err(e) => Check::problem("patterns", Status::Fail, format!("{:#}", e), "Fix the pattern or the example after `like`"),
+    }
+}
+
+fn check_sorting(source: &str) -> Check {
+    let sorting = sorting::extract(source);
+    if sorting.is_empty() {
+        return Check::ok("sorting", "none");
+    }
+    let mut described: Vec<String> = sorting.sorts.iter().map(|sort| format!("{} for \"{}\"", sort.algorithm, sort.phrase)).collect();
+    if sorting.searches {
+        described.push("binary search".to_string());
+    }
+    Check::ok("sorting", described.join("; "))
+}
+
+fn check_lockfile(spec_path: &Path) -> Check {
+    match Lockfile::load(&Lockfile::path_for(spec_path)) {
+        Ok(_) => Check::ok("lockfile", "readable"),
+        Err(e) => Check::problem("lockfile", Status::Fail, format!("{:#}", e), "Fix or delete nhlp.lock, then approve again"),
+    }
+}
+
+/// Sentences whose wording leaves a decision to the model
+fn check_ambiguity(source: &str) -> Check {
+    let lines = source.lines().collect::<Vec<_>>();
+    let mut vague: Vec<String> = Vec::new();
+    let mut last_line = 0;
+    for (span, phrase) in vague_phrases(source) {
+        if span.line != last_line {
+            vague.push(format!("\"{}\" in: {}", phrase, lines[span.line - 1].trim()));
+            last_line = span.line;
+        }
+    }
+    if vague.is_empty() {
+        return Check::ok("ambiguity", "no vague wording found");
+    }
+    Check::problem(
+        "ambiguity",
+        Status::Warn,
+        format!("{} line(s) leave decisions to the model:\n         {}", vague.len(), vague.join("\n         ")),
+        "Say exactly what should happen instead",
+    )
+}
+
+/// Divisors that may be zero and arithmetic that always overflows, for specs in the offline vocabulary
+fn check_arithmetic(source: &str) -> Check {
+    let Ok(sentences) = offline::parse(source) else {
+        return Check::ok("arithmetic", "not verified, since the spec is outside the offline vocabulary");
+    };
+    let verification = verify::verify(&sentences);
+    let reported: Vec<String> = verification
+        .obligations
+        .iter()
+        .filter(|obligation| obligation.is_reported())
+        .map(|obligation| format!("line {}: {}", obligation.line, obligation.describe()))
+        .collect();
+    let proven = format!("{} of {} condition(s) proven", verification.count(Proof::Proven), verification.obligations.len());
+    if reported.is_empty() {
+        return Check::ok("arithmetic", proven);
+    }
+    Check::problem(
+        "arithmetic",
+        Status::Warn,
+        format!("{}; {} problem(s):\n         {}", proven, reported.len(), reported.join("\n         ")),
+        "Divide only by values the spec keeps away from zero",
+    )
+}
+
+/// Every vague phrase in a spec and where it is, skipping `#` heading lines
+pub fn vague_phrases(source: &str) -> Vec<(Span, String)> {
+    let mut found = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for phrase in VAGUE.find_iter(line) {
+            let span = Span {
+                line: index + 1,
+                column: line[..phrase.start()].chars().count() + 1,
+                length: phrase.as_str().chars().count(),
+            };
+            found.push((span, phrase.as_str().to_string()));
+        }
+    }
+    found
+}
@@ -0,0 +1,50 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Environment variable that overrides where a program keeps its checkpoint
+pub const ENV_VAR: &str = "NHLP_CHECKPOINT";
+
+/// Sentences like `checkpoint every 30 seconds` or `save its state every 5 minutes`
+const HINT_PATTERN: &str = r"(?i)\b(?:checkpoints?(?:\s+its\s+state)?|saves?\s+(?:its|the|all)\s+state)\s+every\s+(?P<count>\d+)\s*(?P<unit>seconds?|secs?|s|minutes?|mins?|m|hours?|h)\b";
+
+static HINT: LazyLock<Regex> = LazyLock::new(|| Regex::new(HINT_PATTERN).expect("checkpoint pattern is valid"));
+
+/// How often the spec asks a long-running program to save its state, if it does
+pub fn interval(description: &str) -> Option<Duration> {
+    let captures = HINT.captures(description)?;
+    let count: u64 = captures["count"].parse().ok()?;
+    let seconds = match captures["unit"].to_lowercase().chars().next() {
+        Some('h') => count * 3600,
+        Some('m') => count * 60,
+        _ => count,
+    };
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// Instructions added to the translation prompt for a program that checkpoints
+///
+/// Generated programs carry no nhlp runtime, so the model writes the
+/// checkpointing itself: one struct holding the state that must survive a
+/// restart, written atomically on a timer and read back at startup.
+pub fn prompt_note(language: &str, program_name: &str, interval: Duration) -> String {
+    let io = if language == "rust" {
+        "std::fs::write to a temporary file followed by std::fs::rename"
+    } else {
+        "fopen/fprintf/fflush/fclose on a temporary file followed by rename()"
+    };
+    format!(
+        "\nCHECKPOINTING: this program runs for a long time and must survive restarts. \
+         Keep all state that has to survive (counters, totals, queues, progress markers) in one struct. \
+         The checkpoint file is named by the {env} environment variable, or \"{name}.checkpoint\" in the \
+         current directory when it is unset. At startup, if the file exists, restore the state from it and \
+         print \"Resumed from checkpoint\" to stderr; if it is unreadable, print a warning and start fresh. \
+         Every {seconds} seconds of work, and before exiting normally, write the whole state as one \
+         `key=value` line per field using {io}, so a crash never leaves a partial checkpoint. \
+         Never write secrets or input that was only needed once.\n",
+        env = ENV_VAR,
+        name = program_name,
+        seconds = interval.as_secs(),
+        io = io,
+    )
+}
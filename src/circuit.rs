@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failures after which a model's circuit opens
+///
+/// No more than the attempts each ladder tier gets, so a model that uses
+/// up its tier is skipped by the rest of the compile instead of being
+/// retried by every later stage.
+pub const FAILURE_THRESHOLD: usize = 2;
+
+/// How long an open circuit short-circuits calls before allowing a trial call
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Failure history for one model
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+}
+
+/// Circuit breaker that stops calling models which keep failing
+///
+/// Once a model fails `FAILURE_THRESHOLD` times in a row its circuit opens and
+/// callers skip straight to their fallback. After `COOLDOWN` one trial call is
+/// let through; success closes the circuit again.
+pub struct CircuitBreaker {
+    states: Mutex<HashMap<String, BreakerState>>,
+    cooldown: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::with_cooldown(COOLDOWN)
+    }
+    
+    /// A breaker whose open circuits allow a trial call after `cooldown`
+    pub fn with_cooldown(cooldown: Duration) -> Self {
+        Self { states: Mutex::default(), cooldown }
+    }
+    
+    /// Whether a call to `model` should be attempted
+    pub fn allow(&self, model: &str) -> bool {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        match states.get_mut(model).and_then(|state| state.opened_at.as_mut()) {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() >= self.cooldown => {
+                // Half-open: allow one trial and restart the cooldown
+                *opened_at = Instant::now();
+                true
+            }
+            Some(_) => false,
+        }
+    }
+    
+    /// Record a successful call, closing the circuit
+    pub fn record_success(&self, model: &str) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        states.remove(model);
+    }
+    
+    /// Record a failed call, opening the circuit once the threshold is reached
+    pub fn record_failure(&self, model: &str) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let state = states.entry(model.to_string()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= FAILURE_THRESHOLD && state.opened_at.is_none() {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Cooldown short enough to wait out in a test
+    const SHORT: Duration = Duration::from_millis(50);
+
+    fn fail(breaker: &CircuitBreaker, model: &str, times: usize) {
+        for _ in 0..times {
+            breaker.record_failure(model);
+        }
+    }
+
+    #[test]
+    fn circuit_opens_at_the_threshold() {
+        let breaker = CircuitBreaker::new();
+        fail(&breaker, "flaky", FAILURE_THRESHOLD - 1);
+        assert!(breaker.allow("flaky"));
+        fail(&breaker, "flaky", 1);
+        assert!(!breaker.allow("flaky"));
+        assert!(breaker.allow("other"));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new();
+        fail(&breaker, "flaky", FAILURE_THRESHOLD - 1);
+        breaker.record_success("flaky");
+        fail(&breaker, "flaky", FAILURE_THRESHOLD - 1);
+        assert!(breaker.allow("flaky"));
+    }
+
+    #[test]
+    fn open_circuit_refuses_calls_until_the_cooldown_passes() {
+        let breaker = CircuitBreaker::with_cooldown(Duration::from_secs(3600));
+        fail(&breaker, "flaky", FAILURE_THRESHOLD);
+        assert!(!breaker.allow("flaky"));
+        assert!(!breaker.allow("flaky"));
+    }
+
+    #[test]
+    fn half_open_circuit_allows_one_trial_and_closes_on_success() {
+        let breaker = CircuitBreaker::with_cooldown(SHORT);
+        fail(&breaker, "flaky", FAILURE_THRESHOLD);
+        thread::sleep(SHORT * 2);
+        assert!(breaker.allow("flaky"));
+        // The trial restarted the cooldown, so a second call waits
+        assert!(!breaker.allow("flaky"));
+        breaker.record_success("flaky");
+        assert!(breaker.allow("flaky"));
+        assert!(breaker.allow("flaky"));
+    }
+
+    #[test]
+    fn failed_trial_keeps_the_circuit_open() {
+        let breaker = CircuitBreaker::with_cooldown(SHORT);
+        fail(&breaker, "flaky", FAILURE_THRESHOLD);
+        thread::sleep(SHORT * 2);
+        assert!(breaker.allow("flaky"));
+        breaker.record_failure("flaky");
+        assert!(!breaker.allow("flaky"));
+        thread::sleep(SHORT * 2);
+        assert!(breaker.allow("flaky"));
+    }
+}
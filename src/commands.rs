@@ -0,0 +1,227 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Sentences like `run the command ls -l and capture its output`
+const SPEC_PATTERN: &str = r#"(?i)\bruns?\s+the\s+(?:shell\s+)?command\s+[`"']?(?P<command>[\w./-]+)"#;
+
+/// Functions and syscalls in generated C or Rust that start another program, or a
+/// process that could exec one
+const SPAWN_PATTERN: &str = r"\b(?:system|popen|wordexp|fork|vfork|execl|execlp|execle|execv|execvp|execvpe|execve|execveat|fexecve|posix_spawnp?|WinExec|CreateProcess\w*|ShellExecute\w*|_?spawn[lv]p?e?|_exec[lv]p?e?|SYS_(?:execve|execveat|fork|vfork|clone3?)|Command)\b";
+
+/// A whole argument that is one string literal
+const LITERAL_PATTERN: &str = r#"^"(?P<literal>(?:[^"\\]|\\.)*)"$"#;
+
+static SPEC: LazyLock<Regex> = LazyLock::new(|| Regex::new(SPEC_PATTERN).expect("command pattern is valid"));
+static SPAWN: LazyLock<Regex> = LazyLock::new(|| Regex::new(SPAWN_PATTERN).expect("spawn pattern is valid"));
+static LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(LITERAL_PATTERN).expect("literal pattern is valid"));
+
+/// Shell syntax that makes a command line run something other than its words
+const SHELL_EXPANSION: &[&str] = &["$", "`", "(", "\\"];
+
+/// A place in generated code that starts another program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spawn {
+    /// Line of the generated code, counting from 1
+    pub line: usize,
+    /// The function, syscall, or type referred to
+    pub call: String,
+    /// The program started, or None when it can't be read from a literal
+    pub command: Option<String>,
+}
+
+/// Commands the spec asks the program to run
+pub fn described(description: &str) -> Vec<String> {
+    let mut commands: Vec<String> = SPEC
+        .captures_iter(description)
+        .map(|captures| captures["command"].trim_end_matches(['.', '-']).to_string())
+        .collect();
+    commands.dedup();
+    commands
+}
+
+/// Every reference in `code` to something that starts another program, with
+/// the programs started when they can be read from the call
+///
+/// Only a direct call whose command argument is a single string literal names
+/// its program: `system("ls -l | wc")` runs `ls` and `wc`, `execvp("ls", args)`
+/// runs `ls`. Anything else has no command, so an allowlist turns it down: a
+/// computed or conditional argument, a shell line using expansions, a function
+/// pointer or alias taken to one of these, and fork, which runs whatever the
+/// child does next. Plain `use` imports of Rust's Command aren't references.
+pub fn spawned(code: &str) -> Vec<Spawn> {
+    let masked = mask(code);
+    let mut found = Vec::new();
+    for call in SPAWN.find_iter(&masked) {
+        let line = masked[..call.start()].matches('\n').count() + 1;
+        let before = masked[..call.start()].trim_end();
+        if before.ends_with('.') || before.ends_with("->") {
+            continue;
+        }
+        let statement = masked[..call.start()].rsplit(['\n', ';']).next().unwrap_or_default().trim_start_matches(|c: char| c.is_whitespace() || c == '{' || c == '}');
+        if call.as_str() == "Command" && statement.starts_with("use ") && !masked[call.end()..].trim_start().starts_with("as ") {
+            continue;
+        }
+        
+        let name = call.as_str().to_string();
+        let commands = command_argument(code, &masked, &name, call.end()).map_or(vec![None], |literal| {
+            if shell_line(&name) {
+                shell_commands(&literal)
+            } else {
+                vec![Some(basename(&literal))]
+            }
+        });
+        found.extend(commands.into_iter().map(|command| Spawn { line, call: name.clone(), command }));
+    }
+    found
+}
+
+/// The literal passed as the program to a direct call starting at `after`, if it is one
+fn command_argument(code: &str, masked: &str, name: &str, after: usize) -> Option<String> {
+    let rest = &masked[after..];
+    let (position, open) = match name {
+        "Command" => (0, after + rest.find("::new")? + "::new".len()),
+        "system" | "popen" | "WinExec" | "execl" | "execlp" | "execle" | "execv" | "execvp" | "execvpe" | "execve" => (0, after),
+        "execveat" | "posix_spawn" | "posix_spawnp" => (1, after),
+        _ => return None,
+    };
+    if !masked[after..open].chars().all(|c| c.is_whitespace() || c == ':' || c.is_alphanumeric()) {
+        return None;
+    }
+    let arguments = arguments(masked, open)?;
+    let (start, end) = *arguments.get(position)?;
+    LITERAL.captures(code[start..end].trim()).map(|captures| captures["literal"].to_string())
+}
+
+/// Byte ranges of each argument of the call whose parenthesis follows `open`
+fn arguments(masked: &str, open: usize) -> Option<Vec<(usize, usize)>> {
+    let opening = open + masked[open..].find(|c: char| !c.is_whitespace())?;
+    if !masked[opening..].starts_with('(') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut start = opening + 1;
+    let mut ranges = Vec::new();
+    for (index, c) in masked[opening..].char_indices().map(|(index, c)| (opening + index, c)) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 1 => {
+                ranges.push((start, index));
+                return Some(ranges);
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 1 => {
+                ranges.push((start, index));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether a call's command is a shell command line rather than a program path
+fn shell_line(call: &str) -> bool {
+    matches!(call, "system" | "popen" | "WinExec")
+}
+
+/// The program each part of a shell command line runs, or a single None when the
+/// line expands something at runtime
+fn shell_commands(line: &str) -> Vec<Option<String>> {
+    if SHELL_EXPANSION.iter().any(|syntax| line.contains(syntax)) {
+        return vec![None];
+    }
+    line.split(['|', ';', '&', '\n'])
+        .filter_map(|part| part.split_whitespace().find(|word| !word.contains('=')))
+        .map(|word| Some(basename(word)))
+        .collect()
+}
+
+fn basename(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+/// The code with comments blanked and string contents replaced by spaces, keeping every offset
+///
+/// Character literals are blanked too; a quote that doesn't close one within
+/// a few characters is a Rust lifetime and left alone.
+fn mask(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let mut masked = String::with_capacity(code.len());
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        if c == '/' && next == Some('/') {
+            while index < chars.len() && chars[index] != '\n' {
+                push_blank(&mut masked, chars[index]);
+                index += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let end = (index + 2..chars.len().saturating_sub(1)).find(|&i| chars[i] == '*' && chars[i + 1] == '/').map_or(chars.len(), |i| i + 2);
+            chars[index..end].iter().for_each(|&c| push_blank(&mut masked, c));
+            index = end;
+        } else if c == '"' || (c == '\'' && is_char_literal(&chars[index..])) {
+            masked.push(c);
+            index += 1;
+            while index < chars.len() && chars[index] != c {
+                if chars[index] == '\\' && index + 1 < chars.len() {
+                    push_blank(&mut masked, chars[index]);
+                    index += 1;
+                }
+                push_blank(&mut masked, chars[index]);
+                index += 1;
+            }
+            if index < chars.len() {
+                masked.push(c);
+                index += 1;
+            }
+        } else {
+            masked.push(c);
+            index += 1;
+        }
+    }
+    masked
+}
+
+/// Push spaces taking up as many bytes as `c`, or the newline itself
+fn push_blank(masked: &mut String, c: char) {
+    if c == '\n' {
+        masked.push('\n');
+    } else {
+        masked.extend(std::iter::repeat_n(' ', c.len_utf8()));
+    }
+}
+
+/// Whether the quote at the start of `chars` opens a character literal like `'a'` or `'\n'`
+fn is_char_literal(chars: &[char]) -> bool {
+    match chars.get(1) {
+        Some('\\') => chars.iter().skip(2).take(10).any(|&c| c == '\''),
+        Some(_) => chars.get(2) == Some(&'\''),
+        None => false,
+    }
+}
+
+/// Instructions added to the translation prompt for a program that runs commands
+///
+/// Output is captured into a string the rest of the program can use, and
+/// the command is named by a literal so `--allow-command` can check it.
+pub fn prompt_note(language: &str, allowed: &[String]) -> String {
+    let capture = if language == "rust" {
+        "std::process::Command::new(\"NAME\") with each argument passed through .arg(), and read .output() \
+         into a String with String::from_utf8_lossy"
+    } else {
+        "popen(\"NAME ARGS\", \"r\") and read the whole output with fread into a buffer that grows with realloc, \
+         then pclose it and check the exit status"
+    };
+    let mut note = format!(
+        "\nCOMMANDS: run each command the description names with {}. Write the command name as a string \
+         literal at the call. Store the captured output, without its trailing newline, in a string variable \
+         named after what it holds. Report a command that can't start or exits with an error instead of \
+         using empty output. Never pass input from the user to a shell.\n",
+        capture
+    );
+    if !allowed.is_empty() {
+        note.push_str(&format!("Only these commands may be run: {}.\n", allowed.join(", ")));
+    }
+    note
+}
@@ -1,20 +1,62 @@
 use anyhow::{Context, Result};
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::fs;
-use std::io::{self, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::Arc;
 use tempfile::{Builder, NamedTempFile};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::env;
 
-use crate::gemini::GeminiClient;
+use crate::acceptance::{self, AcceptanceResult};
+use crate::artifacts::{self, ArtifactCache};
+use crate::backend;
+use crate::cache::ResponseCache;
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::capabilities::{self, Capability};
+use crate::checkpoint;
+use crate::commands;
+use crate::cost;
+use crate::counterexamples::{self, Counterexample, CounterexampleResult};
+use crate::coverage;
+use crate::data_formats;
+use crate::equivalence::{self, EquivalenceReport};
+use crate::error::NhlpError;
+use crate::features::TargetFeatures;
+use crate::glossary::Glossary;
+use crate::hardening::{self, HardeningSummary};
+use crate::library::{self, LibraryKind};
+use crate::linker::{self, LinkOptions};
+use crate::lint::{Lint, LintLevels};
+use crate::llm::{Completion, EgressRecord, LlmClient, OFFLINE_TIER};
+use crate::lockfile::Lockfile;
+use crate::offline;
+use crate::opt_level::OptLevel;
+use crate::patterns;
+use crate::policy::{ConstructPolicy, Level};
+use crate::precedents::{self, PrecedentIndex};
+use crate::progress::{Decision, Progress, ProgressObserver, Stage};
+use crate::redact::{Redaction, Redactor};
+use crate::report::{self, CompilationReport, Outcome};
+use crate::runner::{self, ProgramOutput};
+use crate::signals;
+use crate::sorting;
+use crate::sourcemap::SourceMap;
+use crate::stack::{self, StackEstimate};
+use crate::transcript::Transcript;
+use crate::verify::{self, Proof};
 
 /// Represents available compilers
+#[derive(Clone, Copy)]
 struct CompilerInfo {
     gcc: bool,
     clang: bool,
     rustc: bool,
+    /// Whether WebAssembly builds can be run
+    wasmtime: bool,
 }
 
 impl CompilerInfo {
@@ -43,7 +85,15 @@ impl CompilerInfo {
             .status()
             .is_ok();
         
-        Self { gcc, clang, rustc }
+        // Check for a WASI runtime
+        let wasmtime = Command::new(runner::WASM_RUNTIME)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok();
+        
+        Self { gcc, clang, rustc, wasmtime }
     }
     
     fn has_c_compiler(&self) -> bool {
@@ -51,16 +101,235 @@ impl CompilerInfo {
     }
 }
 
+/// Stages that send requests to the model, each of which can have its own timeout
+pub const MODEL_STAGES: [&str; 3] = ["translate", "counterexamples", "explanation"];
+
+/// Settings that control how programs are compiled
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// Fail when generated code uses capabilities not approved in the lockfile
+    pub require_approval: bool,
+    
+    /// Models to fall back through in order; `offline` translates by rule
+    pub model_ladder: Vec<String>,
+    
+    /// Time limit for each request to the model
+    pub request_timeout: Duration,
+    
+    /// Time limits for the requests of particular stages, overriding `request_timeout`
+    pub stage_timeouts: BTreeMap<String, Duration>,
+    
+    /// Mask secrets in the program before it is sent to the model
+    pub redact: bool,
+    
+    /// Extra regular expressions whose matches are masked
+    pub redact_patterns: Vec<String>,
+    
+    /// Fail instead of making any network request
+    pub air_gapped: bool,
+    
+    /// Allow/warn/deny levels for unsafe constructs in generated code
+    pub construct_policy: ConstructPolicy,
+    
+    /// Programs the generated code may start; when set, any other command fails the build
+    pub allowed_commands: Vec<String>,
+    
+    /// Allow/warn/deny levels for the warnings raised about a program
+    pub lints: LintLevels,
+    
+    /// Instrument the program and report which described behaviors ran
+    pub coverage: bool,
+    
+    /// Print how much code, I/O, allocation, and looping each sentence produced
+    pub cost: bool,
+    
+    /// Run the program against model-proposed edge-case inputs
+    pub counterexamples: bool,
+    
+    /// Fail when -O0 and -O2 builds behave differently on generated inputs
+    pub check_equivalence: bool,
+    
+    /// Run the acceptance criteria written in the spec and fail when the program doesn't meet them
+    pub check_acceptance: bool,
+    
+    /// Capture the program's stdout, stderr, and exit code instead of inheriting stdio
+    pub capture_output: bool,
+    
+    /// File fed to the program's stdin instead of the terminal
+    pub stdin_file: Option<PathBuf>,
+    
+    /// Write textual LLVM IR to this path instead of building and running an executable
+    pub emit_ir: Option<PathBuf>,
+    
+    /// Write a relocatable object file to this path instead of linking and running an executable
+    pub emit_obj: Option<PathBuf>,
+    
+    /// Write annotated assembly to this path instead of building and running an executable
+    pub emit_asm: Option<PathBuf>,
+    
+    /// Build with debug info; C programs' line tables point at the spec's sentences
+    pub debug_info: bool,
+    
+    /// Stack size built into the program, which runs its `main` on a thread with that stack
+    pub stack_size: Option<u64>,
+    
+    /// Flags for linking the program into an executable
+    pub link: LinkOptions,
+    
+    /// Build a library exporting the functions the spec describes instead of an executable
+    pub library: Option<LibraryKind>,
+    
+    /// CPU features enabled or disabled for code generation
+    pub features: TargetFeatures,
+    
+    /// Optimization level; None leaves the toolchain's default
+    pub opt_level: Option<OptLevel>,
+    
+    /// Build a position-independent executable with stack canaries, RELRO, NX, and fortified calls
+    pub harden: bool,
+    
+    /// Directory receiving every prompt and raw model response
+    pub debug_llm: Option<PathBuf>,
+    
+    /// Target triple to cross-compile for instead of the host
+    pub target: Option<String>,
+    
+    /// LLM provider to translate with; None reads NHLP_LLM_PROVIDER or uses Gemini
+    pub llm_provider: Option<String>,
+    
+    /// Translate with deterministic rules instead of a model, so no API key or network is needed
+    pub offline: bool,
+    
+    /// Reuse model responses cached by earlier compilations of the same prompts
+    pub cache: bool,
+    
+    /// Show the model how earlier compilations translated similar sentences, and record this one's
+    pub precedents: bool,
+    
+    /// Reuse the executable in .nhlp-cache when the generated code and build flags are unchanged
+    pub incremental: bool,
+    
+    /// Directory the executable is written to instead of the current directory
+    pub out_dir: Option<PathBuf>,
+    
+    /// Run the program once it is built; when false, the build is the last step
+    pub run: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            require_approval: false,
+            model_ladder: Vec::new(),
+            request_timeout: Duration::from_secs(120),
+            stage_timeouts: BTreeMap::new(),
+            redact: true,
+            redact_patterns: Vec::new(),
+            air_gapped: false,
+            construct_policy: ConstructPolicy::default(),
+            allowed_commands: Vec::new(),
+            lints: LintLevels::default(),
+            coverage: false,
+            cost: false,
+            counterexamples: false,
+            check_equivalence: false,
+            check_acceptance: false,
+            capture_output: false,
+            stdin_file: None,
+            emit_ir: None,
+            emit_obj: None,
+            emit_asm: None,
+            debug_info: false,
+            stack_size: None,
+            link: LinkOptions::default(),
+            library: None,
+            features: TargetFeatures::default(),
+            opt_level: None,
+            harden: false,
+            debug_llm: None,
+            target: None,
+            llm_provider: None,
+            offline: false,
+            cache: true,
+            precedents: false,
+            incremental: true,
+            out_dir: None,
+            run: true,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Parse `stage=seconds` timeouts such as `translate=300`
+    pub fn parse_stage_timeouts(entries: &[String]) -> Result<BTreeMap<String, Duration>> {
+        let mut timeouts = BTreeMap::new();
+        for entry in entries {
+            let (stage, seconds) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid stage timeout `{}`, expected stage=seconds", entry))?;
+            let stage = stage.trim();
+            if !MODEL_STAGES.contains(&stage) {
+                anyhow::bail!("Unknown stage `{}` in --stage-timeout; stages are {}", stage, MODEL_STAGES.join(", "));
+            }
+            let seconds: u64 = seconds.trim().parse().with_context(|| format!("Invalid seconds in stage timeout `{}`", entry))?;
+            timeouts.insert(stage.to_string(), Duration::from_secs(seconds));
+        }
+        Ok(timeouts)
+    }
+    
+    /// Time limit for each request `stage` sends to the model
+    pub fn timeout(&self, stage: &str) -> Duration {
+        self.stage_timeouts.get(stage).copied().unwrap_or(self.request_timeout)
+    }
+}
+
 /// The NHLP native compiler
+///
+/// Clones share the same LLM client and its connection pool, so one
+/// compiler can serve concurrent compilations from several threads.
+#[derive(Clone)]
 pub struct Compiler {
-    gemini_client: GeminiClient,
+    llm: Arc<LlmClient>,
+    transcript: Option<Arc<Transcript>>,
+    cache: Option<Arc<ResponseCache>>,
     compilers: CompilerInfo,
+    options: CompileOptions,
 }
 
+// Compiler handles must stay shareable across threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Compiler>();
+};
+
 impl Compiler {
     /// Create a new compiler instance
-    pub fn new() -> Result<Self> {
-        let gemini_client = GeminiClient::new()?;
+    pub fn new(options: CompileOptions) -> Result<Self> {
+        if options.offline && (options.counterexamples || options.check_equivalence) {
+            return Err(anyhow::anyhow!(
+                "--counterexamples and --check-equivalence ask a model for inputs and can't be used with --offline"
+            ));
+        }
+        if options.harden && options.opt_level == Some(OptLevel::O0) {
+            warn!("--harden with -O0: _FORTIFY_SOURCE has no effect without optimization");
+        }
+        if options.debug_info && options.link.strip.is_some() {
+            warn!("-g with --strip: stripping removes the debug info again");
+        }
+        if options.library.is_some() && options.offline {
+            return Err(anyhow::anyhow!("--lib needs a model; offline translation only writes whole programs"));
+        }
+        if options.library.is_some() && options.target.as_deref().is_some_and(is_wasm_target) {
+            return Err(anyhow::anyhow!("--lib can't build WebAssembly modules"));
+        }
+        
+        // Offline compilations never reach the model, so don't require a key for it
+        let ladder = if options.offline {
+            vec![OFFLINE_TIER.to_string()]
+        } else {
+            options.model_ladder.clone()
+        };
+        let llm = Arc::new(LlmClient::new(backend::configured(options.llm_provider.as_deref())?, ladder, options.air_gapped)?);
         let compilers = CompilerInfo::new();
         
         // Log available compilers
@@ -80,13 +349,31 @@ impl Compiler {
             warn!("No compilers found - unable to generate machine code directly");
         }
         
-        Ok(Self { gemini_client, compilers })
+        let transcript = match &options.debug_llm {
+            Some(dir) => Some(Arc::new(Transcript::new(dir)?)),
+            None => None,
+        };
+        
+        // Simulated responses aren't worth caching and mustn't shadow real ones
+        let cache = if options.cache && !llm.is_simulated() {
+            Some(Arc::new(ResponseCache::open()?))
+        } else {
+            None
+        };
+        
+        Ok(Self { llm, transcript, cache, compilers, options })
     }
 
     /// Compile a .dshp file directly to native machine code and execute it
-    pub fn execute<P: AsRef<Path>>(&self, input_path: P) -> Result<()> {
-        info!("Compiling NHLP directly to machine code");
-
+    ///
+    /// Cancelling `cancel` stops the compilation at the next stage boundary and
+    /// removes the temporary source file. `progress` is told as each stage starts.
+    pub fn execute<P: AsRef<Path>>(
+        &self,
+        input_path: P,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<CompilationReport> {
         // Read the input file
         let input = fs::read_to_string(&input_path)
             .with_context(|| format!("Failed to read input file: {:?}", input_path.as_ref()))?;
@@ -99,182 +386,1389 @@ impl Compiler {
             .and_then(|s| s.to_str())
             .unwrap_or("nhlp_program");
         
+        self.execute_source(input_path.as_ref(), program_name, &input, cancel, progress)
+    }
+    
+    /// Compile program text that didn't come from a single .dshp file, such as
+    /// the merged modules of a project, and execute it
+    ///
+    /// `input_path` locates the glossary and lockfile and is recorded as the
+    /// report's source; `program_name` names the executable.
+    pub fn execute_source(
+        &self,
+        input_path: &Path,
+        program_name: &str,
+        input: &str,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<CompilationReport> {
+        let result = self.compile_source(input_path, program_name, input, cancel, progress);
+        // --debug-llm keeps the transcript, which should say why it stops short
+        if let (Err(e), Some(transcript)) = (&result, &self.transcript) {
+            if e.is::<Cancelled>() {
+                if let Err(e) = transcript.outcome("cancelled", &format!("Compilation of {} cancelled", input_path.display())) {
+                    warn!("Failed to record the cancellation: {}", e);
+                }
+            }
+        }
+        result
+    }
+    
+    /// Every stage of `execute_source`, stopping at the first failure
+    fn compile_source(
+        &self,
+        input_path: &Path,
+        program_name: &str,
+        input: &str,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<CompilationReport> {
+        info!("Compiling NHLP directly to machine code");
+        
         let start_time = Instant::now();
+        let spec = Spec { path: input_path, program_name, text: input };
+        let mut decisions = Vec::new();
+        let prepared = self.prepare_prompt(&spec, &mut decisions, progress)?;
+        let language = prepared.language;
+        let translation = self.translate(&spec, &prepared, &mut decisions, cancel, progress)?;
+        let binary_instructions = prepared.redaction.restore(&translation.text, language)?;
+        let (stack, capabilities) = self.check_generated(&spec, &binary_instructions, language, &mut decisions, progress)?;
+        let (source_file, executable_path, toolchain) = self.build_or_reuse(&spec, &binary_instructions, &translation, language, cancel, progress)?;
+        
+        let elapsed = start_time.elapsed();
+        info!("Compilation complete in {:.2?}", elapsed);
+        if self.options.precedents && translation.model != OFFLINE_TIER && !self.options.offline && !self.llm.is_simulated() {
+            self.record_precedents(input_path, &translation.text, language);
+        }
         
-        // Determine which language to target based on available compilers
-        let use_rust = !self.compilers.has_c_compiler() && self.compilers.rustc;
+        let costs = cost::analyze(&binary_instructions);
+        if self.options.cost {
+            println!("{}", cost::render(&costs));
+            if stack.is_unbounded() {
+                println!("Stack: too large to count along {}", stack.path.join(" -> "));
+            } else if !stack.path.is_empty() {
+                println!("Stack: about {} along {}", stack::human_size(stack.bytes), stack.path.join(" -> "));
+            }
+        }
         
+        let emitted = self.options.emit_ir.is_some() || self.options.emit_obj.is_some() || self.options.emit_asm.is_some();
+        let mut report = CompilationReport {
+            schema_version: report::SCHEMA_VERSION,
+            outcome: Outcome::Completed,
+            source_file: input_path.display().to_string(),
+            source_hash: report::hash_source(input),
+            source: input.to_string(),
+            provider: if self.options.offline { "offline" } else { self.llm.provider() }.to_string(),
+            model: translation.model,
+            tier: translation.tier,
+            prompt_hash: report::hash_source(&prepared.prompt),
+            language: language.to_string(),
+            toolchain: toolchain.to_string(),
+            executable: executable_path.clone(),
+            generated_code: binary_instructions,
+            capabilities,
+            egress: translation.egress,
+            costs,
+            coverage: Vec::new(),
+            counterexamples: Vec::new(),
+            equivalence: None,
+            acceptance: Vec::new(),
+            output: None,
+            hardening: None,
+            decisions,
+            build_flags: self.codegen_args(language),
+            // IR, objects, and assembly aren't linked
+            link_flags: if emitted { Vec::new() } else { self.link_args(language) },
+        };
+        // readelf can't summarize an archive of objects
+        let linked = self.options.library != Some(LibraryKind::Static);
+        if self.options.harden && linked && !emitted {
+            report.hardening = self.check_hardening(Path::new(&executable_path));
+        }
+        if self.options.emit_ir.is_some() {
+            info!("LLVM IR written to {}", executable_path);
+            return Ok(report);
+        }
+        if self.options.emit_obj.is_some() {
+            info!("Object file written to {}", executable_path);
+            return Ok(report);
+        }
+        if self.options.emit_asm.is_some() {
+            info!("Assembly written to {}", executable_path);
+            return Ok(report);
+        }
+        if let Some(kind) = self.options.library {
+            self.write_library_files(kind, Path::new(&executable_path), input_path, program_name, &report.generated_code, language)?;
+            return Ok(report);
+        }
+        if let Some(target) = self.options.target.as_deref().filter(|target| !self.can_run(target)) {
+            if is_wasm_target(target) {
+                info!("Built {} for {}; install {} to run it", executable_path, target, runner::WASM_RUNTIME);
+            } else {
+                info!("Built {} for {}; skipping the run since it can't execute on this machine", executable_path, target);
+            }
+            return Ok(report);
+        }
+        if !self.options.run {
+            info!("Built {}", executable_path);
+            return Ok(report);
+        }
+        
+        self.run_checks(&spec, &prepared, source_file.path(), &mut report, cancel, progress)?;
+        Ok(report)
+    }
+    
+    /// Redact the spec and build its translation prompt, adding a note for
+    /// each kind of work the spec describes and recording the decision
+    fn prepare_prompt(&self, spec: &Spec, decisions: &mut Vec<String>, progress: &dyn ProgressObserver) -> Result<PreparedPrompt> {
+        let (input, program_name) = (spec.text, spec.program_name);
+        
+        // Mask credentials and personal data before the program leaves the machine
+        let glossary = Glossary::load(&Glossary::path_for(spec.path))?;
+        let mut prepared = redacted_prompt(input, &glossary, self.options.redact, &self.options.redact_patterns, self.compilers)?;
+        let language = prepared.language;
+        let prompt = &mut prepared.prompt;
+        if self.options.library.is_some() {
+            prompt.push_str(library::prompt_note(language));
+        }
+        if let Some(interval) = checkpoint::interval(input) {
+            decide(progress, decisions, Stage::Translating, format!("Checkpointing every {}s; the program resumes from {}.checkpoint or ${}", interval.as_secs(), program_name, checkpoint::ENV_VAR));
+            prompt.push_str(&checkpoint::prompt_note(language, program_name, interval));
+        }
+        let data_formats = data_formats::extract(input);
+        if !data_formats.is_empty() {
+            let formats: Vec<String> = data_formats.iter().map(|data| data.format.to_string()).collect();
+            decide(progress, decisions, Stage::Translating, format!("Writing {} parsing into the program", formats.join(" and ")));
+            prompt.push_str(&data_formats::prompt_note(language, &data_formats));
+        }
+        let patterns = patterns::extract(input)?;
+        if !patterns.is_empty() {
+            let expressions: Vec<&str> = patterns.iter().map(|pattern| pattern.regex.as_str()).collect();
+            decide(progress, decisions, Stage::Translating, format!("Matching {} pattern(s): {}", patterns.len(), expressions.join(", ")));
+            prompt.push_str(&patterns::prompt_note(language, &patterns));
+        }
+        let sorting = sorting::extract(input);
+        if !sorting.is_empty() {
+            let mut chosen: Vec<String> = sorting.sorts.iter().map(|sort| format!("{} for \"{}\"", sort.algorithm, sort.phrase)).collect();
+            if sorting.searches {
+                chosen.push("binary search to find positions".to_string());
+            }
+            decide(progress, decisions, Stage::Translating, format!("Sorting with {}", chosen.join("; ")));
+            prompt.push_str(&sorting::prompt_note(language, &sorting));
+        }
+        let described = commands::described(input);
+        if !described.is_empty() {
+            decide(progress, decisions, Stage::Translating, format!("Running command(s): {}", described.join(", ")));
+            prompt.push_str(&commands::prompt_note(language, &self.options.allowed_commands));
+        }
+        let signals = signals::extract(input);
+        if !signals.is_empty() {
+            let names: Vec<String> = signals.keys().map(|signal| signal.to_string()).collect();
+            decide(progress, decisions, Stage::Translating, format!("Handling {}", names.join(", ")));
+            prompt.push_str(&signals::prompt_note(language, &signals));
+        }
+        if self.options.precedents && !self.options.offline {
+            if let Some((count, note)) = self.precedent_note(spec.path, input, language) {
+                decide(progress, decisions, Stage::Translating, format!("Following {} precedent(s) from earlier compilations", count));
+                prompt.push_str(&note);
+            }
+        }
+        if !prepared.redaction.is_empty() {
+            decide(progress, decisions, Stage::Translating, format!("Redacted {} sensitive value(s) before translation", prepared.redaction.len()));
+        }
+        Ok(prepared)
+    }
+    
+    /// Translate the spec by rule with --offline, or with the model ladder,
+    /// returning the generated code with placeholders still in place of secrets
+    fn translate(
+        &self,
+        spec: &Spec,
+        prepared: &PreparedPrompt,
+        decisions: &mut Vec<String>,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<Completion> {
         // Send to Neural Compiler Engine for direct translation to machine code
         info!("Neural Compiler Engine: analyzing natural language semantics");
-        let (binary_instructions, language) = if use_rust {
-            (self.translate_to_rust_code(&input)?, "rust")
+        let language = prepared.language;
+        let translation = if self.options.offline {
+            progress.on_progress(&Progress::new(Stage::Translating, 1, 1, "Translating with offline rules"));
+            if language != "c" {
+                return Err(NhlpError::MissingToolchain { language: "C", install: "gcc or clang" }.into());
+            }
+            Completion {
+                text: offline::translate(spec.text)?,
+                model: offline::MODEL.to_string(),
+                tier: 0,
+                egress: Vec::new(),
+            }
         } else {
-            (self.translate_to_c_code(&input)?, "c")
+            progress.on_progress(&Progress::new(
+                Stage::Translating, 1, 2,
+                format!("Sending program to {}", self.llm.model()),
+            ));
+            // The offline tier translates the spec by rule, which only writes C
+            let response = self.ask("translate", &prepared.prompt, (language == "c").then_some(spec.text), cancel)?;
+            
+            // Extract the machine code instructions
+            progress.on_progress(&Progress::new(Stage::Translating, 2, 2, "Extracting generated code"));
+            let code = extract_code_from_response(&response.text)?;
+            Completion { text: code, ..response }
         };
+        if translation.tier > 0 {
+            decide(progress, decisions, Stage::Translating, format!("Using fallback model {}", translation.model));
+            self.lint(Lint::ModelDegraded, format!(
+                "Translated by fallback model {} (tier {}) instead of {}",
+                translation.model, translation.tier + 1, self.llm.model()
+            ))?;
+        }
+        Ok(translation)
+    }
+    
+    /// Check the generated code before anything is built: unsafe constructs,
+    /// signal handlers, arithmetic, stack use, and external interactions
+    ///
+    /// Returns the stack estimate and the capabilities the code uses.
+    fn check_generated(
+        &self,
+        spec: &Spec,
+        code: &str,
+        language: &str,
+        decisions: &mut Vec<String>,
+        progress: &dyn ProgressObserver,
+    ) -> Result<(StackEstimate, BTreeSet<Capability>)> {
+        let (input_path, input) = (spec.path, spec.text);
+        let checks = if self.options.require_approval { 3 } else { 2 };
+        progress.on_progress(&Progress::new(Stage::Checking, 1, checks, "Checking unsafe constructs"));
+        self.check_constructs(input_path, input, code)?;
+        let signals = signals::extract(input);
+        if !signals.is_empty() && !signals::installs_handler(code) {
+            let source_map = SourceMap::new(input, code);
+            for (signal, phrase) in &signals {
+                let excerpt = source_map.locate(phrase).map(|span| source_map.render(&input_path.display().to_string(), span));
+                self.lint_at(Lint::UnhandledSignal, format!("The spec reacts to {}, but the program installs no handler for it", signal), excerpt)?;
+            }
+        }
         
-        // Create temporary source file with appropriate extension
-        let source_file = create_temp_source_file(&binary_instructions, language, program_name)?;
-        let source_path = source_file.path().to_path_buf();
+        self.check_arithmetic(input_path, input)?;
+        let stack = stack::analyze(code, language);
+        self.check_stack(&stack)?;
+        
+        progress.on_progress(&Progress::new(Stage::Checking, 2, checks, "Scanning external interactions"));
+        let capabilities = capabilities::scan(code);
+        if !capabilities.is_empty() {
+            decide(progress, decisions, Stage::Checking, format!("Program uses: {}", capabilities::describe(&capabilities)));
+        }
+        self.check_commands(input_path, input, code)?;
+        if self.options.require_approval {
+            progress.on_progress(&Progress::new(Stage::Checking, 3, checks, "Checking approvals in nhlp.lock"));
+            self.check_approval(input_path, &capabilities)?;
+        }
+        Ok((stack, capabilities))
+    }
+    
+    /// Write the code to a temporary source file and build it, or reuse an
+    /// identical earlier build, or stop at IR, an object, or assembly when asked
+    ///
+    /// Returns the source file, which later checks rebuild from, the path of
+    /// what was built, and the toolchain that built it.
+    fn build_or_reuse(
+        &self,
+        spec: &Spec,
+        code: &str,
+        translation: &Completion,
+        language: &str,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<(NamedTempFile, String, &'static str)> {
+        let (input_path, program_name) = (spec.path, spec.program_name);
         
-        // Generate final executable
-        info!("Generating native machine code");
-        let executable_path = self.generate_executable(&source_path, program_name, language)?;
+        // Create temporary source file with appropriate extension
+        progress.on_progress(&Progress::new(Stage::Building, 1, 2, format!("Writing {} source", language)));
+        let header = provenance_header(input_path, spec.text, &translation.model, translation.tier);
+        let code = self.debuggable(input_path, spec.text, code, language);
+        let code = self.with_stack_size(code, language);
+        let source_file = create_temp_source_file(&format!("{}{}", header, code), language, program_name)?;
+        let source_path = source_file.path();
         
-        let elapsed = start_time.elapsed();
-        info!("Compilation complete in {:.2?}", elapsed);
+        // Generate final executable, or stop at LLVM IR when asked to
+        cancel.check()?;
+        let (executable_path, toolchain) = if let Some(ir_path) = &self.options.emit_ir {
+            progress.on_progress(&Progress::new(Stage::Building, 2, 2, "Generating LLVM IR"));
+            info!("Generating LLVM IR");
+            let toolchain = self.emit_ir(source_path, program_name, language, ir_path)?;
+            (ir_path.display().to_string(), toolchain)
+        } else if let Some(object_path) = &self.options.emit_obj {
+            progress.on_progress(&Progress::new(Stage::Building, 2, 2, "Generating object file"));
+            info!("Generating object file");
+            let toolchain = self.emit_object(source_path, program_name, language, object_path)?;
+            (object_path.display().to_string(), toolchain)
+        } else if let Some(asm_path) = &self.options.emit_asm {
+            progress.on_progress(&Progress::new(Stage::Building, 2, 2, "Generating assembly"));
+            info!("Generating assembly");
+            let toolchain = self.emit_asm(source_path, program_name, language, asm_path)?;
+            (asm_path.display().to_string(), toolchain)
+        } else {
+            progress.on_progress(&Progress::new(Stage::Building, 2, 2, "Generating native machine code"));
+            info!("Generating native machine code");
+            self.generate_executable(input_path, source_path, program_name, language)?
+        };
+        Ok((source_file, executable_path, toolchain))
+    }
+    
+    /// Run the checks the options ask for against the built program, then the
+    /// program itself, recording each result in `report`
+    ///
+    /// A failed equivalence or acceptance check fails the compilation before the program runs.
+    fn run_checks(
+        &self,
+        spec: &Spec,
+        prepared: &PreparedPrompt,
+        source_path: &Path,
+        report: &mut CompilationReport,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressObserver,
+    ) -> Result<()> {
+        let PreparedPrompt { description, redaction, language, .. } = prepared;
+        let (executable_path, toolchain) = (report.executable.clone(), report.toolchain.clone());
+        let runs = 1
+            + self.options.counterexamples as usize
+            + self.options.check_equivalence as usize
+            + self.options.check_acceptance as usize;
+        let mut run = 0;
+        if self.options.counterexamples {
+            cancel.check()?;
+            run += 1;
+            progress.on_progress(&Progress::new(Stage::Running, run, runs, "Running proposed counterexamples"));
+            report.counterexamples = self.check_counterexamples(description, redaction, &executable_path, cancel)?;
+        }
+        if self.options.check_equivalence {
+            cancel.check()?;
+            run += 1;
+            progress.on_progress(&Progress::new(Stage::Running, run, runs, "Comparing -O0 and -O2 builds"));
+            let equivalence = self.check_equivalence(source_path, spec.program_name, language, description, redaction, cancel)?;
+            let divergences = equivalence.divergences.len();
+            report.equivalence = Some(equivalence);
+            if divergences > 0 {
+                return Err(anyhow::anyhow!(
+                    "Optimized build behaves differently from the -O0 build on {} input(s)",
+                    divergences
+                ));
+            }
+        }
+        if self.options.check_acceptance {
+            cancel.check()?;
+            run += 1;
+            progress.on_progress(&Progress::new(Stage::Running, run, runs, "Checking acceptance criteria"));
+            report.acceptance = self.check_acceptance(spec.text, &executable_path)?;
+            let explanation_egress = self.explain_failures(&mut report.acceptance, description, &report.generated_code, cancel)?;
+            report.egress.extend(explanation_egress);
+            let failures = report.acceptance.iter().filter(|result| !result.passed).count();
+            if failures > 0 {
+                return Err(anyhow::anyhow!(
+                    "{} of {} acceptance test(s) failed",
+                    failures,
+                    report.acceptance.len()
+                ));
+            }
+        }
         
         // Run the compiled binary
+        cancel.check()?;
+        progress.on_progress(&Progress::new(Stage::Running, run + 1, runs, format!("Running {}", executable_path)));
         info!("Running native executable: {:?}", executable_path);
-        self.run_binary(&executable_path)?;
+        report.output = self.run_binary(&executable_path)?;
         
+        // Coverage data is written when the program exits
+        if self.options.coverage && toolchain != "rustc" {
+            let sentences = coverage::collect(Path::new(&executable_path), source_path, &toolchain)?;
+            println!("{}", coverage::render(&sentences));
+            let source_map = SourceMap::new(spec.text, &report.generated_code);
+            let spec_path = spec.path.display().to_string();
+            for sentence in sentences.iter().filter(|sentence| sentence.executed_lines == 0) {
+                let excerpt = source_map.locate(&sentence.sentence).map(|span| source_map.render(&spec_path, span));
+                self.lint_at(Lint::UncoveredSentence, format!("Code for \"{}\" never ran", sentence.sentence), excerpt)?;
+            }
+            report.coverage = sentences;
+        }
         Ok(())
     }
     
-    /// Translate the natural language program directly to C code
-    fn translate_to_c_code(&self, program_description: &str) -> Result<String> {
-        let prompt = format!(
-            r#"You are the NHLP compiler that translates natural language directly to machine code.
-
-Your task is to translate the following NHLP (Natural High Level Programming Language) program:
-
----
-NHLP PROGRAM:
-{}
----
-
-IMPORTANT: Generate complete, compilable C code that implements this program exactly as described.
-Include all necessary headers and implement full interactive capabilities.
-The code must be surrounded by triple backticks with the language identifier.
-
-RESPOND ONLY WITH THE COMPLETE CODE.
-"#,
-            program_description
-        );
+    /// Ask the model for adversarial inputs implied by the program description
+    fn propose_inputs(&self, description: &str, redaction: &Redaction, cancel: &CancellationToken) -> Result<Vec<Counterexample>> {
+        let prompt = counterexamples::build_prompt(description);
+        let response = self.ask("counterexamples", &prompt, None, cancel)?;
+        counterexamples::parse(&redaction.restore(&extract_code_from_response(&response.text)?, "json")?)
+    }
+    
+    /// Build the program at -O0 and -O2 and compare their behavior on generated inputs
+    fn check_equivalence(
+        &self,
+        source_path: &Path,
+        program_name: &str,
+        language: &str,
+        description: &str,
+        redaction: &Redaction,
+        cancel: &CancellationToken,
+    ) -> Result<EquivalenceReport> {
+        let mut inputs = self.propose_inputs(description, redaction, cancel)?;
+        inputs.push(Counterexample {
+            description: "no input".to_string(),
+            stdin: String::new(),
+        });
         
-        // Get the translated code from Gemini
-        let response = self.gemini_client.execute_code(&prompt)?;
+        // Build both variants side by side in a scratch directory
+        let build_dir = tempfile::tempdir()?;
+        let mut variants = Vec::new();
+        for (level, name) in [(OptLevel::O0, "O0"), (OptLevel::O2, "O2")] {
+            let output_path = build_dir.path().join(self.executable_name(&format!("{}_{}", program_name, name)));
+            let mut args = level.driver_args(language);
+            args.extend(self.options.link.driver_args(language));
+            self.build(source_path, program_name, language, &output_path, &args)?;
+            variants.push(output_path.to_string_lossy().into_owned());
+        }
         
-        // Extract the machine code instructions
-        let code = extract_code_from_response(&response);
+        let report = equivalence::compare(&variants[0], &variants[1], &inputs)?;
+        for divergence in &report.divergences {
+            warn!(
+                "-O0 and -O2 builds diverge on {}: {} vs {}",
+                divergence.description,
+                divergence.unoptimized.outcome(),
+                divergence.optimized.outcome()
+            );
+        }
+        info!("Checked {} input(s) for optimizer equivalence", report.inputs_checked);
         
-        Ok(code)
+        Ok(report)
     }
     
-    /// Translate the natural language program directly to Rust code
-    fn translate_to_rust_code(&self, program_description: &str) -> Result<String> {
-        let prompt = format!(
-            r#"You are the NHLP compiler that translates natural language directly to machine code.
-
-Your task is to translate the following NHLP (Natural High Level Programming Language) program:
-
----
-NHLP PROGRAM:
-{}
----
-
-IMPORTANT: Generate complete, compilable Rust code that implements this program exactly as described.
-Include all necessary crates and implement full interactive capabilities.
-The code must be surrounded by triple backticks with the language identifier.
-Be sure to handle user input properly and make the code robust.
-Make sure the code is valid Rust that can be compiled with rustc directly.
-Do not use any external crates that need to be added to Cargo.toml - use only the standard library.
-
-RESPOND ONLY WITH THE COMPLETE RUST CODE.
-"#,
-            program_description
+    /// Run the program against adversarial inputs the model proposes for its description
+    fn check_counterexamples(
+        &self,
+        description: &str,
+        redaction: &Redaction,
+        executable_path: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<CounterexampleResult>> {
+        let proposed = self.propose_inputs(description, redaction, cancel)?;
+        let results = counterexamples::run(executable_path, proposed)?;
+        for result in &results {
+            if result.passed {
+                info!("Counterexample passed: {}", result.description);
+            } else {
+                self.lint(Lint::CounterexampleFailed, format!("Counterexample failed: {} ({})", result.description, result.outcome))?;
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    /// Run the program against the acceptance criteria written in its spec
+    fn check_acceptance(&self, input: &str, executable_path: &str) -> Result<Vec<AcceptanceResult>> {
+        let tests = acceptance::extract(input);
+        if tests.is_empty() {
+            self.lint(Lint::NoAcceptanceCriteria, "No acceptance criteria found; write them as \"when run with input 5 it prints 25\"")?;
+        }
+        let results = acceptance::run(executable_path, tests)?;
+        for result in &results {
+            if result.passed {
+                info!("Acceptance test passed: {}", result.sentence);
+            } else {
+                error!("Acceptance test failed: {} ({})", result.sentence, result.outcome);
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    /// Ask the model to explain each failed acceptance test, returning the requests made
+    ///
+    /// Explanations are advisory, so a failed request is logged rather than
+    /// failing the build a second time.
+    fn explain_failures(
+        &self,
+        results: &mut [AcceptanceResult],
+        description: &str,
+        code: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<EgressRecord>> {
+        if self.options.offline {
+            return Ok(Vec::new());
+        }
+        
+        let redactor = Redactor::new(&self.options.redact_patterns)?;
+        let mut egress = Vec::new();
+        for result in results.iter_mut().filter(|result| !result.passed) {
+            let prompt = acceptance::build_explanation_prompt(description, code, result);
+            // The program's output and code hold the restored secrets, so mask them again
+            let (prompt, redaction) = if self.options.redact {
+                redactor.redact(&prompt)
+            } else {
+                (prompt, Default::default())
+            };
+            match self.ask("explanation", &prompt, None, cancel) {
+                Ok(completion) => {
+                    let explanation = redaction.restore_text(completion.text.trim());
+                    println!("Why \"{}\" failed:\n{}\n", result.sentence, explanation);
+                    result.explanation = Some(explanation);
+                    egress.extend(completion.egress);
+                }
+                Err(e) if e.is::<Cancelled>() => return Err(e),
+                Err(e) => warn!("Could not explain acceptance failure: {}", e),
+            }
+        }
+        
+        Ok(egress)
+    }
+    
+    /// Read back and print the hardening an executable actually got
+    ///
+    /// Only ELF executables can be inspected; for anything else, or without
+    /// readelf, the summary is skipped with a warning.
+    fn check_hardening(&self, executable: &Path) -> Option<HardeningSummary> {
+        if self.options.target.as_deref().is_some_and(is_wasm_target) {
+            warn!("--harden does not apply to WebAssembly modules");
+            return None;
+        }
+        match hardening::inspect(executable) {
+            Ok(summary) => {
+                println!("{}", summary);
+                Some(summary)
+            }
+            Err(e) => {
+                warn!("Could not verify hardening of {:?}: {}", executable, e);
+                None
+            }
+        }
+    }
+    
+    /// Report a lint at its configured level, failing the compilation when it is denied
+    fn lint(&self, lint: Lint, message: impl fmt::Display) -> Result<()> {
+        self.lint_at(lint, message, None)
+    }
+    
+    /// Raise a lint with an excerpt underlining the part of the spec it concerns
+    fn lint_at(&self, lint: Lint, message: impl fmt::Display, excerpt: Option<String>) -> Result<()> {
+        let excerpt = excerpt.map(|excerpt| format!("\n{}", excerpt)).unwrap_or_default();
+        match self.options.lints.level(lint) {
+            Level::Allow => Ok(()),
+            Level::Warn => {
+                warn!("{}: {} [{}]{}", lint.code(), message, lint, excerpt);
+                Ok(())
+            }
+            Level::Deny => Err(anyhow::anyhow!("{}: {} [{}, denied]{}", lint.code(), message, lint, excerpt)),
+        }
+    }
+    
+    /// A prompt note showing how earlier compilations translated sentences like the spec's, with how many it shows
+    ///
+    /// The index only improves translations, so one that can't be read is
+    /// skipped with a warning rather than failing the compilation.
+    fn precedent_note(&self, input_path: &Path, input: &str, language: &str) -> Option<(usize, String)> {
+        let index = ResponseCache::open().and_then(|cache| PrecedentIndex::load(&PrecedentIndex::path_in(cache.dir())));
+        let index = match index {
+            Ok(index) => index,
+            Err(e) => {
+                warn!("Skipping precedents: {:#}", e);
+                return None;
+            }
+        };
+        let found = index.retrieve(&precedent_spec(input_path), input, language);
+        if found.is_empty() {
+            return None;
+        }
+        Some((found.len(), precedents::prompt_note(language, &found)))
+    }
+    
+    /// Record the sentences of a translation that built, for later compilations to follow
+    ///
+    /// `code` is the translation before secrets were restored into it.
+    fn record_precedents(&self, input_path: &Path, code: &str, language: &str) {
+        let recorded = ResponseCache::open()
+            .and_then(|cache| PrecedentIndex::load(&PrecedentIndex::path_in(cache.dir())))
+            .and_then(|mut index| {
+                let recorded = index.record(&precedent_spec(input_path), code, language);
+                index.save()?;
+                Ok(recorded)
+            });
+        match recorded {
+            Ok(recorded) => debug!("Recorded {} precedent(s)", recorded),
+            Err(e) => warn!("Failed to record precedents: {:#}", e),
+        }
+    }
+    
+    /// Warn about divisors that may be zero and arithmetic that always overflows
+    ///
+    /// Only specs in the offline vocabulary can be verified; others are skipped.
+    fn check_arithmetic(&self, input_path: &Path, input: &str) -> Result<()> {
+        let Ok(sentences) = offline::parse(input) else {
+            return Ok(());
+        };
+        let verification = verify::verify(&sentences);
+        info!(
+            "Arithmetic: {} condition(s) proven, {} unproven, {} refuted",
+            verification.count(Proof::Proven), verification.count(Proof::Unproven), verification.count(Proof::Refuted)
         );
+        let source_map = SourceMap::new(input, "");
+        let spec = input_path.display().to_string();
+        for obligation in verification.obligations.iter().filter(|obligation| obligation.is_reported()) {
+            let excerpt = source_map.locate(&obligation.sentence).map(|span| source_map.render(&spec, span));
+            self.lint_at(Lint::UnsafeArithmetic, obligation.describe(), excerpt)?;
+        }
+        Ok(())
+    }
+    
+    /// Warn about recursion, and about call chains needing more stack than the program gets
+    ///
+    /// The limit is only known for programs that run on this machine.
+    fn check_stack(&self, stack: &StackEstimate) -> Result<()> {
+        if stack.path.is_empty() {
+            return Ok(());
+        }
+        for function in &stack.recursive {
+            self.lint(Lint::UnboundedRecursion, format!(
+                "{} calls itself, so how much stack it needs depends on the input; the estimate counts one call of it",
+                function
+            ))?;
+        }
+        if stack.is_unbounded() {
+            return self.lint(Lint::StackOverflow, format!(
+                "{} declares arrays too large to count, so no stack size is enough for it",
+                stack.path.join(" -> ")
+            ));
+        }
+        info!("Estimated stack use: {} along {}", stack::human_size(stack.bytes), stack.path.join(" -> "));
+        let native = self.options.target.as_deref().is_none_or(is_host_target);
+        let limit = self.program_stack().or_else(runner::stack_limit);
+        if let Some(limit) = limit.filter(|limit| native && stack.bytes > *limit) {
+            self.lint(Lint::StackOverflow, format!(
+                "{} needs about {} of stack, but the program gets {}; pass a larger --stack-size",
+                stack.path.join(" -> "), stack::human_size(stack.bytes), stack::human_size(limit)
+            ))?;
+        }
+        Ok(())
+    }
+    
+    /// Report unsafe constructs in the generated code, failing on denied ones
+    ///
+    /// Each finding is traced back to the sentence of `input` it was written for.
+    fn check_constructs(&self, input_path: &Path, input: &str, code: &str) -> Result<()> {
+        let findings = self.options.construct_policy.check(code);
+        let source_map = SourceMap::new(input, code);
+        let spec = input_path.display().to_string();
+        
+        let mut denied = 0;
+        for finding in &findings {
+            let excerpt = source_map.span_for_line(finding.line).map(|span| source_map.render(&spec, span));
+            if finding.level == Level::Deny {
+                match excerpt {
+                    Some(excerpt) => error!("Denied construct in generated code: {}\n{}", finding, excerpt),
+                    None => error!("Denied construct in generated code: {}", finding),
+                }
+                denied += 1;
+            } else {
+                self.lint_at(Lint::UnsafeConstruct, format!("Generated code uses {}", finding), excerpt)?;
+            }
+        }
+        
+        if denied > 0 {
+            return Err(anyhow::anyhow!(
+                "Generated code contains {} denied construct(s). Rewrite the program or relax the policy with --construct-policy.",
+                denied
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Fail if the program starts commands outside `--allow-command`
+    ///
+    /// A command computed at runtime can't be checked, so it counts as not
+    /// allowed. Without an allowlist the commands are only logged.
+    fn check_commands(&self, input_path: &Path, input: &str, code: &str) -> Result<()> {
+        let spawns = commands::spawned(code);
+        let allowed = &self.options.allowed_commands;
+        if allowed.is_empty() {
+            for spawn in &spawns {
+                match &spawn.command {
+                    Some(command) => info!("Program runs {} (line {} of the generated code)", command, spawn.line),
+                    None => info!("Program calls {} without a literal command (line {} of the generated code)", spawn.call, spawn.line),
+                }
+            }
+            return Ok(());
+        }
+        
+        let source_map = SourceMap::new(input, code);
+        let spec = input_path.display().to_string();
+        let mut denied = 0;
+        for spawn in &spawns {
+            let reason = match &spawn.command {
+                Some(command) if allowed.contains(command) => continue,
+                Some(command) => format!("Generated code runs {}, which --allow-command doesn't list", command),
+                None => format!("Generated code calls {} without a literal command, which --allow-command can't check", spawn.call),
+            };
+            match source_map.span_for_line(spawn.line).map(|span| source_map.render(&spec, span)) {
+                Some(excerpt) => error!("{}\n{}", reason, excerpt),
+                None => error!("{} (line {} of the generated code)", reason, spawn.line),
+            }
+            denied += 1;
+        }
+        if denied > 0 {
+            return Err(anyhow::anyhow!(
+                "Generated code starts {} command(s) outside the allowlist. Allow them with --allow-command or rewrite the program.",
+                denied
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Fail if the program introduces capabilities that have not been approved
+    fn check_approval(&self, input_path: &Path, capabilities: &BTreeSet<Capability>) -> Result<()> {
+        let lock_path = Lockfile::path_for(input_path);
+        let mut lockfile = Lockfile::load(&lock_path)?;
+        
+        let unapproved = lockfile.unapproved(input_path, capabilities);
+        if unapproved.is_empty() {
+            return Ok(());
+        }
+        
+        let description = capabilities::describe(&unapproved);
+        lockfile.record_pending(input_path, unapproved);
+        lockfile.save(&lock_path)?;
         
-        // Get the translated code from Gemini
-        let response = self.gemini_client.execute_code(&prompt)?;
+        Err(anyhow::anyhow!(
+            "Program introduces new external interactions: {}. Review the program and run `nhlp approve {}` to allow them.",
+            description,
+            input_path.display()
+        ))
+    }
+    
+    /// Send a prompt to the model, reusing a cached response when there is one and
+    /// logging the exchange when --debug-llm is set
+    ///
+    /// `program` is the spec the offline tier translates when the ladder reaches it.
+    fn ask(&self, stage: &str, prompt: &str, program: Option<&str>, cancel: &CancellationToken) -> Result<Completion> {
+        let key = ResponseCache::key(self.llm.provider(), self.llm.ladder(), prompt);
+        if let Some(completion) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+            info!("Using cached {} response from {}", stage, completion.model);
+            return Ok(completion);
+        }
         
-        // Extract the machine code instructions
-        let code = extract_code_from_response(&response);
+        let result = match &self.transcript {
+            Some(transcript) => {
+                let exchange = transcript.prompt(stage, prompt)?;
+                let result = self.llm.execute_code(prompt, program, self.options.timeout(stage), cancel);
+                transcript.response(exchange, stage, result.as_ref().map(|completion| completion.text.as_str()))?;
+                result
+            }
+            None => self.llm.execute_code(prompt, program, self.options.timeout(stage), cancel),
+        };
         
-        Ok(code)
+        // An offline-tier fallback is a degraded answer and mustn't shadow a real one on the next run
+        let cacheable = result.as_ref().ok().filter(|completion| completion.model != OFFLINE_TIER);
+        if let (Some(cache), Some(completion)) = (&self.cache, cacheable) {
+            if let Err(e) = cache.put(&key, completion) {
+                warn!("Failed to cache {} response: {}", stage, e);
+            }
+        }
+        result
     }
     
-    /// Generate an executable from the machine code
-    fn generate_executable(&self, source_path: &Path, program_name: &str, language: &str) -> Result<String> {
+    /// Generate an executable from the machine code, returning its path and the toolchain used
+    ///
+    /// Unless incremental builds are off, an executable built earlier from the
+    /// same code with the same flags is restored from the `.nhlp-cache`
+    /// directory next to `input_path` instead of invoking the toolchain.
+    fn generate_executable(&self, input_path: &Path, source_path: &Path, program_name: &str, language: &str) -> Result<(String, &'static str)> {
         // Check if we have any compilers available
         if !self.compilers.has_c_compiler() && !self.compilers.rustc {
-            return Err(anyhow::anyhow!(
-                "No compilers found. Please install gcc, clang, or rustc to compile NHLP programs."
-            ));
+            return Err(NhlpError::MissingToolchain { language: "C or Rust", install: "gcc, clang, or rustc" }.into());
         }
         
-        // Get current directory for output path
-        let current_dir = env::current_dir()?;
-        let output_path = current_dir.join(if cfg!(windows) {
-            format!("{}.exe", program_name)
-        } else {
-            program_name.to_string()
-        });
+        // Write next to where nhlp was run unless another directory was given
+        let out_dir = match &self.options.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::current_dir()?,
+        };
+        let output_path = out_dir.join(self.executable_name(program_name));
         
         let output_path_str = output_path.to_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid output path"))?;
+            .ok_or_else(|| NhlpError::InvalidOutputPath(output_path.clone()))?;
+        
+        let link_args = self.link_args(language);
+        
+        // Coverage builds write their notes next to the executable, so they can't be reused
+        if !self.options.incremental || self.options.coverage {
+            let toolchain = self.build(source_path, program_name, language, &output_path, &link_args)?;
+            self.finish_build(&output_path, language)?;
+            return Ok((output_path_str.to_string(), toolchain));
+        }
+        
+        let toolchain = match language {
+            "rust" => "rustc",
+            "c" => self.c_compiler()?.1,
+            _ => return Err(NhlpError::UnsupportedLanguage(language.to_string()).into()),
+        };
+        let artifacts = ArtifactCache::for_spec(input_path);
+        let source = fs::read(source_path)
+            .with_context(|| format!("Failed to read generated source: {:?}", source_path))?;
+        let codegen_args = self.codegen_args(language);
+        let mut build = vec![language, toolchain, program_name, self.options.target.as_deref().unwrap_or_default()];
+        let strip_args = self.options.link.strip_args().unwrap_or_default();
+        build.extend(codegen_args.iter().chain(&link_args).chain(&strip_args).map(String::as_str));
+        let key = ArtifactCache::key(&source, &build);
+        if artifacts.restore(program_name, &key, &output_path) {
+            info!("Generated code is unchanged; reusing the executable from {}", artifacts::DIR_NAME);
+            return Ok((output_path_str.to_string(), toolchain));
+        }
         
-        // Compile based on language
-        let compiler_result = match language {
+        let toolchain = self.build(source_path, program_name, language, &output_path, &link_args)?;
+        self.finish_build(&output_path, language)?;
+        if let Err(e) = artifacts.store(program_name, &key, &output_path) {
+            warn!("Failed to cache the build artifact: {}", e);
+        }
+        Ok((output_path_str.to_string(), toolchain))
+    }
+    
+    /// File name of the executable, or library, built for the configured target
+    fn executable_name(&self, program_name: &str) -> String {
+        if let Some(kind) = self.options.library {
+            return kind.file_name(program_name, self.options.target.as_deref());
+        }
+        match self.options.target.as_deref() {
+            Some(target) if is_wasm_target(target) => format!("{}.wasm", program_name),
+            Some(target) if target.contains("windows") => format!("{}.exe", program_name),
+            None if cfg!(windows) => format!("{}.exe", program_name),
+            _ => program_name.to_string(),
+        }
+    }
+    
+    /// Whether programs built for `target` can run here, natively or under the WASI runtime
+    fn can_run(&self, target: &str) -> bool {
+        is_host_target(target) || (is_wasm_target(target) && self.compilers.wasmtime)
+    }
+    
+    /// Compile a source file into an executable at `output_path`, returning the toolchain used
+    fn build(
+        &self,
+        source_path: &Path,
+        program_name: &str,
+        language: &str,
+        output_path: &Path,
+        extra_args: &[String],
+    ) -> Result<&'static str> {
+        // Pick the toolchain based on language
+        let (mut command, toolchain) = match language {
             "rust" => {
                 // Rust code
                 if !self.compilers.rustc {
-                    return Err(anyhow::anyhow!("Rust compiler not found"));
+                    return Err(NhlpError::MissingToolchain { language: "Rust", install: "rustc" }.into());
                 }
                 
                 info!("Compiling Rust code to native machine code");
-                Command::new("rustc")
+                let mut command = Command::new("rustc");
+                command
                     .arg(source_path)
                     .arg("--crate-name")
                     .arg(program_name)
                     .arg("-o")
-                    .arg(output_path_str)
-                    .status()
-                    .map_err(|e| anyhow::anyhow!("Rustc compiler error: {}", e))
+                    .arg(output_path);
+                if let Some(target) = &self.options.target {
+                    command.arg("--target").arg(rustc_target(target));
+                }
+                (command, "rustc")
             },
             "c" => {
                 // C code
                 info!("Compiling C code to native machine code");
-                if self.compilers.gcc {
-                    Command::new("gcc")
-                        .arg(source_path)
-                        .arg("-o")
-                        .arg(output_path_str)
-                        .status()
-                        .map_err(|e| anyhow::anyhow!("GCC compiler error: {}", e))
-                } else if self.compilers.clang {
-                    Command::new("clang")
-                        .arg(source_path)
-                        .arg("-o")
-                        .arg(output_path_str)
-                        .status()
-                        .map_err(|e| anyhow::anyhow!("Clang compiler error: {}", e))
-                } else {
-                    Err(anyhow::anyhow!("No C compiler found"))
-                }
+                let (mut command, toolchain) = self.c_compiler()?;
+                command
+                    .arg(source_path)
+                    .arg("-o")
+                    .arg(output_path);
+                (command, toolchain)
             },
-            _ => Err(anyhow::anyhow!("Unsupported language: {}", language)),
+            _ => return Err(NhlpError::UnsupportedLanguage(language.to_string()).into()),
         };
         
+        // Instrument the program to record which lines run
+        if self.options.coverage {
+            if self.options.target.as_deref().is_some_and(is_wasm_target) {
+                warn!("Coverage instrumentation is not supported for WebAssembly targets");
+            } else if toolchain == "rustc" {
+                warn!("Coverage instrumentation is only supported for C programs");
+            } else {
+                command.arg("--coverage");
+            }
+        }
+        command.args(self.codegen_args(language));
+        command.args(extra_args);
+        
         // Check compilation result
-        match compiler_result {
-            Ok(status) if status.success() => Ok(output_path_str.to_string()),
-            Ok(status) => Err(anyhow::anyhow!("Machine code compilation failed with status: {}", status)),
-            Err(e) => Err(e),
+        let status = command
+            .status()
+            .map_err(|source| NhlpError::ToolchainUnavailable { toolchain, source })?;
+        if !status.success() {
+            return Err(NhlpError::BuildFailed { toolchain, status }.into());
+        }
+        
+        Ok(toolchain)
+    }
+    
+    /// Write the C header declaring a library's exported functions, and a
+    /// pkg-config file for it, next to the library
+    fn write_library_files(
+        &self,
+        kind: LibraryKind,
+        library_path: &Path,
+        input_path: &Path,
+        program_name: &str,
+        code: &str,
+        language: &str,
+    ) -> Result<()> {
+        let (header, skipped) = library::header(program_name, code, language);
+        if !skipped.is_empty() {
+            warn!("Left out of the header because their types have no C equivalent: {}", skipped.join(", "));
+        }
+        let header_path = library_path.with_file_name(format!("{}.h", program_name));
+        fs::write(&header_path, header)
+            .with_context(|| format!("Failed to write header: {:?}", header_path))?;
+        
+        // A shared library records its own dependencies; callers of a static one link them
+        let mut libs = Vec::new();
+        if kind == LibraryKind::Static {
+            libs.extend(self.options.link.args.iter().cloned());
+            libs.extend(kind.native_libs(language).iter().map(|lib| lib.to_string()));
+        }
+        let dir = fs::canonicalize(library_path.parent().unwrap_or_else(|| Path::new(".")))?;
+        let pc_path = library_path.with_file_name(format!("{}.pc", program_name));
+        fs::write(&pc_path, library::pkg_config(program_name, &dir, &input_path.display().to_string(), &libs))
+            .with_context(|| format!("Failed to write pkg-config file: {:?}", pc_path))?;
+        
+        info!(
+            "Built library {} with header {} and pkg-config file {}",
+            library_path.display(),
+            header_path.display(),
+            pc_path.display()
+        );
+        Ok(())
+    }
+    
+    /// Steps after the toolchain runs: archiving a static C library, then stripping
+    fn finish_build(&self, output_path: &Path, language: &str) -> Result<()> {
+        if self.options.library == Some(LibraryKind::Static) && language == "c" {
+            library::archive(output_path)?;
+        }
+        self.strip(output_path)
+    }
+    
+    /// Strip the linked executable as --strip asks
+    fn strip(&self, executable: &Path) -> Result<()> {
+        let Some(mut args) = self.options.link.strip_args() else {
+            return Ok(());
+        };
+        if self.options.target.as_deref().is_some_and(is_wasm_target) {
+            warn!("--strip does not apply to WebAssembly modules");
+            return Ok(());
+        }
+        // Objects in a static library still need the symbols their callers link against
+        if self.options.library == Some(LibraryKind::Static) {
+            for arg in &mut args {
+                if arg == "--strip-all" {
+                    *arg = "--strip-unneeded".to_string();
+                }
+            }
+        }
+        let (before, after) = linker::strip(executable, &args)?;
+        info!("Stripped {:?}: {} -> {} bytes", executable, before, after);
+        Ok(())
+    }
+    
+    /// Arguments every build of the program gets for CPU features, hardening, and optimization
+    fn codegen_args(&self, language: &str) -> Vec<String> {
+        let mut args = self.options.features.driver_args(language);
+        if self.options.harden && !self.options.target.as_deref().is_some_and(is_wasm_target) {
+            args.extend(hardening::driver_args(language, self.options.library.is_none()));
+        }
+        // After hardening's -O2, so the level asked for wins
+        if let Some(level) = self.options.opt_level {
+            args.extend(level.driver_args(language));
+        }
+        if self.options.debug_info {
+            args.extend(debug_args(language));
+        }
+        if language == "c" && self.program_stack().is_some() {
+            args.push("-pthread".to_string());
+        }
+        args
+    }
+    
+    /// Link arguments for `language`: the link options, then the library output kind
+    fn link_args(&self, language: &str) -> Vec<String> {
+        let mut args = self.options.link.driver_args(language);
+        if let Some(kind) = self.options.library {
+            args.extend(kind.driver_args(language));
+        }
+        args
+    }
+    
+    /// The stack size to build into the program: none for libraries, which
+    /// have no `main`, or for WebAssembly, which has no threads to run it on
+    fn program_stack(&self) -> Option<u64> {
+        let wasm = self.options.target.as_deref().is_some_and(is_wasm_target);
+        self.options.stack_size.filter(|_| self.options.library.is_none() && !wasm)
+    }
+    
+    /// `code` running its `main` on a thread with the stack from `--stack-size`, when one was given
+    fn with_stack_size(&self, code: String, language: &str) -> String {
+        let Some(bytes) = self.program_stack() else {
+            return code;
+        };
+        match stack::with_stack_size(&code, language, bytes) {
+            Some(wrapped) => wrapped,
+            None => {
+                warn!("The program's main has a form --stack-size can't wrap, so only runs through nhlp get the larger stack");
+                code
+            }
+        }
+    }
+    
+    /// The code to build: with `-g`, C code gets `#line` directives so its
+    /// debug info points at the sentences of the spec
+    ///
+    /// rustc has no equivalent, so a Rust program's debug info points at
+    /// the generated code, which is deleted after the build.
+    fn debuggable(&self, input_path: &Path, input: &str, code: &str, language: &str) -> String {
+        if !self.options.debug_info {
+            return code.to_string();
+        }
+        if language != "c" {
+            warn!("-g maps Rust programs to the generated code rather than the spec; only C programs can point at sentences");
+            return code.to_string();
+        }
+        let spec = fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf());
+        SourceMap::new(input, code).with_line_directives(code, &spec.display().to_string())
+    }
+    
+    /// The C compiler command for the configured target, and the toolchain it belongs to
+    ///
+    /// Clang cross-compiles with `--target`; GCC needs a cross compiler named
+    /// after the triple, such as `aarch64-linux-gnu-gcc`.
+    fn c_compiler(&self) -> Result<(Command, &'static str)> {
+        let Some(target) = &self.options.target else {
+            return if self.compilers.gcc {
+                Ok((Command::new("gcc"), "gcc"))
+            } else if self.compilers.clang {
+                Ok((Command::new("clang"), "clang"))
+            } else {
+                Err(NhlpError::MissingToolchain { language: "C", install: "gcc or clang" }.into())
+            };
+        };
+        
+        if self.compilers.clang {
+            let mut command = Command::new("clang");
+            command.arg(format!("--target={}", c_target(target))).args(wasi_sysroot_arg(target));
+            return Ok((command, "clang"));
         }
+        let cross_gcc = format!("{}-gcc", c_target(target));
+        if Command::new(&cross_gcc).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok() {
+            return Ok((Command::new(cross_gcc), "gcc"));
+        }
+        Err(NhlpError::MissingToolchain { language: "cross C", install: "clang or a cross gcc for the target" }.into())
     }
     
-    /// Run the binary executable
-    fn run_binary(&self, path: &str) -> Result<()> {
-        let status = Command::new(path)
+    /// Lower a source file to textual LLVM IR at `output_path`, returning the toolchain used
+    fn emit_ir(&self, source_path: &Path, program_name: &str, language: &str, output_path: &Path) -> Result<&'static str> {
+        let (mut command, toolchain) = match language {
+            "rust" => {
+                if !self.compilers.rustc {
+                    return Err(NhlpError::MissingToolchain { language: "Rust", install: "rustc" }.into());
+                }
+                let mut command = Command::new("rustc");
+                command
+                    .arg(source_path)
+                    .arg("--crate-name")
+                    .arg(program_name)
+                    .arg("--emit=llvm-ir")
+                    .arg("-o")
+                    .arg(output_path);
+                if let Some(target) = &self.options.target {
+                    command.arg("--target").arg(rustc_target(target));
+                }
+                (command, "rustc")
+            },
+            "c" => {
+                // GCC has no LLVM backend, so C needs clang here
+                if !self.compilers.clang {
+                    return Err(NhlpError::MissingToolchain { language: "LLVM IR", install: "clang" }.into());
+                }
+                let mut command = Command::new("clang");
+                if let Some(target) = &self.options.target {
+                    command.arg(format!("--target={}", c_target(target))).args(wasi_sysroot_arg(target));
+                }
+                command
+                    .arg("-S")
+                    .arg("-emit-llvm")
+                    .arg(source_path)
+                    .arg("-o")
+                    .arg(output_path);
+                (command, "clang")
+            },
+            _ => return Err(NhlpError::UnsupportedLanguage(language.to_string()).into()),
+        };
+        command.args(self.options.features.driver_args(language));
+        if let Some(level) = self.options.opt_level {
+            command.args(level.driver_args(language));
+        }
+        if self.options.debug_info {
+            command.args(debug_args(language));
+        }
+        
+        let status = command
+            .status()
+            .map_err(|source| NhlpError::ToolchainUnavailable { toolchain, source })?;
+        if !status.success() {
+            return Err(NhlpError::BuildFailed { toolchain, status }.into());
+        }
+        
+        Ok(toolchain)
+    }
+    
+    /// Compile a source file to a relocatable object at `output_path` without linking,
+    /// returning the toolchain used
+    fn emit_object(&self, source_path: &Path, program_name: &str, language: &str, output_path: &Path) -> Result<&'static str> {
+        let flag = if language == "rust" { "--emit=obj" } else { "-c" };
+        self.build(source_path, program_name, language, output_path, &[flag.to_string()])
+    }
+    
+    /// Compile a source file to assembly at `output_path` without assembling it,
+    /// returning the toolchain used
+    ///
+    /// C compilers annotate the instructions with the variables they touch;
+    /// rustc's output already names the functions and their sections.
+    fn emit_asm(&self, source_path: &Path, program_name: &str, language: &str, output_path: &Path) -> Result<&'static str> {
+        let flags: &[&str] = if language == "rust" { &["--emit=asm"] } else { &["-S", "-fverbose-asm"] };
+        let flags: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        self.build(source_path, program_name, language, output_path, &flags)
+    }
+    
+    /// Run the binary executable, returning its output when it is captured
+    fn run_binary(&self, path: &str) -> Result<Option<ProgramOutput>> {
+        if self.options.capture_output {
+            let stdin = match &self.options.stdin_file {
+                Some(stdin_file) => fs::read_to_string(stdin_file)
+                    .with_context(|| format!("Failed to read stdin file: {:?}", stdin_file))?,
+                None => String::new(),
+            };
+            let output = runner::run_captured(path, &stdin, None)?;
+            print!("{}", output.stdout);
+            eprint!("{}", output.stderr);
+            if !output.success() {
+                self.lint(Lint::ProgramFailed, format!("Program {}", output.outcome()))?;
+            }
+            return Ok(Some(output));
+        }
+        
+        let mut command = runner::command(path);
+        if let Some(stdin_file) = &self.options.stdin_file {
+            let stdin = fs::File::open(stdin_file)
+                .with_context(|| format!("Failed to open stdin file: {:?}", stdin_file))?;
+            command.stdin(stdin);
+        }
+        let status = command
             .status()
             .with_context(|| format!("Failed to execute the compiled program: {}", path))?;
         
-        if !status.success() {
-            warn!("Program exited with non-zero status: {}", status);
+        if runner::crashed_with_segfault(&status) {
+            self.lint(Lint::ProgramFailed, "Program crashed with a segmentation fault; if it recurses deeply, try a larger --stack-size")?;
+        } else if !status.success() {
+            self.lint(Lint::ProgramFailed, format!("Program exited with non-zero status: {}", status))?;
         }
         
-        Ok(())
+        Ok(None)
+    }
+}
+
+/// The spec a precedent was recorded from, as an absolute path when it can be resolved
+fn precedent_spec(input_path: &Path) -> String {
+    fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf()).display().to_string()
+}
+
+/// Record a decision in the compilation log, the progress stream, and the report, so all three agree
+fn decide(progress: &dyn ProgressObserver, decisions: &mut Vec<String>, stage: Stage, message: String) {
+    info!("{}", message);
+    progress.on_decision(&Decision::new(stage, message.clone()));
+    decisions.push(message);
+}
+
+/// Toolchain arguments for full debug info when compiling `language`
+fn debug_args(language: &str) -> Vec<String> {
+    let args: &[&str] = if language == "rust" { &["-C", "debuginfo=2"] } else { &["-g"] };
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Whether `target` is a WebAssembly System Interface target such as wasm32-wasi
+fn is_wasm_target(target: &str) -> bool {
+    target.starts_with("wasm32-wasi")
+}
+
+/// Clang's `--sysroot` for WASI targets, taken from WASI_SYSROOT since clang has no default libc for them
+fn wasi_sysroot_arg(target: &str) -> Option<String> {
+    if !is_wasm_target(target) {
+        return None;
+    }
+    env::var("WASI_SYSROOT").ok().map(|sysroot| format!("--sysroot={}", sysroot))
+}
+
+/// The name rustc knows a target by; it renamed wasm32-wasi to wasm32-wasip1,
+/// and its only riscv64 Linux target is the RV64GC one
+fn rustc_target(target: &str) -> String {
+    if target == "wasm32-wasi" {
+        "wasm32-wasip1".to_string()
+    } else if let Some(rest) = target.strip_prefix("riscv64-") {
+        format!("riscv64gc-{}", rest)
+    } else {
+        target.to_string()
+    }
+}
+
+/// The name clang and cross GCCs know a target by; they spell rustc's
+/// riscv64gc triples as plain riscv64 and take the extensions from -march
+fn c_target(target: &str) -> String {
+    match target.strip_prefix("riscv64gc-") {
+        Some(rest) => format!("riscv64-{}", rest),
+        None => target.to_string(),
+    }
+}
+
+/// Whether binaries built for `target` run on this machine
+fn is_host_target(target: &str) -> bool {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    target.starts_with(env::consts::ARCH) && target.contains(os)
+}
+
+/// The spec a compilation works from, as each stage sees it
+struct Spec<'a> {
+    /// Locates the glossary and lockfile, and names the spec in messages
+    path: &'a Path,
+    program_name: &'a str,
+    text: &'a str,
+}
+
+/// A program made ready to send to the model
+struct PreparedPrompt {
+    /// The program with sensitive values masked
+    description: String,
+    redaction: Redaction,
+    prompt: String,
+    /// Language the prompt asks for ("c" or "rust")
+    language: &'static str,
+}
+
+/// Redact a program and build the prompt translating it for the available toolchains,
+/// defining any glossary terms it uses
+fn redacted_prompt(
+    input: &str,
+    glossary: &Glossary,
+    redact: bool,
+    redact_patterns: &[String],
+    compilers: CompilerInfo,
+) -> Result<PreparedPrompt> {
+    // Determine which language to target based on available compilers
+    let use_rust = !compilers.has_c_compiler() && compilers.rustc;
+    
+    let redactor = Redactor::new(redact_patterns)?;
+    let (mut description, redaction) = if redact {
+        redactor.redact(input)
+    } else {
+        (input.to_string(), Default::default())
+    };
+    if !redaction.is_empty() {
+        description.push_str(
+            "\n\nNote: values like NHLP_REDACTED_1 stand in for confidential values. \
+             Use them verbatim inside string literals.",
+        );
     }
+    
+    let glossary = glossary.prompt_section(input);
+    let (prompt, language) = if use_rust {
+        (build_rust_prompt(&description, &glossary), "rust")
+    } else {
+        (build_c_prompt(&description, &glossary), "c")
+    };
+    Ok(PreparedPrompt { description, redaction, prompt, language })
+}
+
+/// The translation prompt compiling `input` would send, without contacting the model
+pub fn translation_prompt(input: &str, glossary: &Glossary, redact: bool, redact_patterns: &[String]) -> Result<String> {
+    Ok(redacted_prompt(input, glossary, redact, redact_patterns, CompilerInfo::new())?.prompt)
+}
+
+/// Build the prompt translating the natural language program to C code
+fn build_c_prompt(program_description: &str, glossary: &str) -> String {
+    format!(
+        r#"You are the NHLP compiler that translates natural language directly to machine code.
+
+Your task is to translate the following NHLP (Natural High Level Programming Language) program:
+
+---
+NHLP PROGRAM:
+{}
+---
+{}
+IMPORTANT: Generate complete, compilable C code that implements this program exactly as described.
+Include all necessary headers and implement full interactive capabilities.
+Before each function, add a comment quoting the sentence of the NHLP program it implements.
+The code must be surrounded by triple backticks with the language identifier.
+
+RESPOND ONLY WITH THE COMPLETE CODE.
+"#,
+        program_description, glossary
+    )
+}
+
+/// Build the prompt translating the natural language program to Rust code
+fn build_rust_prompt(program_description: &str, glossary: &str) -> String {
+    format!(
+        r#"You are the NHLP compiler that translates natural language directly to machine code.
+
+Your task is to translate the following NHLP (Natural High Level Programming Language) program:
+
+---
+NHLP PROGRAM:
+{}
+---
+{}
+IMPORTANT: Generate complete, compilable Rust code that implements this program exactly as described.
+Include all necessary crates and implement full interactive capabilities.
+Before each function, add a comment quoting the sentence of the NHLP program it implements.
+The code must be surrounded by triple backticks with the language identifier.
+Be sure to handle user input properly and make the code robust.
+Make sure the code is valid Rust that can be compiled with rustc directly.
+Do not use any external crates that need to be added to Cargo.toml - use only the standard library.
+
+RESPOND ONLY WITH THE COMPLETE RUST CODE.
+"#,
+        program_description, glossary
+    )
+}
+
+/// Comment block tracing generated code back to the program it was compiled from
+fn provenance_header(input_path: &Path, input: &str, model: &str, tier: usize) -> String {
+    // Line comments are valid in both C and Rust
+    format!(
+        "// Generated by nhlp {} from {}\n\
+         // Source SHA-256: {}\n\
+         // Model: {} (fallback tier {})\n\n",
+        env!("CARGO_PKG_VERSION"),
+        input_path.display(),
+        report::hash_source(input),
+        model,
+        tier + 1
+    )
 }
 
 /// Create a temporary source file with the appropriate extension
@@ -298,20 +1792,203 @@ fn create_temp_source_file(code: &str, language: &str, program_name: &str) -> Re
 }
 
 /// Extract machine code from the neural compiler response
-fn extract_code_from_response(response: &str) -> String {
+fn extract_code_from_response(response: &str) -> Result<String, NhlpError> {
+    if response.trim().is_empty() {
+        return Err(NhlpError::EmptyResponse);
+    }
+    
     // Find code block between triple backticks
     if let Some(start) = response.find("```") {
-        if let Some(end) = response[start + 3..].find("```") {
-            let block = &response[start + 3..start + 3 + end];
-            
-            // Remove language identifier if present (e.g., ```c)
-            if let Some(newline) = block.find('\n') {
-                return block[newline + 1..].trim().to_string();
-            }
-            return block.trim().to_string();
+        let Some(end) = response[start + 3..].find("```") else {
+            // An opening fence without a closing one means the output limit was hit
+            return Err(NhlpError::TruncatedResponse);
+        };
+        let block = &response[start + 3..start + 3 + end];
+        
+        // Remove language identifier if present (e.g., ```c)
+        let code = match block.find('\n') {
+            Some(newline) => block[newline + 1..].trim(),
+            None => block.trim(),
+        };
+        if code.is_empty() {
+            return Err(NhlpError::EmptyResponse);
         }
+        return Ok(code.to_string());
     }
     
     // If no triple backticks, return the whole response
-    response.to_string()
-} 
\ No newline at end of file
+    Ok(response.to_string())
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::LlmBackend;
+
+    /// A provider serving its only model from `address`, answering with the body's `text`
+    struct Local {
+        address: String,
+    }
+
+    impl LlmBackend for Local {
+        fn name(&self) -> &'static str {
+            "local"
+        }
+
+        fn default_model(&self) -> &'static str {
+            "local-model"
+        }
+
+        fn api_key_var(&self) -> Option<&'static str> {
+            None
+        }
+
+        fn endpoint(&self, _model: &str) -> String {
+            format!("http://{}/generate", self.address)
+        }
+
+        fn models_url(&self) -> String {
+            format!("http://{}/models", self.address)
+        }
+
+        fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+            Vec::new()
+        }
+
+        fn payload(&self, _model: &str, prompt: &str) -> serde_json::Value {
+            serde_json::json!({ "prompt": prompt })
+        }
+
+        fn extract_text(&self, response: &serde_json::Value) -> Result<String> {
+            response["text"].as_str().map(str::to_string).context("No text in response")
+        }
+
+        fn is_local(&self) -> bool {
+            true
+        }
+    }
+
+    /// A compiler asking `ladder` at `address`, caching responses in `cache_dir`
+    fn compiler(address: &str, ladder: &[&str], cache_dir: &Path, options: CompileOptions) -> Compiler {
+        let backend = Box::new(Local { address: address.to_string() });
+        let ladder = ladder.iter().map(|model| model.to_string()).collect();
+        Compiler {
+            llm: Arc::new(LlmClient::new(backend, ladder, false).unwrap()),
+            transcript: None,
+            cache: Some(Arc::new(ResponseCache::in_dir(cache_dir))),
+            compilers: CompilerInfo { gcc: false, clang: false, rustc: false, wasmtime: false },
+            options,
+        }
+    }
+
+    /// Key `ask` caches `prompt` under for `compiler`
+    fn key(compiler: &Compiler, prompt: &str) -> String {
+        ResponseCache::key(compiler.llm.provider(), compiler.llm.ladder(), prompt)
+    }
+
+    #[test]
+    fn stage_timeouts_override_the_request_timeout() {
+        let options = CompileOptions {
+            request_timeout: Duration::from_secs(60),
+            stage_timeouts: CompileOptions::parse_stage_timeouts(&["translate=300".to_string()]).unwrap(),
+            ..CompileOptions::default()
+        };
+        assert_eq!(options.timeout("translate"), Duration::from_secs(300));
+        assert_eq!(options.timeout("explanation"), Duration::from_secs(60));
+        assert!(CompileOptions::parse_stage_timeouts(&["parse=5".to_string()]).is_err());
+        assert!(CompileOptions::parse_stage_timeouts(&["translate".to_string()]).is_err());
+    }
+    
+    #[test]
+    fn cached_response_is_used_without_asking_the_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let compiler = compiler("127.0.0.1:9", &["offline"], dir.path(), CompileOptions::default());
+        let cancel = CancellationToken::new();
+        
+        // Without a spec the offline tier can't answer, so only a cache hit succeeds
+        assert!(compiler.ask("translate", "prompt", None, &cancel).is_err());
+        let cached = Completion { text: "cached".to_string(), model: "earlier".to_string(), tier: 0, egress: Vec::new() };
+        compiler.cache.as_ref().unwrap().put(&key(&compiler, "prompt"), &cached).unwrap();
+        let completion = compiler.ask("translate", "prompt", None, &cancel).unwrap();
+        assert_eq!(completion.text, "cached");
+        assert_eq!(completion.model, "earlier");
+        
+        // A different prompt misses
+        assert!(compiler.ask("translate", "other prompt", None, &cancel).is_err());
+    }
+    
+    #[test]
+    fn offline_tier_responses_are_never_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let compiler = compiler("127.0.0.1:9", &["offline"], dir.path(), CompileOptions::default());
+        let cancel = CancellationToken::new();
+        let completion = compiler.ask("translate", "prompt", Some("Print \"hi\"."), &cancel).unwrap();
+        assert_eq!(completion.model, OFFLINE_TIER);
+        assert!(compiler.cache.as_ref().unwrap().get(&key(&compiler, "prompt")).is_none());
+    }
+    
+    #[cfg(feature = "network")]
+    #[test]
+    fn model_response_is_cached_on_a_miss() {
+        use std::io::{BufRead, BufReader, Read};
+        use std::net::TcpListener;
+        use std::thread;
+        
+        // Answers one request, so a second one reaching the model fails
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            reader.by_ref().take(length).read_to_end(&mut Vec::new()).unwrap();
+            let body = r#"{"text":"generated"}"#;
+            let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+        
+        let dir = tempfile::tempdir().unwrap();
+        let compiler = compiler(&address, &["local-model"], dir.path(), CompileOptions::default());
+        let cancel = CancellationToken::new();
+        let completion = compiler.ask("translate", "prompt", None, &cancel).unwrap();
+        assert_eq!(completion.text, "generated");
+        server.join().unwrap();
+        
+        let cached = compiler.cache.as_ref().unwrap().get(&key(&compiler, "prompt")).unwrap();
+        assert_eq!(cached.text, "generated");
+        assert_eq!(cached.model, "local-model");
+        assert_eq!(compiler.ask("translate", "prompt", None, &cancel).unwrap().text, "generated");
+    }
+    
+    #[test]
+    fn lint_at_follows_the_lint_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let levels = |allow: &[&str], deny: &[&str]| {
+            let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+            LintLevels::from_flags(&names(allow), &[], &names(deny)).unwrap()
+        };
+        let with_lints = |lints| compiler("127.0.0.1:9", &["offline"], dir.path(), CompileOptions { lints, ..CompileOptions::default() });
+        let excerpt = || Some("  system(cmd);".to_string());
+        
+        let allowed = with_lints(levels(&["unsafe-construct"], &[]));
+        assert!(allowed.lint_at(Lint::UnsafeConstruct, "shell call", excerpt()).is_ok());
+        let warned = with_lints(LintLevels::default());
+        assert!(warned.lint_at(Lint::UnsafeConstruct, "shell call", excerpt()).is_ok());
+        
+        let denied = with_lints(levels(&[], &["unsafe-construct"]));
+        let message = denied.lint_at(Lint::UnsafeConstruct, "shell call", excerpt()).unwrap_err().to_string();
+        assert_eq!(message, "NH0001: shell call [unsafe-construct, denied]\n  system(cmd);");
+        assert!(denied.lint_at(Lint::ModelDegraded, "degraded", None).is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use crate::sourcemap::{self, SourceMap};
+
+/// Calls that read or write the terminal, files, or sockets
+const IO_PATTERN: &str = r"\b(?:printf|fprintf|puts|fputs|putchar|scanf|fscanf|fgets|getchar|getline|fread|fwrite|fopen|fclose|fflush|read|write|send|recv)\s*\(|\b(?:println|print|eprintln|eprint|write|writeln)!|\.(?:read_line|read_to_string|write_all|flush)\s*\(";
+
+/// Calls that allocate on the heap
+const ALLOCATION_PATTERN: &str = r"\b(?:malloc|calloc|realloc|strdup|strndup)\s*\(|\b(?:Box|Vec|String|HashMap|BTreeMap|HashSet|BTreeSet)::(?:new|with_capacity|from)\b|\b(?:vec|format)!|\.(?:to_string|to_owned|to_vec|collect)\b";
+
+/// Loop headers
+const LOOP_PATTERN: &str = r"\b(?:for|while|loop)\b";
+
+/// String and character literals, whose contents aren't code
+const LITERAL_PATTERN: &str = r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)'"#;
+
+static IO: LazyLock<Regex> = LazyLock::new(|| Regex::new(IO_PATTERN).expect("valid I/O pattern"));
+static ALLOCATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(ALLOCATION_PATTERN).expect("valid allocation pattern"));
+static LOOP: LazyLock<Regex> = LazyLock::new(|| Regex::new(LOOP_PATTERN).expect("valid loop pattern"));
+static LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(LITERAL_PATTERN).expect("valid literal pattern"));
+
+/// The work the generated code does on behalf of one sentence of the program
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SentenceCost {
+    pub sentence: String,
+    /// Lines of code, excluding blanks and comments
+    pub lines: usize,
+    /// Input/output calls: terminal, files, and sockets
+    pub io_calls: usize,
+    pub allocations: usize,
+    pub loops: usize,
+}
+
+/// Attribute the generated code's lines, I/O calls, allocations, and loops
+/// to the sentence comment preceding them, as the source map reads them
+///
+/// This is a static count of what each sentence turned into, not a profile,
+/// but it's enough to show which sentences produce the most work.
+pub fn analyze(code: &str) -> Vec<SentenceCost> {
+    let source_map = SourceMap::new("", code);
+    let mut sentences: Vec<SentenceCost> = Vec::new();
+    for (index, line) in code.lines().enumerate() {
+        let line = line.trim();
+        // Comments, preprocessor lines, and lone braces aren't work done for a sentence
+        if line.is_empty() || line.starts_with("//") || line.starts_with('#') || line.chars().all(|c| matches!(c, '{' | '}' | ';' | ')')) {
+            continue;
+        }
+        
+        let sentence = source_map.sentence_for_line(index + 1).unwrap_or(sourcemap::UNATTRIBUTED);
+        let position = match sentences.iter().position(|cost| cost.sentence == sentence) {
+            Some(position) => position,
+            None => {
+                sentences.push(SentenceCost {
+                    sentence: sentence.to_string(),
+                    lines: 0,
+                    io_calls: 0,
+                    allocations: 0,
+                    loops: 0,
+                });
+                sentences.len() - 1
+            }
+        };
+        let code = LITERAL.replace_all(line, "\"\"");
+        let current = &mut sentences[position];
+        current.lines += 1;
+        current.io_calls += IO.find_iter(&code).count();
+        current.allocations += ALLOCATION.find_iter(&code).count();
+        current.loops += LOOP.find_iter(&code).count();
+    }
+    sentences
+}
+
+/// Render per-sentence costs as a table, most expensive first
+pub fn render(sentences: &[SentenceCost]) -> String {
+    let mut sorted: Vec<&SentenceCost> = sentences.iter().collect();
+    sorted.sort_by_key(|sentence| std::cmp::Reverse(sentence.lines));
+    
+    let mut output = String::from("Cost by sentence:\n");
+    for sentence in sorted {
+        let _ = writeln!(
+            output,
+            "  {:>4} lines {:>3} I/O {:>3} alloc {:>3} loops  {}",
+            sentence.lines, sentence.io_calls, sentence.allocations, sentence.loops, sentence.sentence
+        );
+    }
+    output.trim_end().to_string()
+}
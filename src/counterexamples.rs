@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::runner;
+
+/// Time each counterexample run may take before it counts as a hang
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An adversarial input proposed for a program
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Counterexample {
+    /// The edge case the input exercises, e.g. "empty list"
+    pub description: String,
+    /// Text fed to the program's standard input
+    pub stdin: String,
+}
+
+/// Result of running a program against one counterexample
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CounterexampleResult {
+    pub description: String,
+    pub stdin: String,
+    pub passed: bool,
+    pub outcome: String,
+}
+
+/// Build the prompt asking for adversarial inputs for a program
+pub fn build_prompt(program_description: &str) -> String {
+    format!(
+        r#"You are testing a program built from the following NHLP (Natural High Level Programming Language) description:
+
+---
+NHLP PROGRAM:
+{}
+---
+
+Propose up to 6 adversarial inputs that probe the edge cases this description implies,
+such as empty input, zero or negative numbers, very long text, or unexpected choices.
+Each input is the complete text typed on standard input, with a newline after each answer.
+
+Respond ONLY with a JSON array surrounded by triple backticks, in this form:
+```json
+[{{"description": "negative balance", "stdin": "-50\n"}}]
+```
+"#,
+        program_description
+    )
+}
+
+/// Parse the model's list of counterexamples
+pub fn parse(code: &str) -> Result<Vec<Counterexample>> {
+    serde_json::from_str(code).with_context(|| "Failed to parse proposed counterexamples")
+}
+
+/// Run the program against each counterexample
+///
+/// A counterexample passes when the program exits successfully within the
+/// time limit; crashes, error statuses, and hangs all fail.
+pub fn run(executable: &str, counterexamples: Vec<Counterexample>) -> Result<Vec<CounterexampleResult>> {
+    counterexamples
+        .into_iter()
+        .map(|counterexample| {
+            let output = runner::run_captured(executable, &counterexample.stdin, Some(RUN_TIMEOUT))?;
+            Ok(CounterexampleResult {
+                description: counterexample.description,
+                stdin: counterexample.stdin,
+                passed: output.success(),
+                outcome: output.outcome(),
+            })
+        })
+        .collect()
+}
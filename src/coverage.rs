@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use std::process::Command;
+
+use crate::sourcemap;
+
+/// How much of the code implementing one sentence of the program ran
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SentenceCoverage {
+    pub sentence: String,
+    pub executable_lines: usize,
+    pub executed_lines: usize,
+}
+
+/// Read the coverage data an instrumented program wrote at exit
+pub fn collect(executable: &Path, source: &Path, toolchain: &str) -> Result<Vec<SentenceCoverage>> {
+    let directory = executable.parent().unwrap_or_else(|| Path::new("."));
+    let data_file = format!(
+        "{}-{}.gcda",
+        executable.file_name().unwrap_or_default().to_string_lossy(),
+        source.file_stem().unwrap_or_default().to_string_lossy()
+    );
+    
+    // Clang writes LLVM's flavor of the gcov format
+    let mut command = if toolchain == "clang" {
+        let mut command = Command::new("llvm-cov");
+        command.arg("gcov");
+        command
+    } else {
+        Command::new("gcov")
+    };
+    
+    let output = command
+        .arg("-t")
+        .arg(&data_file)
+        .current_dir(directory)
+        .output()
+        .with_context(|| "Failed to run gcov to read coverage data")?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "gcov failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    Ok(parse_gcov(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Group gcov line counts by the sentence comment preceding them
+fn parse_gcov(output: &str) -> Vec<SentenceCoverage> {
+    let mut sentences = vec![SentenceCoverage {
+        sentence: sourcemap::UNATTRIBUTED.to_string(),
+        executable_lines: 0,
+        executed_lines: 0,
+    }];
+    let mut in_comment_run = false;
+    
+    for line in output.lines() {
+        // Each line is "count:line number:source"
+        let mut fields = line.splitn(3, ':');
+        let (Some(count), Some(number), Some(source)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if number.trim() == "0" {
+            continue;
+        }
+        
+        let source = source.trim();
+        if let Some(comment) = source.strip_prefix("//") {
+            let comment = comment.trim().trim_matches('"');
+            if is_provenance_comment(comment) {
+                continue;
+            }
+            
+            // Consecutive comment lines describe the same sentence
+            if in_comment_run {
+                let current = sentences.last_mut().expect("sentences always has an entry");
+                current.sentence.push(' ');
+                current.sentence.push_str(comment);
+            } else {
+                sentences.push(SentenceCoverage {
+                    sentence: comment.to_string(),
+                    executable_lines: 0,
+                    executed_lines: 0,
+                });
+            }
+            in_comment_run = true;
+            continue;
+        }
+        in_comment_run = false;
+        
+        let count = count.trim();
+        if count == "-" {
+            continue;
+        }
+        
+        let current = sentences.last_mut().expect("sentences always has an entry");
+        current.executable_lines += 1;
+        if !count.starts_with('#') && !count.starts_with('=') {
+            current.executed_lines += 1;
+        }
+    }
+    
+    sentences.retain(|sentence| sentence.executable_lines > 0);
+    sentences
+}
+
+/// Whether a comment is part of the header nhlp adds to generated code
+pub fn is_provenance_comment(comment: &str) -> bool {
+    comment.starts_with("Generated by nhlp")
+        || comment.starts_with("Source SHA-256:")
+        || comment.starts_with("Model:")
+}
+
+/// Render per-sentence coverage as a table
+pub fn render(sentences: &[SentenceCoverage]) -> String {
+    let mut output = String::from("Coverage by sentence:\n");
+    for sentence in sentences {
+        let percent = 100 * sentence.executed_lines / sentence.executable_lines.max(1);
+        let marker = if sentence.executed_lines == 0 { " [never run]" } else { "" };
+        let _ = writeln!(
+            output,
+            "  {:>3}% ({}/{} lines) {}{}",
+            percent, sentence.executed_lines, sentence.executable_lines, sentence.sentence, marker
+        );
+    }
+    output.trim_end().to_string()
+}
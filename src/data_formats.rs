@@ -0,0 +1,109 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// A mention of a data format, e.g. `the CSV file` or `sales.csv`
+const FORMAT_PATTERN: &str = r"(?i)\b(?P<format>csv|json)\b|\.(?P<extension>csv|json)\b";
+
+/// Columns or fields listed after a format mention, e.g. `with columns name, age and price`
+const FIELDS_PATTERN: &str = r"(?i)\b(?:columns?|fields?|keys?)\s+(?:called\s+|named\s+)?(?P<list>[A-Za-z_]\w*(?:\s*,\s*(?:and\s+)?[A-Za-z_]\w*|\s+and\s+[A-Za-z_]\w*)+)";
+
+static FORMAT: LazyLock<Regex> = LazyLock::new(|| Regex::new(FORMAT_PATTERN).expect("format pattern is valid"));
+static FIELDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(FIELDS_PATTERN).expect("fields pattern is valid"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Csv => write!(f, "CSV"),
+            Format::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Data the spec reads or writes in a structured format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataFormat {
+    pub format: Format,
+    /// Columns or fields the spec names, in order; empty when it names none
+    pub fields: Vec<String>,
+}
+
+/// Structured data formats the spec works with, and the fields it describes for each
+///
+/// Fields are taken from the sentence that mentions the format, so
+/// `read sales.csv with columns date, region and amount` gives three.
+pub fn extract(description: &str) -> Vec<DataFormat> {
+    let mut found: Vec<DataFormat> = Vec::new();
+    for sentence in description.split_inclusive(['.', '\n']).filter(|sentence| !sentence.trim().is_empty()) {
+        for captures in FORMAT.captures_iter(sentence) {
+            let name = captures.name("format").or_else(|| captures.name("extension")).map_or("", |m| m.as_str());
+            let format = if name.eq_ignore_ascii_case("csv") { Format::Csv } else { Format::Json };
+            let fields: Vec<String> = FIELDS
+                .captures(sentence)
+                .map(|fields| split_list(&fields["list"]))
+                .unwrap_or_default();
+            match found.iter_mut().find(|data| data.format == format) {
+                Some(data) if data.fields.is_empty() => data.fields = fields,
+                Some(_) => {}
+                None => found.push(DataFormat { format, fields }),
+            }
+        }
+    }
+    found
+}
+
+/// `name, age and price` as its items
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .flat_map(|item| item.split(" and "))
+        .map(|item| item.trim().trim_start_matches("and ").trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Instructions added to the translation prompt for programs that handle CSV or JSON
+///
+/// Generated programs can't depend on libraries, so the model writes the
+/// parsing itself; these are the cases its first attempts tend to miss.
+pub fn prompt_note(language: &str, formats: &[DataFormat]) -> String {
+    let record = if language == "rust" { "struct" } else { "typedef struct" };
+    let mut note = String::from("\nDATA FORMATS: write the parsing and writing code yourself, using only the standard library.\n");
+    for data in formats {
+        match data.format {
+            Format::Csv => note.push_str(
+                "CSV: treat the first row as a header unless the description says otherwise. Split fields \
+                 following RFC 4180: fields may be quoted, quoted fields may contain commas, newlines, and \
+                 doubled quotes (\"\"), and a trailing \\r ends a line. Skip empty lines. When writing, quote any \
+                 field containing a comma, quote, or newline. \"The third column\" means the third field \
+                 counting from 1.\n",
+            ),
+            Format::Json => note.push_str(
+                "JSON: write a small recursive-descent parser for objects, arrays, strings with escapes \
+                 (including \\uXXXX), numbers, true, false, and null, that rejects trailing garbage. When \
+                 writing, escape quotes, backslashes, and control characters.\n",
+            ),
+        }
+        if !data.fields.is_empty() {
+            note.push_str(&format!(
+                "Read each {} record into a {} with one field per described {}: {}. Choose each field's \
+                 type from how the description uses it, and convert when reading.\n",
+                data.format,
+                record,
+                if data.format == Format::Csv { "column" } else { "field" },
+                data.fields.join(", "),
+            ));
+        }
+    }
+    note.push_str(
+        "Report a malformed record, or a value that doesn't convert to its field's type, with its line \
+         number on stderr and exit with status 1 instead of guessing.\n",
+    );
+    note
+}
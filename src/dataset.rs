@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::capabilities;
+use crate::lockfile::Lockfile;
+
+/// Stage recorded for the capability approvals taken from lockfiles
+const APPROVAL_STAGE: &str = "approval";
+
+/// One prompt/response pair from a --debug-llm transcript
+struct Exchange {
+    stage: String,
+    prompt: Option<String>,
+    response: Option<String>,
+}
+
+/// Convert the transcripts in `dirs` and the approvals in `lockfiles` into chat-format JSONL fine-tuning records
+///
+/// Only exchanges that got a response are exported; failed requests have
+/// nothing to learn from. Each approved spec in a lockfile becomes a record
+/// asking which external interactions the spec needs, answered with what the
+/// reviewer approved. `stages` limits the export to those pipeline stages
+/// when not empty. Returns the number of records written.
+pub fn export(dirs: &[impl AsRef<Path>], lockfiles: &[impl AsRef<Path>], stages: &[String], output: &Path) -> Result<usize> {
+    let mut lines = String::new();
+    let mut records = 0;
+    for dir in dirs {
+        for exchange in read_transcript(dir.as_ref())?.into_values() {
+            let (Some(prompt), Some(response)) = (exchange.prompt, exchange.response) else {
+                continue;
+            };
+            if !stages.is_empty() && !stages.contains(&exchange.stage) {
+                continue;
+            }
+            let record = json!({
+                "messages": [
+                    {"role": "user", "content": prompt},
+                    {"role": "assistant", "content": response},
+                ],
+                "metadata": {"stage": exchange.stage},
+            });
+            lines.push_str(&record.to_string());
+            lines.push('\n');
+            records += 1;
+        }
+    }
+    if stages.is_empty() || stages.iter().any(|stage| stage == APPROVAL_STAGE) {
+        for path in lockfiles {
+            for record in approvals(path.as_ref())? {
+                lines.push_str(&record.to_string());
+                lines.push('\n');
+                records += 1;
+            }
+        }
+    }
+    
+    fs::write(output, lines)
+        .with_context(|| format!("Failed to write dataset: {:?}", output))?;
+    Ok(records)
+}
+
+/// A record for each spec approved in a lockfile, skipping specs no longer next to it
+fn approvals(path: &Path) -> Result<Vec<serde_json::Value>> {
+    if !path.exists() {
+        anyhow::bail!("Lockfile not found: {:?}", path);
+    }
+    let lockfile = Lockfile::load(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut records = Vec::new();
+    for (spec, approved) in lockfile.approvals() {
+        let Ok(source) = fs::read_to_string(dir.join(spec)) else {
+            continue;
+        };
+        let prompt = format!(
+            "Which external interactions (network, file-write, process-spawn) does this program need? \
+             Answer with a comma-separated list, or none.\n\n{}",
+            source
+        );
+        let answer = match approved.is_empty() {
+            true => "none".to_string(),
+            false => capabilities::describe(approved),
+        };
+        records.push(json!({
+            "messages": [
+                {"role": "user", "content": prompt},
+                {"role": "assistant", "content": answer},
+            ],
+            "metadata": {"stage": APPROVAL_STAGE, "spec": spec},
+        }));
+    }
+    Ok(records)
+}
+
+/// Group a transcript directory's files by exchange number
+fn read_transcript(dir: &Path) -> Result<BTreeMap<String, Exchange>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read transcript directory: {:?}", dir))?;
+    
+    let mut exchanges = BTreeMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // Files are named like 001-translate.prompt.txt
+        let Some((id, rest)) = name.strip_suffix(".txt").and_then(|stem| stem.split_once('-')) else {
+            continue;
+        };
+        let Some((stage, kind)) = rest.rsplit_once('.') else {
+            continue;
+        };
+        
+        let exchange = exchanges.entry(id.to_string()).or_insert_with(|| Exchange {
+            stage: stage.to_string(),
+            prompt: None,
+            response: None,
+        });
+        match kind {
+            "prompt" => exchange.prompt = Some(fs::read_to_string(&path)?),
+            "response" => exchange.response = Some(fs::read_to_string(&path)?),
+            _ => {}
+        }
+    }
+    Ok(exchanges)
+}
@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::interpreter::{Machine, Value};
+use crate::trace::{Condition, Trace};
+
+const HELP: &str = "Commands:
+  step, s            run the next sentence
+  continue, c        run until a breakpoint or the end
+  break, b LINE|TEXT stop before the sentences on a line, or the first sentence containing TEXT
+  delete, d LINE     remove the breakpoint on a line
+  list, l            show the sentences, the next one, and breakpoints
+  print, p [NAME]    show a variable, or every variable, with its type
+  set NAME VALUE     change a variable's value
+  watch [NAME]       report every change to a variable, or list the watched ones
+  unwatch NAME       stop watching a variable
+  restart            start the program over
+  quit, q            leave (or press Ctrl-D)";
+
+const REPLAY_HELP: &str = "Commands:
+  step, s [N]        replay the next step, or the next N
+  back, rs [N]       undo the last step, or the last N
+  goto N             go to just after step N (0 is the start)
+  when NAME OP VALUE go to the step that first made a condition true, e.g. when total < 0
+  when NAME          go to the next step that changes a variable
+  list, l            show the sentences and the next one
+  print, p [NAME]    show a variable, or every variable, with its type
+  quit, q            leave (or press Ctrl-D)";
+
+/// Step through a spec with the interpreter, reading commands from the terminal
+///
+/// The program's input comes from `stdin_file`, or from the terminal, where
+/// the program's prompts and the debugger's share lines. With `record`, the
+/// last run's steps are written there as a trace when the session ends.
+pub fn run(input_path: &Path, stdin_file: Option<&Path>, record: Option<&Path>) -> Result<()> {
+    let program = fs::read_to_string(input_path)
+        .with_context(|| format!("Failed to read input file: {:?}", input_path))?;
+    let start = || -> Result<Machine<Box<dyn BufRead>, io::Stdout>> {
+        let input: Box<dyn BufRead> = match stdin_file {
+            Some(path) => Box::new(BufReader::new(
+                fs::File::open(path).with_context(|| format!("Failed to open stdin file: {:?}", path))?,
+            )),
+            None => Box::new(TerminalInput::default()),
+        };
+        let mut machine = Machine::new(&program, input, io::stdout())?;
+        if record.is_some() {
+            machine.record();
+        }
+        Ok(machine)
+    };
+    let mut machine = start()?;
+    let mut breakpoints = BTreeSet::new();
+    let mut finished = false;
+    
+    println!("Debugging {:?}: {} sentence(s). Type help for commands.", input_path, machine.sentences().len());
+    show_next(&machine);
+    loop {
+        print!("(nhlp) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        
+        match command {
+            "" => continue,
+            "quit" | "q" => break,
+            "help" | "h" => println!("{}", HELP),
+            "step" | "s" | "next" | "n" if finished => println!("The program has finished; restart to run it again"),
+            "step" | "s" | "next" | "n" => {
+                finished = !advance(&mut machine)?;
+                if !finished {
+                    show_next(&machine);
+                }
+            }
+            "continue" | "c" if finished => println!("The program has finished; restart to run it again"),
+            "continue" | "c" => loop {
+                if !advance(&mut machine)? {
+                    finished = true;
+                    break;
+                }
+                let next = &machine.sentences()[machine.position()];
+                if breakpoints.contains(&next.line) {
+                    println!("Breakpoint, line {}", next.line);
+                    show_next(&machine);
+                    break;
+                }
+            },
+            "break" | "b" => match breakpoint_line(&machine, argument) {
+                Some(line) => {
+                    breakpoints.insert(line);
+                    println!("Breakpoint on line {}", line);
+                }
+                None => println!("No sentence on that line or containing that text"),
+            },
+            "delete" | "d" => match argument.parse::<usize>() {
+                Ok(line) if breakpoints.remove(&line) => println!("Removed the breakpoint on line {}", line),
+                _ => println!("No breakpoint on that line"),
+            },
+            "list" | "l" => {
+                for (index, sentence) in machine.sentences().iter().enumerate() {
+                    let marker = if index == machine.position() { "=>" } else { "  " };
+                    let stop = if breakpoints.contains(&sentence.line) { "*" } else { " " };
+                    println!("{}{}{:>4}  {}", marker, stop, sentence.line, sentence.text);
+                }
+            }
+            "print" | "p" if argument.is_empty() => {
+                if machine.variables().is_empty() {
+                    println!("No variables have a value yet");
+                }
+                for (name, value) in machine.variables() {
+                    println!("{}", describe(name, value));
+                }
+            }
+            "print" | "p" => match machine.variables().get(argument) {
+                Some(value) => println!("{}", describe(argument, value)),
+                None => println!("{} has no value yet", argument),
+            },
+            "set" => {
+                let (name, value) = argument.split_once(' ').unwrap_or((argument, ""));
+                match machine.set_variable(name, value.trim()) {
+                    Ok(()) => println!("{}", describe(name, &machine.variables()[name])),
+                    Err(e) => println!("error: {:#}", e),
+                }
+            }
+            "watch" if argument.is_empty() => {
+                if machine.watched().is_empty() {
+                    println!("No variables are watched");
+                }
+                for name in machine.watched() {
+                    println!("{}", name);
+                }
+            }
+            "watch" => match machine.watch(argument) {
+                Ok(()) => println!("Watching {}", argument),
+                Err(e) => println!("error: {:#}", e),
+            },
+            "unwatch" if machine.unwatch(argument) => println!("Stopped watching {}", argument),
+            "unwatch" => println!("{} isn't watched", argument),
+            "restart" => {
+                let watched = machine.watched().clone();
+                machine = start()?;
+                for name in &watched {
+                    machine.watch(name)?;
+                }
+                finished = false;
+                show_next(&machine);
+            }
+            _ => println!("Unknown command {}; type help", command),
+        }
+    }
+    
+    if let (Some(path), Some(steps)) = (record, machine.trace()) {
+        Trace::new(input_path, machine.sentences(), steps.to_vec()).save(path)?;
+        println!("Recorded {} step(s) to {:?}; replay them with nhlp debug --replay", steps.len(), path);
+    }
+    Ok(())
+}
+
+/// The program's view of the terminal, taking a line at a time so the
+/// debugger can read its own commands between the program's reads
+#[derive(Default)]
+struct TerminalInput {
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl Read for TerminalInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl BufRead for TerminalInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.line.len() {
+            self.line.clear();
+            self.consumed = 0;
+            io::stdin().lock().read_until(b'\n', &mut self.line)?;
+        }
+        Ok(&self.line[self.consumed..])
+    }
+    
+    fn consume(&mut self, amount: usize) {
+        self.consumed = (self.consumed + amount).min(self.line.len());
+    }
+}
+
+/// Run the next sentence, returning false once the program has finished
+///
+/// A runtime error ends the program the way it would end the native build.
+fn advance<R: BufRead, W: Write>(machine: &mut Machine<R, W>) -> Result<bool> {
+    match machine.step() {
+        Ok(true) if machine.position() < machine.sentences().len() => return Ok(true),
+        Ok(_) => println!("The program finished"),
+        Err(e) => println!("The program stopped: {:#}", e),
+    }
+    Ok(false)
+}
+
+fn show_next<R: BufRead, W: Write>(machine: &Machine<R, W>) {
+    if let Some(sentence) = machine.sentences().get(machine.position()) {
+        println!("=> line {}: {}", sentence.line, sentence.text);
+    }
+}
+
+/// The line to stop on for `break LINE` or `break TEXT`
+fn breakpoint_line<R: BufRead, W: Write>(machine: &Machine<R, W>, argument: &str) -> Option<usize> {
+    let sentences = machine.sentences();
+    if let Ok(line) = argument.parse::<usize>() {
+        return sentences.iter().any(|sentence| sentence.line == line).then_some(line);
+    }
+    let needle = argument.to_lowercase();
+    if needle.is_empty() {
+        return None;
+    }
+    sentences.iter().find(|sentence| sentence.text.to_lowercase().contains(&needle)).map(|sentence| sentence.line)
+}
+
+/// A variable's value and its inferred type
+fn describe(name: &str, value: &Value) -> String {
+    match value {
+        Value::Number(_) => format!("{} = {} (number)", name, value),
+        Value::Text(text) => format!("{} = {:?} (text)", name, text),
+        Value::List(_) => format!("{} = {} (list)", name, value),
+    }
+}
+
+/// Walk through a recorded trace forwards and backwards, reading commands from the terminal
+pub fn replay(trace_path: &Path) -> Result<()> {
+    let trace = Trace::load(trace_path)?;
+    let total = trace.steps.len();
+    let mut position = 0;
+    
+    println!("Replaying {} step(s) of {:?}. Type help for commands.", total, trace.source);
+    show_replay_next(&trace, position);
+    loop {
+        print!("(nhlp replay) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        let count = || if argument.is_empty() { Some(1) } else { argument.parse::<usize>().ok() };
+        
+        match command {
+            "" => continue,
+            "quit" | "q" => return Ok(()),
+            "help" | "h" => println!("{}", REPLAY_HELP),
+            "step" | "s" | "next" | "n" => match count() {
+                Some(_) if position == total => println!("At the end of the trace"),
+                Some(count) => {
+                    for index in position..(position + count).min(total) {
+                        show_step(&trace, index);
+                    }
+                    position = (position + count).min(total);
+                    show_replay_next(&trace, position);
+                }
+                None => println!("Expected a number of steps"),
+            },
+            "back" | "rs" | "reverse-step" => match count() {
+                Some(_) if position == 0 => println!("At the start of the trace"),
+                Some(count) => {
+                    position = position.saturating_sub(count);
+                    println!("Back to step {}/{}", position, total);
+                    show_replay_next(&trace, position);
+                }
+                None => println!("Expected a number of steps"),
+            },
+            "goto" => match argument.parse::<usize>() {
+                Ok(step) if step <= total => {
+                    position = step;
+                    println!("At step {}/{}", position, total);
+                    show_replay_next(&trace, position);
+                }
+                _ => println!("Expected a step from 0 to {}", total),
+            },
+            "when" if argument.is_empty() => println!("Expected a condition, e.g. when total < 0, or a variable name"),
+            "when" => {
+                let found = match Condition::parse(argument) {
+                    Some(condition) => trace.first_time(&condition),
+                    None => trace.next_change(position, argument),
+                };
+                match found {
+                    Some(step) => {
+                        position = step;
+                        show_step(&trace, step - 1);
+                        show_replay_next(&trace, position);
+                    }
+                    None => println!("No step makes that happen"),
+                }
+            }
+            "list" | "l" => {
+                let next = trace.steps.get(position).and_then(|step| step.sentence);
+                for (index, sentence) in trace.sentences.iter().enumerate() {
+                    let marker = if Some(index) == next { "=>" } else { "  " };
+                    println!("{}{:>4}  {}", marker, sentence.line, sentence.text);
+                }
+            }
+            "print" | "p" => {
+                let variables = trace.state(position);
+                if argument.is_empty() {
+                    if variables.is_empty() {
+                        println!("No variables have a value yet");
+                    }
+                    for (name, value) in &variables {
+                        println!("{}", describe(name, value));
+                    }
+                } else {
+                    match variables.get(argument) {
+                        Some(value) => println!("{}", describe(argument, value)),
+                        None => println!("{} has no value yet", argument),
+                    }
+                }
+            }
+            _ => println!("Unknown command {}; type help", command),
+        }
+    }
+}
+
+/// What step `index` ran, read, printed, and changed
+fn show_step(trace: &Trace, index: usize) {
+    let step = &trace.steps[index];
+    match step.sentence.map(|sentence| &trace.sentences[sentence]) {
+        Some(sentence) => println!("step {}: line {}: {}", index + 1, sentence.line, sentence.text),
+        None => println!("step {}: set from the debugger", index + 1),
+    }
+    if let Some(input) = &step.input {
+        println!("  read {:?}", input);
+    }
+    for line in step.output.lines() {
+        println!("  | {}", line);
+    }
+    for change in &step.changes {
+        println!("  {}", describe(&change.name, &change.value));
+    }
+    if let Some(error) = &step.error {
+        println!("  stopped: {}", error);
+    }
+}
+
+fn show_replay_next(trace: &Trace, position: usize) {
+    match trace.steps.get(position) {
+        Some(step) => match step.sentence.map(|sentence| &trace.sentences[sentence]) {
+            Some(sentence) => println!("=> line {}: {}", sentence.line, sentence.text),
+            None => println!("=> a value set from the debugger"),
+        },
+        None => println!("End of the trace"),
+    }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::fmt;
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use tempfile::NamedTempFile;
+
+use crate::backend::LlmBackend;
+use crate::runner;
+
+/// How healthy one part of the environment is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Usable, but some features won't work
+    Warn,
+    /// Compilation can't succeed until this is fixed
+    Fail,
+}
+
+/// Outcome of one environment check
+#[derive(Serialize)]
+#[serde(tag = "type", rename = "check")]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    pub fn problem(name: &'static str, status: Status, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = match self.status {
+            Status::Ok => "ok  ",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        write!(f, "[{}] {}: {}", marker, self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check everything a compilation depends on
+pub fn run(backend: &dyn LlmBackend) -> Vec<Check> {
+    let mut checks = vec![check_api_key(backend)];
+    checks.extend(check_provider(backend));
+    checks.push(check_native_compilers());
+    checks.push(tool_check("linker", "cc", &["--version"], Status::Fail, "Install a C toolchain (e.g. build-essential or Xcode command line tools)"));
+    checks.push(tool_check("gcov", "gcov", &["--version"], Status::Warn, "Install gcov (ships with gcc) to use --coverage"));
+    checks.push(tool_check("wasm runtime", runner::WASM_RUNTIME, &["--version"], Status::Warn, "Install wasmtime to run programs built with --target wasm32-wasi"));
+    checks.push(check_writable("output directory", env::current_dir().ok()));
+    checks.push(check_writable("temp directory", Some(env::temp_dir())));
+    checks
+}
+
+/// Whether the provider's API key is configured, or not needed
+fn check_api_key(backend: &dyn LlmBackend) -> Check {
+    if env::var("DSHPC_DEMO_MODE").unwrap_or_default() == "1" {
+        return Check::ok("api key", "not needed in demo mode");
+    }
+    let Some(var) = backend.api_key_var() else {
+        return Check::ok("api key", format!("not needed for {}", backend.name()));
+    };
+    match env::var(var) {
+        Ok(key) if !key.trim().is_empty() => Check::ok("api key", format!("{} is set", var)),
+        _ => Check::problem(
+            "api key",
+            Status::Fail,
+            format!("{} is not set", var),
+            format!("Export {} or add it to a .env file; set DSHPC_DEMO_MODE=1 to try nhlp without one", var),
+        ),
+    }
+}
+
+/// Whether the provider is reachable and accepts the configured key
+#[cfg(feature = "network")]
+fn check_provider(backend: &dyn LlmBackend) -> Option<Check> {
+    let key = match backend.api_key_var() {
+        Some(var) => env::var(var).ok().filter(|key| !key.trim().is_empty())?,
+        None => String::new(),
+    };
+    Some(match crate::llm::check_api_key(backend, &key) {
+        Ok(()) => Check::ok("provider", format!("{} reachable at {}", backend.name(), backend.models_url())),
+        Err(e) => Check::problem(
+            "provider",
+            Status::Fail,
+            e.to_string(),
+            format!("Check your network connection, that {} is reachable, and that the key is valid", backend.models_url()),
+        ),
+    })
+}
+
+#[cfg(not(feature = "network"))]
+fn check_provider(_backend: &dyn LlmBackend) -> Option<Check> {
+    Some(Check::problem(
+        "provider",
+        Status::Warn,
+        "built without network support; only demo mode and the offline tier work",
+        "Rebuild with the default `network` feature",
+    ))
+}
+
+/// Whether any toolchain that can build generated code is installed
+fn check_native_compilers() -> Check {
+    let found: Vec<String> = ["gcc", "clang", "rustc"]
+        .into_iter()
+        .filter_map(|tool| version(tool, &["--version"]).map(|v| format!("{} ({})", tool, v)))
+        .collect();
+    if found.is_empty() {
+        Check::problem(
+            "native compilers",
+            Status::Fail,
+            "none of gcc, clang, or rustc found",
+            "Install gcc or clang (preferred) or rustc and make sure it is on PATH",
+        )
+    } else {
+        Check::ok("native compilers", found.join(", "))
+    }
+}
+
+/// Whether an external tool runs
+fn tool_check(name: &'static str, tool: &str, args: &[&str], missing: Status, fix: &str) -> Check {
+    match version(tool, args) {
+        Some(version) => Check::ok(name, version),
+        None => Check::problem(name, missing, format!("{} not found", tool), fix),
+    }
+}
+
+/// First line of a tool's version output, if the tool runs
+fn version(tool: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    let first_line = String::from_utf8_lossy(&text).lines().next().unwrap_or_default().trim().to_string();
+    Some(first_line)
+}
+
+/// Whether a directory nhlp writes to accepts new files
+fn check_writable(name: &'static str, dir: Option<std::path::PathBuf>) -> Check {
+    let Some(dir) = dir else {
+        return Check::problem(name, Status::Fail, "could not be determined", "Run nhlp from an existing directory");
+    };
+    match NamedTempFile::new_in(&dir) {
+        Ok(_) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::problem(
+            name,
+            Status::Fail,
+            format!("cannot write to {}: {}", dir.display(), e),
+            "Fix the directory's permissions or run nhlp from a writable directory",
+        ),
+    }
+}
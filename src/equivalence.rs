@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::counterexamples::Counterexample;
+use crate::runner::{self, ProgramOutput};
+
+/// Time each run may take before it counts as a hang
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An input on which unoptimized and optimized builds behaved differently
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Divergence {
+    pub description: String,
+    pub stdin: String,
+    pub unoptimized: ProgramOutput,
+    pub optimized: ProgramOutput,
+}
+
+/// Outcome of comparing unoptimized and optimized builds
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EquivalenceReport {
+    pub inputs_checked: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Run both builds on every input and collect where their behavior differs
+pub fn compare(unoptimized: &str, optimized: &str, inputs: &[Counterexample]) -> Result<EquivalenceReport> {
+    let mut report = EquivalenceReport::default();
+    
+    for input in inputs {
+        let unoptimized_output = runner::run_captured(unoptimized, &input.stdin, Some(RUN_TIMEOUT))?;
+        let optimized_output = runner::run_captured(optimized, &input.stdin, Some(RUN_TIMEOUT))?;
+        report.inputs_checked += 1;
+        
+        // Hangs in both builds are equivalent even though their partial output may differ
+        let equivalent = if unoptimized_output.timed_out || optimized_output.timed_out {
+            unoptimized_output.timed_out == optimized_output.timed_out
+        } else {
+            unoptimized_output.stdout == optimized_output.stdout
+                && unoptimized_output.exit_code == optimized_output.exit_code
+        };
+        
+        if !equivalent {
+            report.divergences.push(Divergence {
+                description: input.description.clone(),
+                stdin: input.stdin.clone(),
+                unoptimized: unoptimized_output,
+                optimized: optimized_output,
+            });
+        }
+    }
+    
+    Ok(report)
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use thiserror::Error;
+
+/// Ways the compilation pipeline can fail on malformed model output or a broken toolchain
+///
+/// Each variant has a stable code in the `nhlp explain` catalog.
+#[derive(Error, Debug)]
+pub enum NhlpError {
+    #[error("NH0101: The model returned an empty response")]
+    EmptyResponse,
+    
+    #[error("NH0102: The model's response was cut off inside a code block. Shorten the program or split it into smaller parts.")]
+    TruncatedResponse,
+    
+    #[error("NH0103: No {language} compiler found. Please install {install} to compile NHLP programs.")]
+    MissingToolchain {
+        language: &'static str,
+        install: &'static str,
+    },
+    
+    #[error("NH0104: Unsupported language: {0}")]
+    UnsupportedLanguage(String),
+    
+    #[error("NH0105: Failed to start {toolchain}: {source}")]
+    ToolchainUnavailable {
+        toolchain: &'static str,
+        source: std::io::Error,
+    },
+    
+    #[error("NH0106: {toolchain} failed to build the generated code ({status})")]
+    BuildFailed {
+        toolchain: &'static str,
+        status: ExitStatus,
+    },
+    
+    #[error("NH0107: Output path is not valid UTF-8: {0:?}")]
+    InvalidOutputPath(PathBuf),
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::llm::{MAX_OUTPUT_TOKENS, OFFLINE_TIER};
+use crate::report::CompilationReport;
+
+/// Rough characters per token for English prose and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Generated code is typically several times longer than the sentences describing it
+const OUTPUT_EXPANSION: usize = 4;
+
+/// Assumed time to first token when no history is available
+const NOMINAL_LATENCY: Duration = Duration::from_millis(1500);
+
+/// Assumed generation speed when no history is available
+const NOMINAL_TOKENS_PER_SECOND: u64 = 100;
+
+/// Published prices in US dollars per million input and output tokens
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("claude-3-5-haiku-latest", 0.80, 4.00),
+    ("claude-3-5-sonnet-latest", 3.00, 15.00),
+    (OFFLINE_TIER, 0.0, 0.0),
+];
+
+/// Expected cost and duration of translating a program
+pub struct Estimate {
+    pub model: String,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// Cost in US dollars, if the model's price is known
+    pub cost: Option<f64>,
+    pub duration: Duration,
+    /// Number of past requests the duration is based on, 0 for the nominal guess
+    pub history_requests: usize,
+}
+
+impl Estimate {
+    /// Estimate translating `spec` with `prompt` on `model`, timing from the
+    /// requests recorded in `history` when there are any
+    pub fn new(spec: &str, prompt: &str, model: &str, history: &[CompilationReport]) -> Self {
+        let input_tokens = tokens(prompt);
+        let output_tokens = (tokens(spec) * OUTPUT_EXPANSION).min(MAX_OUTPUT_TOKENS);
+        let cost = PRICES
+            .iter()
+            .find(|(name, _, _)| *name == model)
+            .map(|(_, input_price, output_price)| {
+                (input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0
+            });
+        
+        let past: Vec<u128> = history
+            .iter()
+            .flat_map(|report| &report.egress)
+            .filter(|record| record.outcome == "ok")
+            .map(|record| record.duration_ms)
+            .collect();
+        let duration = if past.is_empty() {
+            NOMINAL_LATENCY + Duration::from_secs(output_tokens as u64 / NOMINAL_TOKENS_PER_SECOND)
+        } else {
+            Duration::from_millis((past.iter().sum::<u128>() / past.len() as u128) as u64)
+        };
+        
+        Self {
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            cost,
+            duration,
+            history_requests: past.len(),
+        }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Model: {}", self.model)?;
+        writeln!(f, "Input tokens: ~{}", self.input_tokens)?;
+        writeln!(f, "Output tokens: ~{}", self.output_tokens)?;
+        match self.cost {
+            Some(cost) => writeln!(f, "Cost: ~${:.4}", cost)?,
+            None => writeln!(f, "Cost: unknown (no price on record for {})", self.model)?,
+        }
+        if self.history_requests > 0 {
+            write!(f, "Time: ~{:.1?} (average of {} past request(s))", self.duration, self.history_requests)
+        } else {
+            write!(f, "Time: ~{:.1?} (nominal; pass --history reports for a better estimate)", self.duration)
+        }
+    }
+}
+
+/// Approximate token count of a text
+fn tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+
+/// Detailed description of one diagnostic code, printed by `nhlp explain`
+pub struct Explanation {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// What the diagnostic means and why it is raised
+    pub description: &'static str,
+    /// Spec text that raises it
+    pub example: &'static str,
+    /// How to rewrite the spec or change the environment so it goes away
+    pub rewrite: &'static str,
+}
+
+/// Every diagnostic code, in code order
+///
+/// NH00xx codes are lints and can be set to allow, warn, or deny; NH01xx
+/// codes are errors that always stop compilation. Codes are never reused or
+/// renumbered, so retired diagnostics keep their entry.
+pub const CATALOG: &[Explanation] = &[
+    Explanation {
+        code: "NH0001",
+        name: "unsafe-construct",
+        summary: "Generated code uses a construct the construct policy warns about",
+        description: "After translation, the generated code is scanned for constructs such as \
+raw pointers, inline assembly, unchecked indexing, or calls to external programs. Constructs the construct \
+policy sets to warn raise this lint; constructs it denies fail the build outright.",
+        example: "Read the user's name and copy it into a greeting buffer, then print the greeting.",
+        rewrite: "State the limits the model should respect so it has no reason to reach for the construct:\n\
+\n    Read the user's name, keeping at most 50 characters, and print \"Hello, {name}!\".\n\
+\nOr set the construct's level with --construct-policy if it is acceptable for this program.",
+    },
+    Explanation {
+        code: "NH0002",
+        name: "model-degraded",
+        summary: "The translation came from a fallback model in the ladder",
+        description: "The first model in the model ladder failed or was unavailable, so a later, \
+usually weaker, model produced the code. The program may still be correct, but it was not \
+written by the model you intended.",
+        example: "Any spec can raise this; it depends on the provider, not on the text.",
+        rewrite: "Check the provider's status and your API quota, then compile again. Deny this lint \
+in CI (`--deny model-degraded`) to refuse builds from fallback models.",
+    },
+    Explanation {
+        code: "NH0003",
+        name: "no-acceptance-criteria",
+        summary: "--check-acceptance found no criteria in the spec",
+        description: "--check-acceptance runs the program against the input/output examples the \
+spec states. The spec had none, so nothing was checked.",
+        example: "Ask for a number and print its square.",
+        rewrite: "Add criteria in the \"when run with ... it prints ...\" form:\n\
+\n    Ask for a number and print its square.\n    When run with input 5 it prints 25.",
+    },
+    Explanation {
+        code: "NH0004",
+        name: "counterexample-failed",
+        summary: "A model-proposed edge-case input made the program fail",
+        description: "--counterexamples asks the model for inputs likely to break the program, such \
+as empty input, zero, or very large numbers, and runs each one. One of them made the program \
+crash, hang, or exit with an error.",
+        example: "Ask for two numbers and print the first divided by the second.",
+        rewrite: "Say what should happen for the failing input:\n\
+\n    Ask for two numbers and print the first divided by the second.\n    \
+If the second number is zero, print \"Cannot divide by zero\" instead.",
+    },
+    Explanation {
+        code: "NH0005",
+        name: "program-failed",
+        summary: "The program crashed, timed out, or exited with a non-zero status",
+        description: "The compiled program ran but did not finish cleanly. Crashes from deep \
+recursion show up as a segmentation fault; programs waiting for input that never comes show up \
+as a timeout.",
+        example: "Compute the factorial of the number the user enters by calling itself.",
+        rewrite: "Bound the work the spec asks for, or describe the loop instead of the recursion:\n\
+\n    Ask for a whole number up to 20 and print its factorial, computed with a loop.\n\
+\nIf the program legitimately needs more stack, pass --stack-size; if it reads input, pass \
+--stdin-file with a file of test input.",
+    },
+    Explanation {
+        code: "NH0006",
+        name: "uncovered-sentence",
+        summary: "--coverage found a sentence whose code never ran",
+        description: "The code generated for a sentence of the spec was never executed during the \
+run, so nothing showed that the sentence was implemented correctly.",
+        example: "Ask for an age. If the age is over 120, print \"That seems unlikely\".",
+        rewrite: "Run with input that takes the branch (--stdin-file), or add acceptance criteria that \
+exercise it:\n\
+\n    When run with input 130 it prints That seems unlikely.",
+    },
+    Explanation {
+        code: "NH0007",
+        name: "unhandled-signal",
+        summary: "The spec describes reacting to a signal, but the generated code installs no handler",
+        description: "The spec says what to do on Ctrl-C, a termination request, or a hangup, but the \
+generated code never calls sigaction or signal, so the signal ends the program at once instead of \
+letting it save or clean up.",
+        example: "Count the lines the user types. On Ctrl-C, print the count.",
+        rewrite: "State the reaction as its own sentence so the model treats it as required, then \
+compile again:\n\
+\n    When the user presses Ctrl-C, print the count and exit cleanly.",
+    },
+    Explanation {
+        code: "NH0008",
+        name: "unbounded-recursion",
+        summary: "The generated code recurses, so no stack size is known to be enough",
+        description: "A function in the generated code calls itself, directly or through other \
+functions. How deep it goes depends on the input, so the stack estimate can't bound it, and a large \
+enough input overflows the stack and crashes the program with a segmentation fault.",
+        example: "Compute the factorial of the number the user enters by calling itself.",
+        rewrite: "Bound the input, or ask for a loop instead of recursion:\n\
+\n    Ask for a whole number up to 20 and print its factorial, computed with a loop.\n\
+\nIf the recursion is deliberate and its depth is known, pass --stack-size with room for it and \
+allow the lint.",
+    },
+    Explanation {
+        code: "NH0009",
+        name: "stack-overflow",
+        summary: "The generated code's deepest call chain needs more stack than the program gets",
+        description: "nhlp estimates the stack each function needs from its parameters, locals, and \
+fixed-size arrays, and adds them up along the deepest chain of calls from main. That chain needs \
+more than the program's stack limit, so it will crash when it gets there. The estimate leaves out \
+what the compiler adds, so the real need is higher still.",
+        example: "Read up to 10 million numbers into an array and print them sorted.",
+        rewrite: "Say that large data belongs on the heap:\n\
+\n    Read up to 10 million numbers into a list that grows as needed, and print them sorted.\n\
+\nOr pass a larger --stack-size.",
+    },
+    Explanation {
+        code: "NH0010",
+        name: "unsafe-arithmetic",
+        summary: "Arithmetic in the spec may divide by zero, or always overflows",
+        description: "For specs in the offline vocabulary, nhlp works out the range of values every \
+variable can hold and checks each quotient and remainder against it. A divisor that can be zero \
+makes a quotient infinite or NaN and stops the program at a remainder. Numbers read from input \
+can be anything, so a divisor read from the user is never proven safe. Arithmetic on known values \
+that always overflows to infinity raises this lint too.",
+        example: "Ask for a number called n. Print 100 modulo n.",
+        rewrite: "Divide by something the spec keeps away from zero:\n\
+\n    Ask for a list of numbers called values. Set n to the count of values. Set d to n plus 1.\n    Print 100 modulo d.\n\
+\nOr allow the lint if a zero divisor can't happen for this program's inputs.",
+    },
+    Explanation {
+        code: "NH0101",
+        name: "empty-response",
+        summary: "The model returned an empty response",
+        description: "The provider answered, but with no text or no code block. This is usually a \
+refusal, a content filter, or a provider outage rather than a problem with the spec.",
+        example: "Any spec can raise this; specs asking for harmful behavior are more likely to.",
+        rewrite: "Compile again, or try another provider with --llm-provider. If it keeps \
+happening, inspect the exchange with --debug-llm.",
+    },
+    Explanation {
+        code: "NH0102",
+        name: "truncated-response",
+        summary: "The model's response was cut off inside a code block",
+        description: "The response hit the model's output limit before the code block closed, so \
+the code is incomplete and cannot be built.",
+        example: "A spec of dozens of paragraphs describing a whole application at once.",
+        rewrite: "Shorten the spec, or split it into modules of a project (see nhlp.toml) so each \
+part is smaller.",
+    },
+    Explanation {
+        code: "NH0103",
+        name: "missing-toolchain",
+        summary: "No compiler for the generated language was found",
+        description: "The generated code needs gcc, clang, or rustc, and none that fits was found on \
+PATH.",
+        example: "Any spec; this depends on the machine, not on the text.",
+        rewrite: "Install the compiler the message names, then run `nhlp doctor` to confirm it is found.",
+    },
+    Explanation {
+        code: "NH0104",
+        name: "unsupported-language",
+        summary: "The generated code is in a language NHLP cannot build",
+        description: "The model chose a language other than C or Rust for the program.",
+        example: "Write this as a Python script that prints the date.",
+        rewrite: "Leave the implementation language out of the spec; describe what the program \
+does, not what it is written in.",
+    },
+    Explanation {
+        code: "NH0105",
+        name: "toolchain-unavailable",
+        summary: "The compiler was found but could not be started",
+        description: "Starting gcc, clang, or rustc failed, typically because of permissions or a \
+broken installation.",
+        example: "Any spec; this depends on the machine, not on the text.",
+        rewrite: "Run the compiler by hand to see why it fails to start, and fix or reinstall it.",
+    },
+    Explanation {
+        code: "NH0106",
+        name: "build-failed",
+        summary: "The compiler rejected the generated code",
+        description: "The generated code did not compile. The compiler's own errors are printed \
+above this one; they usually point at a library or feature the model assumed was available.",
+        example: "Use the fancy graphics library to draw a chart of the numbers.",
+        rewrite: "Ask only for what the standard library provides, and describe behavior rather \
+than specific libraries:\n\
+\n    Print a bar of # characters for each number, as long as the number.",
+    },
+    Explanation {
+        code: "NH0107",
+        name: "invalid-output-path",
+        summary: "The output path is not valid UTF-8",
+        description: "The path the executable would be written to cannot be passed to the \
+compiler because it is not valid UTF-8.",
+        example: "Any spec whose file name or --out-dir contains non-UTF-8 bytes.",
+        rewrite: "Rename the spec or choose an --out-dir with a UTF-8 path.",
+    },
+];
+
+/// Look up a diagnostic by code (`NH0005`, any case) or name (`program-failed`)
+pub fn lookup(code: &str) -> Option<&'static Explanation> {
+    let code = code.trim();
+    CATALOG
+        .iter()
+        .find(|entry| entry.code.eq_ignore_ascii_case(code) || entry.name == code)
+}
+
+/// The catalog entry for the first diagnostic code in the error's chain
+///
+/// Errors and denied lints both start their message with their code, e.g.
+/// `NH0103: No C compiler found...`.
+pub fn for_error(error: &anyhow::Error) -> Option<&'static Explanation> {
+    error.chain().find_map(|cause| code_in(&cause.to_string()))
+}
+
+/// The catalog entry for the first `NHxxxx:` code in a message
+pub fn code_in(message: &str) -> Option<&'static Explanation> {
+    message.match_indices("NH").find_map(|(start, _)| {
+        let code = message.get(start..start + 6)?;
+        if !message[start + 6..].starts_with(':') {
+            return None;
+        }
+        CATALOG.iter().find(|entry| entry.code == code)
+    })
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} [{}]: {}", self.code, self.name, self.summary)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "Example spec text:")?;
+        writeln!(f)?;
+        writeln!(f, "    {}", self.example)?;
+        writeln!(f)?;
+        writeln!(f, "Recommended rewrite:")?;
+        writeln!(f)?;
+        write!(f, "{}", self.rewrite)
+    }
+}
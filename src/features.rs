@@ -0,0 +1,141 @@
+use anyhow::Result;
+
+/// CPU features that can be turned on or off per architecture, with the
+/// name rustc uses and the name C drivers use in `-m<name>` / `+<name>`
+const X86_FEATURES: &[(&str, &str)] = &[
+    ("sse3", "sse3"),
+    ("ssse3", "ssse3"),
+    ("sse4.1", "sse4.1"),
+    ("sse4.2", "sse4.2"),
+    ("popcnt", "popcnt"),
+    ("lzcnt", "lzcnt"),
+    ("bmi1", "bmi"),
+    ("bmi2", "bmi2"),
+    ("fma", "fma"),
+    ("aes", "aes"),
+    ("sha", "sha"),
+    ("avx", "avx"),
+    ("avx2", "avx2"),
+    ("avx512f", "avx512f"),
+    ("avx512bw", "avx512bw"),
+    ("avx512vl", "avx512vl"),
+];
+
+const AARCH64_FEATURES: &[(&str, &str)] = &[
+    ("neon", "simd"),
+    ("crc", "crc"),
+    ("aes", "aes"),
+    ("sha2", "sha2"),
+    ("lse", "lse"),
+    ("dotprod", "dotprod"),
+    ("sve", "sve"),
+    ("sve2", "sve2"),
+];
+
+/// Extensions beyond RV64GC, the baseline every riscv64 Linux target assumes
+const RISCV64_FEATURES: &[(&str, &str)] = &[
+    ("v", "v"),
+    ("zba", "zba"),
+    ("zbb", "zbb"),
+    ("zbc", "zbc"),
+    ("zbs", "zbs"),
+    ("zfh", "zfh"),
+];
+
+const WASM_FEATURES: &[(&str, &str)] = &[
+    ("simd128", "simd128"),
+    ("bulk-memory", "bulk-memory"),
+    ("sign-ext", "sign-ext"),
+];
+
+/// CPU features enabled or disabled for code generation, overriding the
+/// target's defaults
+///
+/// Built with `--features avx2,-avx512f`: a leading `-` disables a feature
+/// and an optional `+` enables it. Disabling features a build machine has
+/// keeps the executable runnable on older hosts.
+#[derive(Debug, Clone, Default)]
+pub struct TargetFeatures {
+    arch: String,
+    /// rustc name, C driver name, and whether the feature is enabled
+    features: Vec<(&'static str, &'static str, bool)>,
+}
+
+impl TargetFeatures {
+    /// Parse `--features` for `target`, or the host when no target is given
+    ///
+    /// Fails on features the target's architecture doesn't have.
+    pub fn parse(specs: &[String], target: Option<&str>) -> Result<Self> {
+        let arch = target
+            .and_then(|target| target.split('-').next())
+            .unwrap_or(std::env::consts::ARCH)
+            .to_string();
+        let known = match arch.as_str() {
+            "x86_64" | "i686" | "i586" | "x86" => X86_FEATURES,
+            "aarch64" | "arm64" => AARCH64_FEATURES,
+            "riscv64gc" | "riscv64" => RISCV64_FEATURES,
+            "wasm32" | "wasm64" => WASM_FEATURES,
+            _ => &[],
+        };
+        
+        let mut features: Vec<(&str, &str, bool)> = Vec::new();
+        for spec in specs {
+            let spec = spec.trim();
+            if spec.is_empty() {
+                continue;
+            }
+            let (name, enabled) = match spec.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (spec.strip_prefix('+').unwrap_or(spec), true),
+            };
+            let Some(&(rust_name, c_name)) = known.iter().find(|(rust_name, _)| *rust_name == name) else {
+                let names: Vec<&str> = known.iter().map(|(rust_name, _)| *rust_name).collect();
+                anyhow::bail!(
+                    "Unknown CPU feature `{}` for {}; known features: {}",
+                    name,
+                    arch,
+                    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+                );
+            };
+            // A later mention of the same feature wins
+            features.retain(|(existing, _, _)| *existing != rust_name);
+            features.push((rust_name, c_name, enabled));
+        }
+        Ok(Self { arch, features })
+    }
+    
+    /// Toolchain arguments applying these features when compiling `language`
+    pub fn driver_args(&self, language: &str) -> Vec<String> {
+        if self.features.is_empty() {
+            return Vec::new();
+        }
+        if language == "rust" {
+            let list: Vec<String> = self.features
+                .iter()
+                .map(|(name, _, enabled)| format!("{}{}", if *enabled { '+' } else { '-' }, name))
+                .collect();
+            return vec!["-C".to_string(), format!("target-feature={}", list.join(","))];
+        }
+        
+        // GCC and clang take AArch64 features as -march extensions rather than -m flags
+        if self.arch == "aarch64" || self.arch == "arm64" {
+            let extensions: String = self.features
+                .iter()
+                .map(|(_, name, enabled)| format!("+{}{}", if *enabled { "" } else { "no" }, name))
+                .collect();
+            return vec![format!("-march=armv8-a{}", extensions)];
+        }
+        // RISC-V extensions are spelled into the ISA string: single letters
+        // directly, longer names after underscores. Left out means disabled.
+        if self.arch.starts_with("riscv64") {
+            let enabled: Vec<&str> = self.features.iter().filter(|(_, _, enabled)| *enabled).map(|(_, name, _)| *name).collect();
+            let (letters, named): (Vec<&str>, Vec<&str>) = enabled.iter().partition(|name| name.len() == 1);
+            let named: String = named.iter().map(|name| format!("_{}", name)).collect();
+            return vec![format!("-march=rv64gc{}{}", letters.concat(), named)];
+        }
+        self.features
+            .iter()
+            .map(|(_, name, enabled)| format!("-m{}{}", if *enabled { "" } else { "no-" }, name))
+            .collect()
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the glossary kept next to .dshp sources
+const GLOSSARY_NAME: &str = "nhlp.glossary";
+
+/// One defined term
+#[derive(Debug)]
+struct Term {
+    term: String,
+    meaning: String,
+    /// Matches the term as a whole word or phrase, ignoring case
+    pattern: Regex,
+}
+
+/// Project-wide meanings of words, shared by every program in a directory
+///
+/// The file has one `term: meaning` definition per line, e.g.
+/// `client: the TCP peer that opened the connection, never a customer`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Default)]
+pub struct Glossary {
+    terms: Vec<Term>,
+}
+
+impl Glossary {
+    /// Path of the glossary governing a spec
+    pub fn path_for(spec: &Path) -> PathBuf {
+        spec.parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(GLOSSARY_NAME)
+    }
+    
+    /// Load and validate a glossary, treating a missing file as empty
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read glossary: {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Invalid glossary: {:?}", path))
+    }
+    
+    fn parse(text: &str) -> Result<Self> {
+        let mut terms: Vec<Term> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            
+            let number = index + 1;
+            let Some((term, meaning)) = line.split_once(':') else {
+                anyhow::bail!("line {}: expected `term: meaning`", number);
+            };
+            let (term, meaning) = (term.trim(), meaning.trim());
+            if term.is_empty() || meaning.is_empty() {
+                anyhow::bail!("line {}: both the term and its meaning are required", number);
+            }
+            if terms.iter().any(|existing| existing.term.eq_ignore_ascii_case(term)) {
+                anyhow::bail!("line {}: `{}` is defined more than once", number, term);
+            }
+            
+            let pattern = RegexBuilder::new(&format!(r"\b{}\b", regex::escape(term)))
+                .case_insensitive(true)
+                .build()?;
+            terms.push(Term { term: term.to_string(), meaning: meaning.to_string(), pattern });
+        }
+        Ok(Self { terms })
+    }
+    
+    /// Number of defined terms
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+    
+    /// The defined terms `program` uses
+    pub fn used_terms(&self, program: &str) -> Vec<&str> {
+        self.terms
+            .iter()
+            .filter(|term| term.pattern.is_match(program))
+            .map(|term| term.term.as_str())
+            .collect()
+    }
+    
+    /// Byte ranges of every defined term in `text`, with the term and its meaning
+    #[cfg(feature = "lsp")]
+    pub fn occurrences<'a>(&'a self, text: &str) -> Vec<(std::ops::Range<usize>, &'a str, &'a str)> {
+        self.terms
+            .iter()
+            .flat_map(|term| {
+                term.pattern
+                    .find_iter(text)
+                    .map(move |found| (found.range(), term.term.as_str(), term.meaning.as_str()))
+            })
+            .collect()
+    }
+    
+    /// Prompt section defining the terms `program` uses, or an empty string if it uses none
+    pub fn prompt_section(&self, program: &str) -> String {
+        let used: Vec<String> = self.terms
+            .iter()
+            .filter(|term| term.pattern.is_match(program))
+            .map(|term| format!("- {}: {}", term.term, term.meaning))
+            .collect();
+        if used.is_empty() {
+            return String::new();
+        }
+        format!(
+            "\nGLOSSARY (these terms have fixed meanings throughout the project; follow them over any other reading):\n{}\n",
+            used.join("\n")
+        )
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::Result;
+use std::fmt::Write;
+
+use crate::offline::{self, Expr, Kind, PathPart, Piece, Sentence, Statement};
+
+/// A run of sentences that always execute together, one after another
+#[derive(Debug, Clone, Default)]
+pub struct Block {
+    /// `line N: sentence` for each sentence in the block, or a single condition
+    pub lines: Vec<String>,
+}
+
+/// Control flow between the basic blocks of a spec, as the offline vocabulary reads it
+///
+/// Block 0 is the entry and the last block is the exit. A conditional
+/// sentence ends its block and branches to the block for what it prints,
+/// and to the one for `otherwise` if there is one, which meet again in the
+/// block for the next sentence. A sentence that loops, such as listing a
+/// folder or reading or reducing a list, gets a header block that either
+/// enters its body, which flows back to the header, or leaves the loop. A
+/// reduction's body only takes in each number, so the sentence storing or
+/// printing its result starts the block after the loop.
+#[derive(Debug, Clone)]
+pub struct ControlFlow {
+    pub blocks: Vec<Block>,
+    /// From, to, and the label of a conditional edge
+    pub edges: Vec<(usize, usize, Option<&'static str>)>,
+}
+
+impl ControlFlow {
+    /// Build the graph for a spec, failing on sentences outside the offline vocabulary
+    pub fn new(program: &str) -> Result<Self> {
+        Ok(Self::from_sentences(&offline::parse(program)?))
+    }
+
+    fn from_sentences(sentences: &[Sentence]) -> Self {
+        let mut graph = Self { blocks: vec![Block::default()], edges: Vec::new() };
+        let mut current = 0;
+        for sentence in sentences {
+            if let Some((header, step)) = iteration(&sentence.statement) {
+                // The entry can't be a loop header, since nothing flows back into it
+                if !graph.blocks[current].lines.is_empty() || current == 0 {
+                    current = graph.successor(current, None);
+                }
+                graph.blocks[current].lines.push(format!("line {}: {}", sentence.line, header));
+                let body = graph.successor(current, Some("next"));
+                graph.edges.push((body, current, None));
+                let done = graph.successor(current, Some("done"));
+                match step {
+                    Some(step) => {
+                        graph.blocks[body].lines.push(format!("line {}: {}", sentence.line, step));
+                        graph.blocks[done].lines.push(format!("line {}: {}", sentence.line, sentence.text));
+                    }
+                    None => graph.blocks[body].lines.push(format!("line {}: {}", sentence.line, sentence.text)),
+                }
+                current = done;
+                continue;
+            }
+            let Statement::PrintIfMatches { name, regex, then, otherwise } = &sentence.statement else {
+                graph.blocks[current].lines.push(format!("line {}: {}", sentence.line, sentence.text));
+                continue;
+            };
+
+            // The condition gets a block of its own, so both branches start from it
+            if !graph.blocks[current].lines.is_empty() {
+                current = graph.successor(current, None);
+            }
+            graph.blocks[current].lines.push(format!("line {}: if {} matches {}", sentence.line, name, regex));
+            let matched = graph.successor(current, Some("matches"));
+            graph.blocks[matched].lines.push(format!("line {}: print \"{}\"", sentence.line, printed(then)));
+            let unmatched = match otherwise {
+                Some(otherwise) => {
+                    let unmatched = graph.successor(current, Some("no match"));
+                    graph.blocks[unmatched].lines.push(format!("line {}: print \"{}\"", sentence.line, printed(otherwise)));
+                    Some(unmatched)
+                }
+                None => None,
+            };
+            let join = graph.add_block();
+            graph.edges.push((matched, join, None));
+            match unmatched {
+                Some(unmatched) => graph.edges.push((unmatched, join, None)),
+                None => graph.edges.push((current, join, Some("no match"))),
+            }
+            current = join;
+        }
+        if !graph.blocks[current].lines.is_empty() || current == 0 {
+            current = graph.successor(current, None);
+        }
+        graph.blocks[current].lines.push("exit".to_string());
+        graph
+    }
+
+    fn add_block(&mut self) -> usize {
+        self.blocks.push(Block::default());
+        self.blocks.len() - 1
+    }
+
+    /// A new block that `from` flows into
+    fn successor(&mut self, from: usize, label: Option<&'static str>) -> usize {
+        let to = self.add_block();
+        self.edges.push((from, to, label));
+        to
+    }
+
+    fn predecessors(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges.iter().filter(move |(_, to, _)| *to == block).map(|(from, _, _)| *from)
+    }
+
+    /// The immediate dominator of every block, None for the entry
+    ///
+    /// Uses the iterative algorithm of Cooper, Harvey, and Kennedy over
+    /// reverse postorder, which converges in a couple of passes on graphs
+    /// this size.
+    pub fn immediate_dominators(&self) -> Vec<Option<usize>> {
+        let order = self.reverse_postorder();
+        let mut position = vec![usize::MAX; self.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[*block] = index;
+        }
+        let mut idom: Vec<Option<usize>> = vec![None; self.blocks.len()];
+        idom[0] = Some(0);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut new_idom: Option<usize> = None;
+                for predecessor in self.predecessors(block).filter(|predecessor| idom[*predecessor].is_some()) {
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(current) => intersect(&idom, &position, predecessor, current),
+                    });
+                }
+                if new_idom.is_some() && idom[block] != new_idom {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idom[0] = None;
+        idom
+    }
+
+    /// The dominator tree and dominance frontiers, for passes that place code where control flow meets
+    pub fn dominators(&self) -> Dominators {
+        let idom = self.immediate_dominators();
+        let mut frontiers = vec![Vec::new(); self.blocks.len()];
+        for (block, frontier_of) in frontiers_of(self, &idom) {
+            if !frontiers[frontier_of].contains(&block) {
+                frontiers[frontier_of].push(block);
+            }
+        }
+        frontiers.iter_mut().for_each(|frontier| frontier.sort_unstable());
+        Dominators { idom, frontiers }
+    }
+
+    fn reverse_postorder(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        let mut stack = vec![(0, false)];
+        while let Some((block, expanded)) = stack.pop() {
+            if expanded {
+                postorder.push(block);
+                continue;
+            }
+            if visited[block] {
+                continue;
+            }
+            visited[block] = true;
+            stack.push((block, true));
+            let successors = self.edges.iter().filter(|(from, _, _)| *from == block).map(|(_, to, _)| *to);
+            stack.extend(successors.filter(|to| !visited[*to]).map(|to| (to, false)));
+        }
+        postorder.reverse();
+        postorder
+    }
+}
+
+/// Dominance over a control-flow graph
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    /// The immediate dominator of every block, None for the entry and unreachable blocks
+    pub idom: Vec<Option<usize>>,
+    /// The blocks where each block's dominance ends: those it doesn't strictly
+    /// dominate but has a predecessor of, in block order
+    pub frontiers: Vec<Vec<usize>>,
+}
+
+impl Dominators {
+    /// The blocks `block` immediately dominates, its children in the dominator tree
+    pub fn children(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+        self.idom.iter().enumerate().filter(move |(_, idom)| **idom == Some(block)).map(|(child, _)| child)
+    }
+}
+
+/// Pairs of a join block and a block whose frontier it is in
+///
+/// For each block with several predecessors, walks up the dominator tree
+/// from each predecessor until reaching the block's immediate dominator, as
+/// Cooper, Harvey, and Kennedy describe; every block passed on the way has
+/// the join in its frontier.
+fn frontiers_of(graph: &ControlFlow, idom: &[Option<usize>]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for block in 0..graph.blocks.len() {
+        let predecessors: Vec<usize> = graph.predecessors(block).collect();
+        if predecessors.len() < 2 {
+            continue;
+        }
+        for predecessor in predecessors {
+            if predecessor != 0 && idom[predecessor].is_none() {
+                continue;
+            }
+            let mut runner = Some(predecessor);
+            while let Some(current) = runner.filter(|current| Some(*current) != idom[block]) {
+                pairs.push((block, current));
+                runner = idom[current];
+            }
+        }
+    }
+    pairs
+}
+
+/// The loop header for a sentence that repeats its work, None for one that runs straight through
+///
+/// A reduction also gives the step its body repeats, since the sentence
+/// itself only uses the result once the loop is done.
+fn iteration(statement: &Statement) -> Option<(String, Option<String>)> {
+    match statement {
+        Statement::ListFolder(PathPart::Literal(folder)) => Some((format!("for every entry in {}", folder), None)),
+        Statement::ListFolder(PathPart::Variable(name)) => Some((format!("for every entry in {{{}}}", name), None)),
+        Statement::Ask { name, kind: Kind::List, .. } => Some((format!("until a blank line, read a number into {}", name), None)),
+        Statement::Set { value: Expr::Reduce { reduction, list, filter }, .. } | Statement::PrintNumber(Expr::Reduce { reduction, list, filter }) => {
+            let numbers = if filter.is_some() { "each matching number" } else { "each number" };
+            let step = format!("take {} into the {}", numbers, format!("{:?}", reduction).to_lowercase());
+            Some((format!("for every number in {}", list), Some(step)))
+        }
+        _ => None,
+    }
+}
+
+/// Printed text as the spec wrote it, with its `{name}` placeholders
+fn printed(pieces: &[Piece]) -> String {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.clone(),
+            Piece::Variable(name, _) => format!("{{{}}}", name),
+        })
+        .collect()
+}
+
+/// The closest common dominator of two blocks, walking up from whichever is later in reverse postorder
+///
+/// A block without a dominator yet is walked straight to the entry, which dominates every reachable block.
+fn intersect(idom: &[Option<usize>], position: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while position[a] > position[b] {
+            a = idom[a].unwrap_or(0);
+        }
+        while position[b] > position[a] {
+            b = idom[b].unwrap_or(0);
+        }
+    }
+    a
+}
+
+/// The control-flow graph, the dominator tree, or both, as one Graphviz DOT digraph
+///
+/// Each is a cluster, so `dot -Tsvg` draws them side by side. The dominator
+/// tree shows each block's dominance frontier as dashed edges.
+pub fn dot(graph: &ControlFlow, cfg: bool, domtree: bool) -> String {
+    let mut out = String::from("digraph spec {\n    node [shape=box, fontname=\"monospace\"];\n");
+    let node = |out: &mut String, prefix: &str, index: usize, block: &Block| {
+        let mut label = format!("B{}\\l", index);
+        for line in &block.lines {
+            let _ = write!(label, "{}\\l", escape(line));
+        }
+        let _ = writeln!(out, "        {}{} [label=\"{}\"];", prefix, index, label);
+    };
+    if cfg {
+        out.push_str("    subgraph cluster_cfg {\n        label=\"control flow\";\n");
+        for (index, block) in graph.blocks.iter().enumerate() {
+            node(&mut out, "cfg", index, block);
+        }
+        for (from, to, label) in &graph.edges {
+            let _ = match label {
+                Some(label) => writeln!(out, "        cfg{} -> cfg{} [label=\"{}\"];", from, to, label),
+                None => writeln!(out, "        cfg{} -> cfg{};", from, to),
+            };
+        }
+        out.push_str("    }\n");
+    }
+    if domtree {
+        out.push_str("    subgraph cluster_domtree {\n        label=\"dominator tree\";\n");
+        for (index, block) in graph.blocks.iter().enumerate() {
+            node(&mut out, "dom", index, block);
+        }
+        let dominators = graph.dominators();
+        for index in 0..graph.blocks.len() {
+            for child in dominators.children(index) {
+                let _ = writeln!(out, "        dom{} -> dom{};", index, child);
+            }
+            for frontier in &dominators.frontiers[index] {
+                let _ = writeln!(out, "        dom{} -> dom{} [style=dashed, label=\"frontier\"];", index, frontier);
+            }
+        }
+        out.push_str("    }\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Text safe inside a quoted DOT label
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(blocks: usize, edges: &[(usize, usize)]) -> ControlFlow {
+        ControlFlow {
+            blocks: vec![Block::default(); blocks],
+            edges: edges.iter().map(|&(from, to)| (from, to, None)).collect(),
+        }
+    }
+
+    #[test]
+    fn diamond_joins_in_both_branches_frontiers() {
+        let dominators = graph(5, &[(0, 1), (0, 2), (1, 3), (2, 3), (3, 4)]).dominators();
+        assert_eq!(dominators.idom, [None, Some(0), Some(0), Some(0), Some(3)]);
+        assert_eq!(dominators.frontiers, [vec![], vec![3], vec![3], vec![], vec![]]);
+        assert_eq!(dominators.children(0).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn loop_header_is_in_its_own_and_its_bodys_frontier() {
+        let dominators = graph(4, &[(0, 1), (1, 2), (2, 1), (1, 3)]).dominators();
+        assert_eq!(dominators.idom, [None, Some(0), Some(1), Some(1)]);
+        assert_eq!(dominators.frontiers, [vec![], vec![1], vec![1], vec![]]);
+    }
+
+    #[test]
+    fn unreachable_block_has_no_dominator_and_adds_no_frontier() {
+        let dominators = graph(3, &[(0, 1), (2, 1)]).dominators();
+        assert_eq!(dominators.idom, [None, Some(0), None]);
+        assert_eq!(dominators.frontiers, [Vec::<usize>::new(), vec![], vec![]]);
+    }
+
+    #[test]
+    fn spec_condition_branches_and_meets_again() {
+        let flow = ControlFlow::new("Ask for a word called w.\nIf w matches a number, print \"yes\", otherwise print \"no\".").unwrap();
+        let dominators = flow.dominators();
+        let condition = flow.blocks.iter().position(|block| block.lines.iter().any(|line| line.contains("if w matches"))).unwrap();
+        assert_eq!(dominators.children(condition).count(), 3);
+        let branches: Vec<usize> =
+            flow.edges.iter().filter(|(from, _, label)| *from == condition && label.is_some()).map(|(_, to, _)| *to).collect();
+        assert_eq!(branches.len(), 2);
+        let join = flow.edges.iter().find(|(from, _, _)| *from == branches[0]).map(|(_, to, _)| *to).unwrap();
+        assert_eq!(dominators.idom[join], Some(condition));
+        assert_eq!(dominators.frontiers[branches[0]], [join]);
+        assert_eq!(dominators.frontiers[branches[1]], [join]);
+    }
+
+    #[test]
+    fn reduction_result_is_used_after_its_loop() {
+        let flow = ControlFlow::new("Ask for a list of numbers called scores.\nPrint the sum of scores.").unwrap();
+        let block_with = |text: &str| flow.blocks.iter().position(|block| block.lines.iter().any(|line| line.contains(text))).unwrap();
+        let header = block_with("for every number in scores");
+        let body = block_with("take each number into the sum");
+        let print = block_with("Print the sum of scores");
+        assert!(flow.edges.contains(&(header, body, Some("next"))));
+        assert!(flow.edges.contains(&(body, header, None)));
+        assert!(flow.edges.contains(&(header, print, Some("done"))));
+        assert_ne!(print, body);
+        assert!(!flow.edges.iter().any(|(from, to, _)| *from == print && *to == header));
+    }
+}
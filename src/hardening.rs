@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+/// Tool that reads the ELF headers, segments, and symbols of an executable
+const READELF: &str = "readelf";
+
+/// Toolchain arguments for a position-independent executable with stack
+/// canaries, a non-executable stack, full RELRO, and fortified libc calls
+///
+/// FORTIFY_SOURCE only takes effect in optimized builds, so C programs are
+/// also built with -O2. Rust has bounds checks already; it gets overflow
+/// checks in their place. Libraries are position-independent already and
+/// can't be linked as PIE, so `executable` is false for them.
+pub fn driver_args(language: &str, executable: bool) -> Vec<String> {
+    let (pie, args): (&[&str], &[&str]) = if language == "rust" {
+        (
+            &["-C", "relocation-model=pie"],
+            &[
+                "-C", "overflow-checks=on",
+                "-C", "link-arg=-Wl,-z,relro,-z,now",
+                "-C", "link-arg=-Wl,-z,noexecstack",
+            ],
+        )
+    } else {
+        (
+            &["-fPIE", "-pie"],
+            &[
+                "-fstack-protector-strong",
+                "-O2",
+                "-U_FORTIFY_SOURCE",
+                "-D_FORTIFY_SOURCE=2",
+                "-Wl,-z,relro,-z,now",
+                "-Wl,-z,noexecstack",
+            ],
+        )
+    };
+    let pie = if executable { pie } else { &[] };
+    pie.iter().chain(args).map(|arg| arg.to_string()).collect()
+}
+
+/// How much of the GOT is read-only after relocation
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Relro {
+    None,
+    Partial,
+    Full,
+}
+
+/// Hardening properties read back from a built ELF executable
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HardeningSummary {
+    /// Loaded at a random address (position-independent executable)
+    pub pie: bool,
+    pub relro: Relro,
+    /// The stack is not executable
+    pub nx: bool,
+    /// Some function checks a stack canary; functions without buffers need none
+    pub stack_canary: bool,
+    /// Some libc call was replaced by its bounds-checked `_chk` variant
+    pub fortified: bool,
+}
+
+impl fmt::Display for HardeningSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        let relro = match self.relro {
+            Relro::None => "none",
+            Relro::Partial => "partial",
+            Relro::Full => "full",
+        };
+        writeln!(f, "Hardening:")?;
+        writeln!(f, "  PIE:              {}", yes_no(self.pie))?;
+        writeln!(f, "  RELRO:            {}", relro)?;
+        writeln!(f, "  NX stack:         {}", yes_no(self.nx))?;
+        writeln!(f, "  Stack canaries:   {}", yes_no(self.stack_canary))?;
+        write!(f, "  Fortified calls:  {}", yes_no(self.fortified))
+    }
+}
+
+/// Read the hardening properties of an ELF executable with readelf
+pub fn inspect(executable: &Path) -> Result<HardeningSummary> {
+    let output = Command::new(READELF)
+        .args(["-W", "-h", "-l", "-d", "-s"])
+        .arg(executable)
+        .output()
+        .with_context(|| format!("Failed to run {} to inspect the executable", READELF))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{} failed: {}",
+            READELF,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_readelf(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_readelf(output: &str) -> HardeningSummary {
+    let mut summary = HardeningSummary {
+        pie: false,
+        relro: Relro::None,
+        nx: false,
+        stack_canary: false,
+        fortified: false,
+    };
+    let mut bind_now = false;
+    for line in output.lines() {
+        let line = line.trim();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if line.starts_with("Type:") {
+            summary.pie = line.contains("DYN");
+        } else if fields.first() == Some(&"GNU_RELRO") {
+            summary.relro = Relro::Partial;
+        } else if fields.first() == Some(&"GNU_STACK") {
+            // The flags column is R, W, and E; an E means the stack is executable.
+            // Without a GNU_STACK header at all, the loader assumes it is.
+            summary.nx = !fields.iter().any(|field| field.contains('E') && field.len() <= 3 && field.chars().all(|c| "RWE".contains(c)));
+        } else if line.contains("(BIND_NOW)") || (line.contains("(FLAGS") && line.contains("NOW")) {
+            bind_now = true;
+        } else if let Some(symbol) = fields.last() {
+            let symbol = symbol.split('@').next().unwrap_or_default();
+            if symbol == "__stack_chk_fail" {
+                summary.stack_canary = true;
+            } else if symbol.starts_with("__") && symbol.ends_with("_chk") {
+                summary.fortified = true;
+            }
+        }
+    }
+    if bind_now && summary.relro == Relro::Partial {
+        summary.relro = Relro::Full;
+    }
+    summary
+}
@@ -0,0 +1,683 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::offline::{self, Comparison, Expr, Kind, Op, Operand, PathPart, Piece, Reduction, Sentence, Statement};
+use crate::trace::{Change, Transition};
+
+/// Bytes of a line the native build keeps, its 256-byte buffers less the terminating NUL
+const LINE_LIMIT: usize = 255;
+
+/// How a program is executed
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Translate to C or Rust and build a native executable
+    #[default]
+    Native,
+    /// Run the spec directly with the interpreter; no model or toolchain is involved
+    Interp,
+}
+
+/// Run a spec with the interpreter, reading the terminal or `stdin_file`
+/// and reporting each change to the `watch`ed variables on stderr
+pub fn run(program: &str, stdin_file: Option<&Path>, watch: &[String]) -> Result<()> {
+    let input: Box<dyn BufRead> = match stdin_file {
+        Some(path) => Box::new(BufReader::new(
+            fs::File::open(path).with_context(|| format!("Failed to open stdin file: {:?}", path))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut machine = Machine::new(program, input, io::stdout().lock())?;
+    for name in watch {
+        machine.watch(name)?;
+    }
+    machine.run()
+}
+
+/// A variable's current value
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Value {
+    Number(#[serde(with = "number")] f64),
+    Text(String),
+    List(Vec<f64>),
+}
+
+/// Numbers as JSON numbers, except infinities and NaN, which JSON lacks and
+/// are written as strings instead
+mod number {
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() {
+            serializer.serialize_f64(*value)
+        } else {
+            serializer.serialize_str(&value.to_string())
+        }
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(f64),
+            Text(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(number) => Ok(number),
+            Repr::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => f.write_str(&format_g(*number)),
+            Value::Text(text) => f.write_str(text),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| format_g(*item)).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// Executes a program in the offline vocabulary sentence by sentence
+///
+/// It follows the C the offline translator writes for the same sentences:
+/// prompts, decimal number literals, `%g` number formatting, strtod's
+/// reading of numbers, truncating and saturating remainders, lines cut
+/// to the 255 bytes the C buffers hold, POSIX regular expressions, and the
+/// errors for input that isn't a number and a zero remainder divisor. The
+/// known differences: a line cut inside a multibyte character keeps the
+/// partial character in C and drops it here, errors are worded as nhlp's
+/// own, and where libc's regex functions aren't available (Windows) the
+/// patterns run on the regex crate.
+pub struct Machine<R, W> {
+    sentences: Vec<Sentence>,
+    next: usize,
+    variables: BTreeMap<String, Value>,
+    input: R,
+    output: W,
+    trace: Option<Vec<Transition>>,
+    watched: BTreeSet<String>,
+}
+
+impl<R: BufRead, W: Write> Machine<R, W> {
+    /// Parse `program` into a machine that reads `input` and writes `output`
+    pub fn new(program: &str, input: R, output: W) -> Result<Self> {
+        Ok(Self {
+            sentences: offline::parse(program)?,
+            next: 0,
+            variables: BTreeMap::new(),
+            input,
+            output,
+            trace: None,
+            watched: BTreeSet::new(),
+        })
+    }
+    
+    /// Report on stderr every time a sentence changes `name`, naming the sentence
+    ///
+    /// Fails when no sentence gives `name` a value, which is usually a typo.
+    pub fn watch(&mut self, name: &str) -> Result<()> {
+        let assigned = self.sentences.iter().any(|sentence| match &sentence.statement {
+            Statement::Ask { name: assigned, .. } | Statement::Set { name: assigned, .. } | Statement::Join { name: assigned, .. } => assigned == name,
+            _ => false,
+        });
+        if !assigned {
+            anyhow::bail!("Can't watch {}: no sentence gives it a value", name);
+        }
+        self.watched.insert(name.to_string());
+        Ok(())
+    }
+    
+    /// Stop reporting changes to `name`, returning whether it was watched
+    pub fn unwatch(&mut self, name: &str) -> bool {
+        self.watched.remove(name)
+    }
+    
+    /// Variables being watched
+    pub fn watched(&self) -> &BTreeSet<String> {
+        &self.watched
+    }
+    
+    /// Record every step from now on, for replaying the run later
+    pub fn record(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+    
+    /// Steps recorded since `record`, or None when not recording
+    pub fn trace(&self) -> Option<&[Transition]> {
+        self.trace.as_deref()
+    }
+    
+    /// Every sentence of the program, in order
+    pub fn sentences(&self) -> &[Sentence] {
+        &self.sentences
+    }
+    
+    /// Index of the sentence `step` runs next, or the number of sentences once finished
+    pub fn position(&self) -> usize {
+        self.next
+    }
+    
+    /// Variables given a value so far
+    pub fn variables(&self) -> &BTreeMap<String, Value> {
+        &self.variables
+    }
+    
+    /// Change a variable's value mid-run, parsing `value` as the kind it already holds
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<()> {
+        let current = self.variables.get_mut(name).with_context(|| format!("{} has no value yet", name))?;
+        *current = match current {
+            Value::Number(_) => Value::Number(value.trim().parse().with_context(|| format!("{} holds a number", name))?),
+            Value::Text(_) => Value::Text(value.to_string()),
+            Value::List(_) => Value::List(
+                value
+                    .split([',', ' '])
+                    .filter(|item| !item.is_empty())
+                    .map(|item| item.parse().with_context(|| format!("{} holds numbers", name)))
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        let change = Change { name: name.to_string(), value: current.clone() };
+        if let Some(trace) = &mut self.trace {
+            trace.push(Transition { changes: vec![change], ..Default::default() });
+        }
+        Ok(())
+    }
+    
+    /// Run every remaining sentence
+    pub fn run(&mut self) -> Result<()> {
+        while self.step()? {}
+        Ok(())
+    }
+    
+    /// Execute the next sentence, returning false once there are none left
+    pub fn step(&mut self) -> Result<bool> {
+        let Some(sentence) = self.sentences.get(self.next) else {
+            return Ok(false);
+        };
+        let sentence = sentence.clone();
+        if let Some(trace) = &mut self.trace {
+            trace.push(Transition { sentence: Some(self.next), ..Default::default() });
+        }
+        self.next += 1;
+        let result = self
+            .execute(&sentence.statement)
+            .with_context(|| format!("line {}: {}", sentence.line, sentence.text));
+        if let (Err(e), Some(step)) = (&result, self.current_step()) {
+            step.error = Some(format!("{:#}", e));
+        }
+        result?;
+        self.output.flush()?;
+        Ok(true)
+    }
+    
+    fn execute(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Print(pieces) => {
+                let text = self.fill(pieces)?;
+                self.write(&format!("{}\n", text))?;
+            }
+            Statement::Ask { name, kind: Kind::List, .. } => {
+                self.write(&format!("Enter {}, one per line, then an empty line: ", name))?;
+                self.output.flush()?;
+                let mut items = Vec::new();
+                let mut lines = Vec::new();
+                let mut line = String::new();
+                while self.read_line(&mut line)? > 0 && !is_blank(&line) {
+                    items.push(parse_leading_number(&line).with_context(|| format!("Expected a number for {}", name))?);
+                    lines.push(line.trim_end_matches('\r').to_string());
+                    line.clear();
+                }
+                if let Some(step) = self.current_step() {
+                    step.input = Some(lines.join("\n"));
+                }
+                self.assign(name, Value::List(items));
+            }
+            Statement::Ask { name, kind, .. } => {
+                self.write(&format!("Enter {}: ", name))?;
+                self.output.flush()?;
+                let mut line = String::new();
+                let read = self.read_line(&mut line)?;
+                if let Some(step) = self.current_step().filter(|_| read > 0) {
+                    step.input = Some(line.trim_end_matches('\r').to_string());
+                }
+                let value = match kind {
+                    Kind::Number => match parse_leading_number(&line) {
+                        Some(number) if read > 0 => Value::Number(number),
+                        _ => anyhow::bail!("Expected a number for {}", name),
+                    },
+                    _ => Value::Text(line),
+                };
+                self.assign(name, value);
+            }
+            Statement::Set { name, value, .. } => {
+                let value = self.evaluate(value)?;
+                self.assign(name, Value::Number(value));
+            }
+            Statement::PrintText(name) => {
+                let text = self.variable(name)?.to_string();
+                self.write(&format!("{}\n", text))?;
+            }
+            Statement::PrintNumber(value) => {
+                let number = self.evaluate(value)?;
+                self.write(&format!("{}\n", format_g(number)))?;
+            }
+            Statement::ListFolder(path) => {
+                let path = self.path(path)?;
+                let entries = fs::read_dir(&path).ok().with_context(|| format!("Can't open the folder {}", path))?;
+                let mut names = entries
+                    .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                    .collect::<io::Result<Vec<String>>>()
+                    .with_context(|| format!("Can't open the folder {}", path))?;
+                names.sort();
+                for name in names {
+                    self.write(&format!("{}\n", name))?;
+                }
+            }
+            Statement::CreateFolder(path) => {
+                let path = self.path(path)?;
+                match fs::create_dir(&path) {
+                    Err(e) if e.kind() != io::ErrorKind::AlreadyExists => anyhow::bail!("Can't create the folder {}", path),
+                    _ => {}
+                }
+            }
+            Statement::PrintExists(path) => {
+                let exists = Path::new(&self.path(path)?).exists();
+                self.write(if exists { "yes\n" } else { "no\n" })?;
+            }
+            Statement::Join { name, folder, file, .. } => {
+                let (folder, file) = (self.path(folder)?, self.path(file)?);
+                let separator = if folder.is_empty() || folder.ends_with('/') { "" } else { "/" };
+                let mut joined = format!("{}{}{}", folder, separator, file);
+                truncate(&mut joined);
+                self.assign(name, Value::Text(joined));
+            }
+            Statement::PrintIfMatches { name, regex, then, otherwise } => {
+                let text = self.variable(name)?.to_string();
+                let pieces = if matches(&text, regex)? { Some(then) } else { otherwise.as_ref() };
+                if let Some(pieces) = pieces {
+                    let text = self.fill(pieces)?;
+                    self.write(&format!("{}\n", text))?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Read a line as the native build's fgets does into a 256-byte buffer, dropping
+    /// the newline and anything past the buffer; returns the bytes read, 0 at the end of input
+    fn read_line(&mut self, line: &mut String) -> Result<usize> {
+        let read = self.input.read_line(line)?;
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        truncate(line);
+        Ok(read)
+    }
+    
+    /// Text with its `{variable}` placeholders filled in
+    fn fill(&self, pieces: &[Piece]) -> Result<String> {
+        let mut text = String::new();
+        for piece in pieces {
+            match piece {
+                Piece::Text(part) => text.push_str(part),
+                Piece::Variable(name, _) => text.push_str(&self.variable(name)?.to_string()),
+            }
+        }
+        Ok(text)
+    }
+    
+    fn path(&self, path: &PathPart) -> Result<String> {
+        match path {
+            PathPart::Literal(literal) => Ok(literal.clone()),
+            PathPart::Variable(name) => Ok(self.variable(name)?.to_string()),
+        }
+    }
+    
+    /// The step being recorded, if recording
+    fn current_step(&mut self) -> Option<&mut Transition> {
+        self.trace.as_mut()?.last_mut()
+    }
+    
+    fn write(&mut self, text: &str) -> Result<()> {
+        self.output.write_all(text.as_bytes())?;
+        if let Some(step) = self.current_step() {
+            step.output.push_str(text);
+        }
+        Ok(())
+    }
+    
+    fn assign(&mut self, name: &str, value: Value) {
+        if let Some(step) = self.current_step() {
+            step.changes.push(Change { name: name.to_string(), value: value.clone() });
+        }
+        if self.watched.contains(name) {
+            let sentence = &self.sentences[self.next - 1];
+            let shown = |value: &Value| match value {
+                Value::Number(_) => value.to_string(),
+                Value::Text(text) => format!("{:?}", text),
+                Value::List(_) => value.to_string(),
+            };
+            match self.variables.get(name) {
+                Some(old) if *old == value => {}
+                Some(old) => eprintln!("watch: {} = {}, was {} (line {}: {})", name, shown(&value), shown(old), sentence.line, sentence.text),
+                None => eprintln!("watch: {} = {} (line {}: {})", name, shown(&value), sentence.line, sentence.text),
+            }
+        }
+        self.variables.insert(name.to_string(), value);
+    }
+    
+    fn variable(&self, name: &str) -> Result<&Value> {
+        self.variables.get(name).with_context(|| format!("{} is used before it is given a value", name))
+    }
+    
+    fn evaluate(&self, expr: &Expr) -> Result<f64> {
+        let operand = |operand: &Operand| -> Result<f64> {
+            match operand {
+                Operand::Literal(literal) => Ok(*literal),
+                Operand::Variable(name) => match self.variable(name)? {
+                    Value::Number(number) => Ok(*number),
+                    Value::Text(_) => anyhow::bail!("{} holds text, not a number", name),
+                    Value::List(_) => anyhow::bail!("{} holds a list, not a number", name),
+                },
+            }
+        };
+        let (a, op, b) = match expr {
+            Expr::Value(value) => return operand(value),
+            Expr::Binary(a, op, b) => (operand(a)?, *op, operand(b)?),
+            Expr::Reduce { reduction, list, filter } => {
+                let Value::List(items) = self.variable(list)? else {
+                    anyhow::bail!("{} doesn't hold a list", list);
+                };
+                let filter = match filter {
+                    Some((comparison, bound)) => Some((*comparison, operand(bound)?)),
+                    None => None,
+                };
+                return reduce(items, *reduction, filter, list);
+            }
+        };
+        Ok(match op {
+            Op::Add => a + b,
+            Op::Subtract => a - b,
+            Op::Multiply => a * b,
+            Op::Divide => a / b,
+            Op::Remainder => {
+                // Conversions saturate and NaN becomes 0, as the native build's whole() does
+                let (a, b) = (a as i64, b as i64);
+                match b {
+                    0 => anyhow::bail!("Remainder after dividing by zero"),
+                    -1 => 0.0,
+                    b => (a % b) as f64,
+                }
+            }
+        })
+    }
+}
+
+/// A list reduced as the native build's reduce_numbers does, adding in the same order
+fn reduce(items: &[f64], reduction: Reduction, filter: Option<(Comparison, f64)>, name: &str) -> Result<f64> {
+    let every = filter.is_none();
+    if every && (reduction == Reduction::Sum || (reduction == Reduction::Average && !items.is_empty())) {
+        let mut lanes = [0.0; 4];
+        for (index, item) in items.iter().enumerate() {
+            lanes[index % 4] += item;
+        }
+        let sum = (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]);
+        return Ok(if reduction == Reduction::Sum { sum } else { sum / items.len() as f64 });
+    }
+    let passing: Vec<f64> = items
+        .iter()
+        .copied()
+        .filter(|item| filter.is_none_or(|(comparison, bound)| comparison.holds(*item, bound)))
+        .collect();
+    let best = |better: fn(f64, f64) -> bool| passing.iter().copied().reduce(|best, item| if better(item, best) { item } else { best });
+    let value = match reduction {
+        Reduction::Sum => Some(passing.iter().fold(0.0, |total, item| total + item)),
+        Reduction::Average => (!passing.is_empty()).then(|| passing.iter().fold(0.0, |total, item| total + item) / passing.len() as f64),
+        Reduction::Minimum => best(|item, best| item < best),
+        Reduction::Maximum => best(|item, best| item > best),
+        Reduction::Count => Some(passing.len() as f64),
+    };
+    value.with_context(|| format!("No numbers in {} to reduce", name))
+}
+
+/// Cut text to what the native build's 256-byte buffers hold, at a character boundary
+fn truncate(text: &mut String) {
+    if text.len() > LINE_LIMIT {
+        let end = (0..=LINE_LIMIT).rev().find(|end| text.is_char_boundary(*end)).unwrap_or(0);
+        text.truncate(end);
+    }
+}
+
+/// Whether a line ends list input, as the native build's `line[strspn(line, " \t\r\n")] == '\0'` decides
+///
+/// Only those four characters count as blank, so a line of other whitespace,
+/// like a vertical tab or a no-break space, is read as a number.
+fn is_blank(line: &str) -> bool {
+    matches!(line.trim_start_matches([' ', '\t', '\r', '\n']).as_bytes().first(), None | Some(0))
+}
+
+/// Whether `text` matches a POSIX extended regular expression, using libc's
+/// regcomp and regexec as the native build does
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn matches(text: &str, pattern: &str) -> Result<bool> {
+    use std::ffi::CString;
+    let c_pattern = CString::new(pattern).ok().with_context(|| format!("Invalid pattern {}", pattern))?;
+    // Text holding a NUL ends there in C, too
+    let c_text = CString::new(text.split('\0').next().unwrap_or_default()).unwrap_or_default();
+    // SAFETY: regex_t is plain data that regcomp initializes; it is freed only after a successful regcomp
+    unsafe {
+        let mut compiled: libc::regex_t = std::mem::zeroed();
+        if libc::regcomp(&mut compiled, c_pattern.as_ptr(), libc::REG_EXTENDED | libc::REG_NOSUB) != 0 {
+            anyhow::bail!("Invalid pattern {}", pattern);
+        }
+        let found = libc::regexec(&compiled, c_text.as_ptr(), 0, std::ptr::null_mut(), 0) == 0;
+        libc::regfree(&mut compiled);
+        Ok(found)
+    }
+}
+
+/// Whether `text` matches a regular expression; without libc's regex functions,
+/// the regex crate stands in, which agrees on the POSIX syntax patterns allow
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn matches(text: &str, pattern: &str) -> Result<bool> {
+    let compiled = regex::Regex::new(pattern).with_context(|| format!("Invalid pattern {}", pattern))?;
+    Ok(compiled.is_match(text))
+}
+
+/// The number at the start of `line`, as C's strtod reads it in the C locale
+///
+/// Leading whitespace is skipped; then comes an optional sign and either
+/// `inf`, `infinity`, or `nan`, a hexadecimal number like `0x1.8p3`, or a
+/// decimal one. The longest prefix that forms a number is taken, so `12abc`
+/// reads 12 and `1e` reads 1.
+fn parse_leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start_matches([' ', '\t', '\n', '\x0b', '\x0c', '\r']);
+    let (negative, rest) = match line.as_bytes().first() {
+        Some(b'-') => (true, &line[1..]),
+        Some(b'+') => (false, &line[1..]),
+        _ => (false, line),
+    };
+    let lower = rest.get(..rest.len().min(8)).unwrap_or(rest).to_ascii_lowercase();
+    let magnitude = if lower.starts_with("inf") {
+        f64::INFINITY
+    } else if lower.starts_with("nan") {
+        f64::NAN
+    } else if lower.starts_with("0x") && parse_hex(&rest[2..]).is_some() {
+        parse_hex(&rest[2..])?
+    } else {
+        parse_decimal(rest)?
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+/// The decimal number at the start of `text`: digits with an optional fraction and exponent
+fn parse_decimal(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let whole = digits(0);
+    let mut end = whole;
+    let mut fraction = 0;
+    if bytes.get(end) == Some(&b'.') {
+        fraction = digits(end + 1);
+        end += 1 + fraction;
+    }
+    if whole + fraction == 0 {
+        return None;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits(end + 1 + sign);
+        if exponent > 0 {
+            end += 1 + sign + exponent;
+        }
+    }
+    text[..end].parse().ok()
+}
+
+/// The hexadecimal number after `0x` at the start of `text`, with an optional
+/// fraction and binary exponent, or None when no hex digit follows
+fn parse_hex(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    let mut mantissa = 0u128;
+    let mut scale = 0i32;
+    let mut seen = false;
+    let mut index = 0;
+    let mut fraction = false;
+    while let Some(&byte) = bytes.get(index) {
+        if byte == b'.' && !fraction {
+            fraction = true;
+        } else if let Some(digit) = (byte as char).to_digit(16) {
+            seen = true;
+            // Digits past what the mantissa holds only matter for rounding, which this ignores
+            if mantissa >> 120 == 0 {
+                mantissa = mantissa << 4 | digit as u128;
+                scale -= if fraction { 4 } else { 0 };
+            } else if !fraction {
+                scale += 4;
+            }
+        } else {
+            break;
+        }
+        index += 1;
+    }
+    if !seen {
+        return None;
+    }
+    if matches!(bytes.get(index), Some(b'p' | b'P')) {
+        let rest = &text[index + 1..];
+        let sign_length = usize::from(rest.starts_with(['+', '-']));
+        let exponent_digits = rest[sign_length..].bytes().take_while(u8::is_ascii_digit).count();
+        if exponent_digits > 0 {
+            let exponent: i32 = rest[..sign_length + exponent_digits].parse().unwrap_or(if rest.starts_with('-') { i32::MIN / 2 } else { i32::MAX / 2 });
+            scale = scale.saturating_add(exponent);
+        }
+    }
+    Some(mantissa as f64 * 2f64.powi(scale))
+}
+
+/// A number as C's printf `%g` writes it: six significant digits, trailing
+/// zeros removed, and an exponent for very large or small magnitudes
+pub fn format_g(value: f64) -> String {
+    if value.is_nan() {
+        return if value.is_sign_negative() { "-nan" } else { "nan" }.to_string();
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0" } else { "0" }.to_string();
+    }
+    
+    // Rounding to six significant digits decides the exponent
+    let scientific = format!("{:.5e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let trim = |digits: &str| {
+        if digits.contains('.') {
+            digits.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            digits.to_string()
+        }
+    };
+    if !(-4..6).contains(&exponent) {
+        format!("{}e{}{:02}", trim(mantissa), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    } else {
+        trim(&format!("{:.*}", (5 - exponent) as usize, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leading_number_is_read_as_strtod_reads_it() {
+        assert_eq!(parse_leading_number("12abc"), Some(12.0));
+        assert_eq!(parse_leading_number(" \t-3.5e2x"), Some(-350.0));
+        assert_eq!(parse_leading_number("1e"), Some(1.0));
+        assert_eq!(parse_leading_number("+.5"), Some(0.5));
+        assert_eq!(parse_leading_number("0x1.8p3"), Some(12.0));
+        assert_eq!(parse_leading_number("0xg"), Some(0.0));
+        assert_eq!(parse_leading_number("-Infinity"), Some(f64::NEG_INFINITY));
+        assert!(parse_leading_number("nan").is_some_and(f64::is_nan));
+        assert_eq!(parse_leading_number("."), None);
+        assert_eq!(parse_leading_number("abc"), None);
+    }
+
+    #[test]
+    fn numbers_are_formatted_as_printf_g() {
+        assert_eq!(format_g(0.1 + 0.2), "0.3");
+        assert_eq!(format_g(2.5), "2.5");
+        assert_eq!(format_g(100000.0), "100000");
+        assert_eq!(format_g(1000000.0), "1e+06");
+        assert_eq!(format_g(123456789.0), "1.23457e+08");
+        assert_eq!(format_g(0.0001), "0.0001");
+        assert_eq!(format_g(0.00001), "1e-05");
+        assert_eq!(format_g(-0.0), "-0");
+        assert_eq!(format_g(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format_g(f64::NAN), "nan");
+    }
+
+    #[test]
+    fn only_spaces_tabs_and_line_ends_are_blank() {
+        assert!(is_blank(""));
+        assert!(is_blank("\n"));
+        assert!(is_blank(" \t\r\n"));
+        assert!(is_blank("  \0 7\n"));
+        assert!(!is_blank(" 7\n"));
+        assert!(!is_blank("\x0b\n"));
+        assert!(!is_blank("\x0c\n"));
+        assert!(!is_blank("\u{a0}\n"));
+    }
+
+    #[test]
+    fn long_lines_are_cut_at_a_character_boundary() {
+        let mut ascii = "a".repeat(300);
+        truncate(&mut ascii);
+        assert_eq!(ascii.len(), LINE_LIMIT);
+        let mut accented = "é".repeat(200);
+        truncate(&mut accented);
+        assert_eq!(accented.len(), 254);
+        let mut short = "short".to_string();
+        truncate(&mut short);
+        assert_eq!(short, "short");
+    }
+
+    #[test]
+    fn patterns_are_posix_extended() {
+        assert!(matches("abc123", "^[a-z]+[0-9]+$").unwrap());
+        assert!(!matches("abc", "^[0-9]+$").unwrap());
+        assert!(matches("a(b", "[(]").unwrap());
+        assert!(matches("text", "(").is_err());
+    }
+}
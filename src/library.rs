@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fmt::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::LazyLock;
+
+/// Tool that bundles object files into a static library
+const AR: &str = "ar";
+
+/// Kind of library built instead of an executable
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryKind {
+    /// A shared library (.so, .dylib, or .dll) loadable from C, C++, or Python
+    Shared,
+    /// A static library (.a or .lib) linked into the caller's executable
+    Static,
+}
+
+/// C function definition at the start of a line, e.g. `double area(double w, double h) {`
+const C_FUNCTION: &str = r"(?m)^(?P<ret>[A-Za-z_][\w \t]*?[\w\*][ \t\*]*?)(?P<name>[A-Za-z_]\w*)[ \t]*\((?P<params>[^;{}()]*)\)[ \t]*\{?[ \t]*$";
+
+/// Exported Rust function, e.g. `pub extern "C" fn area(w: f64, h: f64) -> f64 {`
+const RUST_FUNCTION: &str = r#"pub\s+(?:unsafe\s+)?extern\s+"C"\s+fn\s+(?P<name>\w+)\s*\((?P<params>[^)]*)\)\s*(?:->\s*(?P<ret>[^{]+?))?\s*\{"#;
+
+static C_FUNCTION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(C_FUNCTION).expect("C function pattern is valid"));
+static RUST_FUNCTION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(RUST_FUNCTION).expect("Rust function pattern is valid"));
+
+/// Words that start a line like a function definition but never declare one
+const NOT_A_RETURN_TYPE: &[&str] = &["static", "typedef", "return", "else", "if", "while", "for", "switch", "do"];
+
+impl LibraryKind {
+    /// File name of the library for `program_name` on `target`, or the host when None
+    pub fn file_name(self, program_name: &str, target: Option<&str>) -> String {
+        let (windows, macos) = match target {
+            Some(target) => (target.contains("windows"), target.contains("darwin") || target.contains("apple")),
+            None => (cfg!(windows), cfg!(target_os = "macos")),
+        };
+        match self {
+            LibraryKind::Shared if windows => format!("{}.dll", program_name),
+            LibraryKind::Shared if macos => format!("lib{}.dylib", program_name),
+            LibraryKind::Shared => format!("lib{}.so", program_name),
+            LibraryKind::Static if windows => format!("{}.lib", program_name),
+            LibraryKind::Static => format!("lib{}.a", program_name),
+        }
+    }
+    
+    /// Toolchain arguments building this kind of library from `language`
+    pub fn driver_args(self, language: &str) -> Vec<String> {
+        let args: &[&str] = match (self, language) {
+            (LibraryKind::Shared, "rust") => &["--crate-type", "cdylib"],
+            (LibraryKind::Shared, _) => &["-shared", "-fPIC"],
+            (LibraryKind::Static, "rust") => &["--crate-type", "staticlib"],
+            // The object is archived afterwards; PIC lets callers link it into PIEs
+            (LibraryKind::Static, _) => &["-c", "-fPIC"],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+    
+    /// Libraries callers must also link, beyond the arguments given to nhlp
+    ///
+    /// A Rust static library carries the standard library but not the system
+    /// libraries it uses; these are the ones rustc reports for Linux.
+    pub fn native_libs(self, language: &str) -> &'static [&'static str] {
+        match (self, language) {
+            (LibraryKind::Static, "rust") => &["-lgcc_s", "-lutil", "-lrt", "-lpthread", "-lm", "-ldl", "-lc"],
+            _ => &[],
+        }
+    }
+}
+
+/// Bundle the object file at `path` into a static library in place
+pub fn archive(path: &Path) -> Result<()> {
+    let object = path.with_extension("o");
+    std::fs::rename(path, &object)
+        .with_context(|| format!("Failed to move the object file to {:?}", object))?;
+    let status = Command::new(AR)
+        .arg("rcs")
+        .arg(path)
+        .arg(&object)
+        .status()
+        .with_context(|| format!("Failed to run {}; install binutils to build static libraries", AR));
+    let _ = std::fs::remove_file(&object);
+    if !status?.success() {
+        anyhow::bail!("{} failed to create {:?}", AR, path);
+    }
+    Ok(())
+}
+
+/// A pkg-config file locating a library in `dir` and its header, so build
+/// systems can find them with `pkg-config --cflags --libs <name>`
+pub fn pkg_config(program_name: &str, dir: &Path, source: &str, libs: &[String]) -> String {
+    let mut pc = String::new();
+    // pkg-config splits Libs and Cflags into arguments like a shell would, so spaces and quotes in
+    // the prefix are escaped, as is `#`, which would start a comment
+    let mut prefix = String::new();
+    for c in dir.display().to_string().chars() {
+        if matches!(c, '\\' | '"' | '\'' | '#') || c.is_whitespace() {
+            prefix.push('\\');
+        }
+        prefix.push(c);
+    }
+    let _ = writeln!(pc, "prefix={}", prefix);
+    pc.push_str("libdir=${prefix}\nincludedir=${prefix}\n\n");
+    let _ = writeln!(pc, "Name: {}", program_name);
+    let _ = writeln!(pc, "Description: Functions compiled by nhlp from {}", source);
+    // Specs have no version of their own
+    pc.push_str("Version: 0\n");
+    let _ = writeln!(pc, "Libs: -L${{libdir}} -l{}{}", program_name, libs.iter().map(|lib| format!(" {}", lib)).collect::<String>());
+    pc.push_str("Cflags: -I${includedir}\n");
+    pc
+}
+
+/// Instructions added to the translation prompt when building a library
+///
+/// The spec describes functions for other programs to call instead of a
+/// program to run, so the model is asked for exported functions with C
+/// types and no `main`.
+pub fn prompt_note(language: &str) -> &'static str {
+    if language == "rust" {
+        "\nBUILD A LIBRARY, NOT A PROGRAM: do not write a main function and do not read input or print \
+         unless a function is described as doing so. Write each function the program describes for callers \
+         as `#[no_mangle] pub extern \"C\" fn` using only C-compatible parameter and return types \
+         (i32, i64, u32, u64, f32, f64, bool, *const c_char, or raw pointers to them). \
+         Keep helpers private.\n"
+    } else {
+        "\nBUILD A LIBRARY, NOT A PROGRAM: do not write a main function and do not read input or print \
+         unless a function is described as doing so. Write each function the program describes for callers \
+         as a non-static function using only standard C types (int, long, double, bool, const char *, \
+         or pointers to them), with the whole signature on one line. Declare every helper static.\n"
+    }
+}
+
+/// A C header declaring the functions `code` exports, for callers of the library
+///
+/// Only exported functions whose types C can express are declared: non-static
+/// C functions, or Rust `extern "C"` functions with primitive and pointer types.
+/// Returns the header and the names of the exported functions it had to skip.
+pub fn header(program_name: &str, code: &str, language: &str) -> (String, Vec<String>) {
+    let (declarations, skipped) = if language == "rust" {
+        rust_declarations(code)
+    } else {
+        (c_declarations(code), Vec::new())
+    };
+    
+    let guard: String = program_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    let mut header = String::new();
+    let _ = writeln!(header, "/* Generated by nhlp {} for lib{} */", env!("CARGO_PKG_VERSION"), program_name);
+    let _ = writeln!(header, "#ifndef {}_H", guard);
+    let _ = writeln!(header, "#define {}_H\n", guard);
+    header.push_str("#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n\n");
+    header.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+    for declaration in &declarations {
+        let _ = writeln!(header, "{};", declaration);
+    }
+    header.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n");
+    let _ = writeln!(header, "#endif /* {}_H */", guard);
+    (header, skipped)
+}
+
+fn c_declarations(code: &str) -> Vec<String> {
+    C_FUNCTION_REGEX
+        .captures_iter(code)
+        .filter(|captures| {
+            let ret = captures["ret"].trim();
+            let first = ret.split_whitespace().next().unwrap_or_default();
+            &captures["name"] != "main" && !NOT_A_RETURN_TYPE.contains(&first)
+        })
+        .map(|captures| {
+            let params = captures["params"].trim();
+            format!(
+                "{} {}({})",
+                captures["ret"].trim(),
+                &captures["name"],
+                if params.is_empty() { "void" } else { params }
+            )
+        })
+        .collect()
+}
+
+fn rust_declarations(code: &str) -> (Vec<String>, Vec<String>) {
+    let mut declarations = Vec::new();
+    let mut skipped = Vec::new();
+    for captures in RUST_FUNCTION_REGEX.captures_iter(code) {
+        let name = &captures["name"];
+        let ret = match captures.name("ret") {
+            Some(ret) => c_type(ret.as_str()),
+            None => Some("void".to_string()),
+        };
+        let params: Option<Vec<String>> = captures["params"]
+            .split(',')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (param_name, param_type) = param.split_once(':')?;
+                Some(format!("{} {}", c_type(param_type)?, param_name.trim().trim_start_matches("mut ")))
+            })
+            .collect();
+        match (ret, params) {
+            (Some(ret), Some(params)) => declarations.push(format!(
+                "{} {}({})",
+                ret,
+                name,
+                if params.is_empty() { "void".to_string() } else { params.join(", ") }
+            )),
+            _ => skipped.push(name.to_string()),
+        }
+    }
+    (declarations, skipped)
+}
+
+/// The C spelling of a primitive or pointer Rust type
+fn c_type(rust: &str) -> Option<String> {
+    let rust = rust.trim();
+    if let Some(pointee) = rust.strip_prefix("*const") {
+        return Some(format!("const {} *", c_type(pointee)?));
+    }
+    if let Some(pointee) = rust.strip_prefix("*mut") {
+        return Some(format!("{} *", c_type(pointee)?));
+    }
+    let c = match rust.rsplit("::").next().unwrap_or(rust) {
+        "()" | "c_void" => "void",
+        "bool" => "bool",
+        "i8" | "c_char" => "char",
+        "u8" => "uint8_t",
+        "i16" => "int16_t",
+        "u16" => "uint16_t",
+        "i32" | "c_int" => "int32_t",
+        "u32" | "c_uint" => "uint32_t",
+        "i64" | "c_long" | "c_longlong" => "int64_t",
+        "u64" | "c_ulong" | "c_ulonglong" => "uint64_t",
+        "isize" => "ptrdiff_t",
+        "usize" => "size_t",
+        "f32" | "c_float" => "float",
+        "f64" | "c_double" => "double",
+        _ => return None,
+    };
+    Some(c.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkg_config_escapes_the_prefix_for_splitting() {
+        let pc = pkg_config("area", Path::new("/tmp/my libs/$HOME#1/it's"), "area.dshp", &["-lm".to_string()]);
+        assert!(pc.starts_with("prefix=/tmp/my\\ libs/$HOME\\#1/it\\'s\n"));
+        assert!(pc.contains("Libs: -L${libdir} -larea -lm\n"));
+        assert!(pc.contains("Cflags: -I${includedir}\n"));
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Tool that removes symbols and sections from a linked executable
+const STRIP: &str = "strip";
+
+/// What to remove from the executable after linking
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strip {
+    /// Debug information only; the symbol table stays for backtraces and profilers
+    Debuginfo,
+    /// The symbol table, debug information, and the compiler version notes
+    Symbols,
+}
+
+/// System linker the compiler driver invokes
+#[derive(clap::ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Linker {
+    /// Whatever linker the compiler driver uses by default, usually GNU ld
+    Cc,
+    /// LLVM's linker
+    Lld,
+    /// The mold linker, fastest on large programs
+    Mold,
+}
+
+/// How the generated program is linked into an executable
+///
+/// The C compiler driver or rustc performs the link itself, invoking the
+/// system linker with the right startup objects and libc; these options are
+/// translated into the driver flags that adjust that step.
+#[derive(Debug, Clone, Default)]
+pub struct LinkOptions {
+    /// Extra arguments for the linker driver, e.g. `-lm` or `-Wl,--as-needed`
+    pub args: Vec<String>,
+    
+    /// Linker to use instead of the driver's default
+    pub linker: Option<Linker>,
+    
+    /// Link libc and every other library statically
+    pub static_link: bool,
+    
+    /// Optimize across the whole program at link time
+    pub lto: bool,
+    
+    /// Remove debug information or symbols once the executable is linked
+    pub strip: Option<Strip>,
+    
+    /// Symbols to keep when stripping, e.g. ones a crash reporter looks up
+    pub keep_symbols: Vec<String>,
+}
+
+impl LinkOptions {
+    /// Toolchain arguments applying these options when linking a program in `language`
+    pub fn driver_args(&self, language: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let use_linker = match self.linker {
+            Some(Linker::Lld) => Some("-fuse-ld=lld"),
+            Some(Linker::Mold) => Some("-fuse-ld=mold"),
+            Some(Linker::Cc) | None => None,
+        };
+        if language == "rust" {
+            // rustc links through cc, which picks the linker
+            if let Some(flag) = use_linker {
+                args.extend(["-C".to_string(), format!("link-arg={}", flag)]);
+            }
+            if self.static_link {
+                args.extend(["-C".to_string(), "target-feature=+crt-static".to_string()]);
+            }
+            if self.lto {
+                args.extend(["-C".to_string(), "lto".to_string()]);
+            }
+            for arg in &self.args {
+                args.extend(["-C".to_string(), format!("link-arg={}", arg)]);
+            }
+        } else {
+            if let Some(flag) = use_linker {
+                args.push(flag.to_string());
+            }
+            if self.static_link {
+                args.push("-static".to_string());
+            }
+            if self.lto {
+                args.push("-flto".to_string());
+            }
+            // After the source file, so libraries resolve its references
+            args.extend(self.args.iter().cloned());
+        }
+        args
+    }
+    
+    /// Arguments for `strip` applying these options, or None when nothing is stripped
+    pub fn strip_args(&self) -> Option<Vec<String>> {
+        let mut args = match self.strip? {
+            Strip::Debuginfo => vec!["--strip-debug".to_string()],
+            // The .comment section names the compiler versions that built the program
+            Strip::Symbols => vec!["--strip-all".to_string(), "--remove-section=.comment".to_string()],
+        };
+        args.extend(self.keep_symbols.iter().map(|symbol| format!("--keep-symbol={}", symbol)));
+        Some(args)
+    }
+}
+
+/// Strip a linked executable in place, returning its size before and after
+pub fn strip(executable: &Path, args: &[String]) -> Result<(u64, u64)> {
+    let before = fs::metadata(executable)?.len();
+    let status = Command::new(STRIP)
+        .args(args)
+        .arg(executable)
+        .status()
+        .with_context(|| format!("Failed to run {}; install binutils to use --strip", STRIP))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} failed on {:?} ({})", STRIP, executable, status));
+    }
+    Ok((before, fs::metadata(executable)?.len()))
+}
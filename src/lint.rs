@@ -0,0 +1,129 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::policy::Level;
+
+/// Name that sets the level of every lint at once
+const ALL_WARNINGS: &str = "warnings";
+
+/// Classes of warning the compiler can raise about a program, each with a stable code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    /// Generated code uses a construct the construct policy warns about
+    UnsafeConstruct,
+    /// The translation came from a fallback model rather than the primary one
+    ModelDegraded,
+    /// `--check-acceptance` found no criteria in the spec
+    NoAcceptanceCriteria,
+    /// A model-proposed edge-case input made the program fail
+    CounterexampleFailed,
+    /// The program crashed, timed out, or exited with a non-zero status
+    ProgramFailed,
+    /// `--coverage` found a sentence whose code never ran
+    UncoveredSentence,
+    /// The spec describes reacting to a signal, but the generated code installs no handler
+    UnhandledSignal,
+    /// The generated code recurses, so no stack size is known to be enough
+    UnboundedRecursion,
+    /// The generated code's deepest call chain needs more stack than the program gets
+    StackOverflow,
+    /// Arithmetic in the spec may divide by zero, or always overflows
+    UnsafeArithmetic,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 10] = [
+        Lint::UnsafeConstruct,
+        Lint::ModelDegraded,
+        Lint::NoAcceptanceCriteria,
+        Lint::CounterexampleFailed,
+        Lint::ProgramFailed,
+        Lint::UncoveredSentence,
+        Lint::UnhandledSignal,
+        Lint::UnboundedRecursion,
+        Lint::StackOverflow,
+        Lint::UnsafeArithmetic,
+    ];
+    
+    /// Stable diagnostic code; codes are never reused or renumbered
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnsafeConstruct => "NH0001",
+            Lint::ModelDegraded => "NH0002",
+            Lint::NoAcceptanceCriteria => "NH0003",
+            Lint::CounterexampleFailed => "NH0004",
+            Lint::ProgramFailed => "NH0005",
+            Lint::UncoveredSentence => "NH0006",
+            Lint::UnhandledSignal => "NH0007",
+            Lint::UnboundedRecursion => "NH0008",
+            Lint::StackOverflow => "NH0009",
+            Lint::UnsafeArithmetic => "NH0010",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Lint::UnsafeConstruct => "unsafe-construct",
+            Lint::ModelDegraded => "model-degraded",
+            Lint::NoAcceptanceCriteria => "no-acceptance-criteria",
+            Lint::CounterexampleFailed => "counterexample-failed",
+            Lint::ProgramFailed => "program-failed",
+            Lint::UncoveredSentence => "uncovered-sentence",
+            Lint::UnhandledSignal => "unhandled-signal",
+            Lint::UnboundedRecursion => "unbounded-recursion",
+            Lint::StackOverflow => "stack-overflow",
+            Lint::UnsafeArithmetic => "unsafe-arithmetic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Lint {
+    type Err = anyhow::Error;
+    
+    /// Parse a lint by name (`program-failed`) or code (`NH0005`)
+    fn from_str(s: &str) -> Result<Self> {
+        Lint::ALL
+            .into_iter()
+            .find(|lint| lint.to_string() == s || lint.code().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow::anyhow!("Unknown lint: {}", s))
+    }
+}
+
+/// Allow/warn/deny levels for each lint, set from the command line
+///
+/// A level for `warnings` applies to every lint; a level for a specific
+/// lint takes precedence over it, so `--deny warnings --allow model-degraded`
+/// denies everything else.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    all: Option<Level>,
+    overrides: BTreeMap<Lint, Level>,
+}
+
+impl LintLevels {
+    /// Parse the lints named by `--allow`, `--warn`, and `--deny`
+    pub fn from_flags(allow: &[String], warn: &[String], deny: &[String]) -> Result<Self> {
+        let mut levels = Self::default();
+        for (names, level) in [(allow, Level::Allow), (warn, Level::Warn), (deny, Level::Deny)] {
+            for name in names {
+                let name = name.trim();
+                if name == ALL_WARNINGS {
+                    levels.all = Some(level);
+                } else {
+                    levels.overrides.insert(name.parse()?, level);
+                }
+            }
+        }
+        Ok(levels)
+    }
+    
+    /// Level in effect for a lint
+    pub fn level(&self, lint: Lint) -> Level {
+        self.overrides.get(&lint).copied().or(self.all).unwrap_or(Level::Warn)
+    }
+}
@@ -0,0 +1,454 @@
+use anyhow::Result;
+use dotenv::dotenv;
+use log::{debug, info, warn};
+#[cfg(feature = "network")]
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::backend::LlmBackend;
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::circuit::{self, CircuitBreaker};
+use crate::offline;
+
+/// Ladder entry that translates with the offline vocabulary's rules instead of a model
+pub const OFFLINE_TIER: &str = "offline";
+
+/// Longest response a model may produce, in tokens
+pub const MAX_OUTPUT_TOKENS: usize = 8192;
+
+/// Attempts made against each model in the ladder before degrading to the next; the offline tier gets one
+const ATTEMPTS_PER_TIER: usize = 2;
+
+// A model that fails every attempt in its tier must open its circuit
+const _: () = assert!(circuit::FAILURE_THRESHOLD <= ATTEMPTS_PER_TIER);
+
+/// Text returned by demo mode
+const SIMULATED_RESPONSE: &str = "Hello, World!\nThe sum of 5 and 10 is 15.\nProgram completed successfully.";
+
+// Error types for LLM providers
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[cfg(feature = "network")]
+    #[error("API key not found. Set {0} environment variable.")]
+    ApiKeyNotFound(&'static str),
+    
+    #[cfg(feature = "network")]
+    #[error("API request failed: {0}")]
+    RequestFailed(String),
+    
+    #[error("Failed to parse API response: {0}")]
+    ParseError(String),
+    
+    #[cfg(not(feature = "network"))]
+    #[error("Built without network support. Rebuild with the `network` feature or set DSHPC_DEMO_MODE=1.")]
+    NetworkDisabled,
+    
+    #[error("Network request to {0} blocked in air-gapped mode")]
+    AirGapped(String),
+    
+    #[error("The offline tier only translates programs to C; this request needs a model")]
+    OfflineUnavailable,
+}
+
+/// An external request made while translating a program
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EgressRecord {
+    /// Endpoint the request went to, without credentials
+    pub endpoint: String,
+    pub model: String,
+    /// Size of the request body in bytes
+    pub request_bytes: usize,
+    pub duration_ms: u128,
+    /// "ok" or the error the request failed with
+    pub outcome: String,
+}
+
+/// Text produced by one entry of the model ladder
+pub struct Completion {
+    pub text: String,
+    /// Model that produced the text
+    pub model: String,
+    /// Position of that model in the ladder, 0 being the primary
+    pub tier: usize,
+    /// Every external request made to produce the text, including failed ones
+    pub egress: Vec<EgressRecord>,
+}
+
+// Client for the configured LLM provider; the underlying HTTP client pools
+// connections and is safe to share between threads
+pub struct LlmClient {
+    backend: Box<dyn LlmBackend>,
+    #[cfg(feature = "network")]
+    api_key: String,
+    #[cfg(feature = "network")]
+    client: Client,
+    demo_mode: bool,
+    air_gapped: bool,
+    ladder: Vec<String>,
+    breaker: CircuitBreaker,
+}
+
+impl LlmClient {
+    // Create a client for `backend` that falls back through `ladder` in order;
+    // an empty ladder reads NHLP_MODEL_LADDER or uses the backend's default model.
+    // In `air_gapped` mode any request that would leave the machine fails instead.
+    pub fn new(backend: Box<dyn LlmBackend>, ladder: Vec<String>, air_gapped: bool) -> Result<Self> {
+        // Load environment variables from .env file
+        dotenv().ok();
+        
+        // Check for demo mode
+        let demo_mode = env::var("DSHPC_DEMO_MODE").unwrap_or_default() == "1";
+        
+        let ladder = configured_ladder(ladder, backend.as_ref());
+        
+        // Without network support only demo mode or the offline tier can work
+        #[cfg(not(feature = "network"))]
+        if !demo_mode && !ladder.iter().any(|model| model == OFFLINE_TIER) {
+            return Err(LlmError::NetworkDisabled.into());
+        }
+        
+        if demo_mode {
+            info!("Running in demo mode - API calls will be simulated");
+        }
+        
+        // If not in demo mode, get the provider's API key from environment variables
+        #[cfg(feature = "network")]
+        let offline_only = ladder.iter().all(|model| model == OFFLINE_TIER);
+        #[cfg(feature = "network")]
+        let api_key = match backend.api_key_var() {
+            Some(var) if !demo_mode && !offline_only && !air_gapped => {
+                env::var(var).map_err(|_| LlmError::ApiKeyNotFound(var))?
+            }
+            _ => String::new(),
+        };
+        
+        // Timeouts are set on each request, since they differ by stage
+        #[cfg(feature = "network")]
+        let client = Client::builder().build()?;
+        
+        Ok(Self {
+            backend,
+            #[cfg(feature = "network")]
+            api_key,
+            #[cfg(feature = "network")]
+            client,
+            demo_mode,
+            air_gapped,
+            ladder,
+            breaker: CircuitBreaker::new(),
+        })
+    }
+
+    /// Name of the provider requests are sent to
+    pub fn provider(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Name of the primary model requests are sent to
+    pub fn model(&self) -> &str {
+        self.ladder.first().map(String::as_str).unwrap_or(self.backend.default_model())
+    }
+
+    /// Models requests fall back through, in order
+    pub fn ladder(&self) -> &[String] {
+        &self.ladder
+    }
+
+    /// Whether responses are simulated rather than generated by a model
+    pub fn is_simulated(&self) -> bool {
+        self.demo_mode
+    }
+
+    /// Execute code directly using the provider, degrading down the model ladder
+    /// when a model keeps failing
+    ///
+    /// `program` is the spec for the offline tier to translate by rule; a
+    /// request without one, or a spec outside the offline vocabulary, fails
+    /// that tier. Translation by rule is deterministic, so the offline tier
+    /// is tried once and never retried, and its failures don't open its
+    /// circuit for later specs. Each request to a model gives up after
+    /// `timeout`.
+    pub fn execute_code(&self, prompt: &str, program: Option<&str>, timeout: Duration, cancel: &CancellationToken) -> Result<Completion> {
+        debug!("Sending execution request to {}", self.backend.name());
+        
+        let mut last_error = None;
+        let mut egress = Vec::new();
+        for (tier, model) in self.ladder.iter().enumerate() {
+            if model == OFFLINE_TIER && !self.demo_mode {
+                let result = cancel.check().and_then(|()| {
+                    let program = program.ok_or(LlmError::OfflineUnavailable)?;
+                    offline::translate(program).map(|code| format!("```c\n{}```", code))
+                });
+                match result {
+                    Ok(text) => return Ok(self.completed(text, tier, egress)),
+                    Err(e) if e.is::<Cancelled>() => return Err(e),
+                    Err(e) => {
+                        warn!("{} failed: {}", model, e);
+                        last_error = Some(e);
+                        continue;
+                    }
+                }
+            }
+            
+            // Prepare the request payload
+            let simulated = self.demo_mode || model == OFFLINE_TIER;
+            let payload = self.backend.payload(model, prompt);
+            
+            for attempt in 1..=ATTEMPTS_PER_TIER {
+                // Asked before every attempt, so a failed half-open trial ends the tier
+                if !self.breaker.allow(model) {
+                    if attempt == 1 {
+                        warn!("Circuit open for {} after repeated failures, skipping", model);
+                    }
+                    break;
+                }
+                
+                let external = !simulated && !self.backend.is_local();
+                if external && self.air_gapped {
+                    return Err(LlmError::AirGapped(self.backend.endpoint(model)).into());
+                }
+                
+                // Send the request and extract the response text
+                let started = Instant::now();
+                let result = if simulated {
+                    cancel.check().map(|()| SIMULATED_RESPONSE.to_string())
+                } else {
+                    self.post(model, &payload, timeout, cancel)
+                        .and_then(|response| self.backend.extract_text(&response))
+                };
+                
+                if external {
+                    egress.push(EgressRecord {
+                        endpoint: self.backend.endpoint(model),
+                        model: model.clone(),
+                        request_bytes: payload.to_string().len(),
+                        duration_ms: started.elapsed().as_millis(),
+                        outcome: match &result {
+                            Ok(_) => "ok".to_string(),
+                            Err(e) => e.to_string(),
+                        },
+                    });
+                }
+                
+                match result {
+                    Ok(text) => {
+                        self.breaker.record_success(model);
+                        return Ok(self.completed(text, tier, egress));
+                    }
+                    Err(e) if e.is::<Cancelled>() => return Err(e),
+                    Err(e) => {
+                        self.breaker.record_failure(model);
+                        warn!("{} failed (attempt {}/{}): {}", model, attempt, ATTEMPTS_PER_TIER, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No models configured")))
+    }
+
+    /// The completion `tier` of the ladder produced
+    fn completed(&self, text: String, tier: usize, egress: Vec<EgressRecord>) -> Completion {
+        let model = self.ladder[tier].clone();
+        if tier > 0 {
+            info!("Degraded to {} (tier {} of {})", model, tier + 1, self.ladder.len());
+        }
+        info!("Execution completed successfully");
+        Completion { text, model, tier, egress }
+    }
+
+    /// Post a request payload to the provider, giving up after `timeout` or as
+    /// soon as the compilation is cancelled
+    #[cfg(feature = "network")]
+    fn post(&self, model: &str, payload: &serde_json::Value, timeout: Duration, cancel: &CancellationToken) -> Result<serde_json::Value> {
+        use std::sync::mpsc::{self, RecvTimeoutError};
+        use std::thread;
+        
+        cancel.check()?;
+        let url = self.backend.endpoint(model);
+        let headers = self.backend.headers(&self.api_key);
+        
+        // The blocking client can't be interrupted, so wait for it on a worker
+        // thread and abandon the request if cancellation arrives first
+        let client = self.client.clone();
+        let payload = payload.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(post_json(&client, &url, &headers, &payload, timeout));
+        });
+        
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(result) => return result,
+                Err(RecvTimeoutError::Timeout) => cancel.check()?,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(LlmError::RequestFailed("Request thread exited unexpectedly".to_string()).into());
+                }
+            }
+        }
+    }
+
+    /// Builds without the `network` feature cannot reach a provider
+    #[cfg(not(feature = "network"))]
+    fn post(&self, _model: &str, _payload: &serde_json::Value, _timeout: Duration, _cancel: &CancellationToken) -> Result<serde_json::Value> {
+        Err(LlmError::NetworkDisabled.into())
+    }
+}
+
+/// The model ladder to use: `ladder` if given, else NHLP_MODEL_LADDER, else the backend's default model
+pub fn configured_ladder(ladder: Vec<String>, backend: &dyn LlmBackend) -> Vec<String> {
+    if !ladder.is_empty() {
+        return ladder;
+    }
+    
+    let configured: Vec<String> = env::var("NHLP_MODEL_LADDER")
+        .unwrap_or_default()
+        .split(',')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect();
+    if configured.is_empty() {
+        vec![backend.default_model().to_string()]
+    } else {
+        configured
+    }
+}
+
+/// Check that a provider is reachable and accepts `api_key`
+#[cfg(feature = "network")]
+pub fn check_api_key(backend: &dyn LlmBackend, api_key: &str) -> Result<()> {
+    let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+    let mut request = client.get(backend.models_url());
+    for (name, value) in backend.headers(api_key) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .map_err(|e| LlmError::RequestFailed(e.without_url().to_string()))?;
+    
+    if !response.status().is_success() {
+        return Err(LlmError::RequestFailed(format!("API key rejected with status {}", response.status())).into());
+    }
+    
+    Ok(())
+}
+
+/// Post a JSON payload and parse the JSON response
+#[cfg(feature = "network")]
+fn post_json(
+    client: &Client,
+    url: &str,
+    headers: &[(&'static str, String)],
+    payload: &serde_json::Value,
+    timeout: Duration,
+) -> Result<serde_json::Value> {
+    use anyhow::Context;
+    use log::error;
+    
+    let mut request = client.post(url).json(payload).timeout(timeout);
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    let response = request
+        .send()
+        .with_context(|| format!("Failed to send request to {}", url))?;
+    
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+        error!("API request failed with status {}: {}", status, error_text);
+        return Err(LlmError::RequestFailed(error_text).into());
+    }
+    
+    // Parse the response to JSON
+    let response_json: serde_json::Value = response
+        .json()
+        .with_context(|| "Failed to parse API response")?;
+    
+    Ok(response_json)
+}
+
+#[cfg(all(test, feature = "network"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// A provider on a closed local port, counting the requests sent to it
+    struct Unreachable {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl LlmBackend for Unreachable {
+        fn name(&self) -> &'static str {
+            "unreachable"
+        }
+
+        fn default_model(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn api_key_var(&self) -> Option<&'static str> {
+            None
+        }
+
+        fn endpoint(&self, _model: &str) -> String {
+            "http://127.0.0.1:9/generate".to_string()
+        }
+
+        fn models_url(&self) -> String {
+            "http://127.0.0.1:9/models".to_string()
+        }
+
+        fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Vec::new()
+        }
+
+        fn payload(&self, _model: &str, prompt: &str) -> serde_json::Value {
+            serde_json::json!({ "prompt": prompt })
+        }
+
+        fn extract_text(&self, response: &serde_json::Value) -> Result<String> {
+            Ok(response.to_string())
+        }
+
+        fn is_local(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn half_open_model_gets_one_trial_call() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let cooldown = Duration::from_millis(50);
+        let client = LlmClient {
+            backend: Box::new(Unreachable { requests: requests.clone() }),
+            api_key: String::new(),
+            client: Client::new(),
+            demo_mode: false,
+            air_gapped: false,
+            ladder: vec!["flaky".to_string()],
+            breaker: CircuitBreaker::with_cooldown(cooldown),
+        };
+        let cancel = CancellationToken::new();
+        let timeout = Duration::from_secs(5);
+
+        assert!(client.execute_code("prompt", None, timeout, &cancel).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), ATTEMPTS_PER_TIER);
+
+        // Open: nothing is sent
+        assert!(client.execute_code("prompt", None, timeout, &cancel).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), ATTEMPTS_PER_TIER);
+
+        // Half-open: the trial fails and reopens the circuit before a second attempt
+        thread::sleep(cooldown * 2);
+        assert!(client.execute_code("prompt", None, timeout, &cancel).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), ATTEMPTS_PER_TIER + 1);
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::capabilities::Capability;
+use crate::schema::{self, Migration};
+
+/// File name of the lockfile kept next to .dshp sources
+const LOCKFILE_NAME: &str = "nhlp.lock";
+
+/// Current version of the lockfile format
+const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades from each earlier lockfile version, oldest first
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [schema::unchanged];
+
+/// Reviewed decisions about the programs in a directory
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Lockfile {
+    /// Version of the lockfile format
+    #[serde(default)]
+    schema_version: u32,
+    
+    /// Capabilities that have been reviewed and approved, per spec
+    #[serde(default)]
+    approved: BTreeMap<String, BTreeSet<Capability>>,
+    
+    /// Capabilities awaiting review, per spec
+    #[serde(default)]
+    pending: BTreeMap<String, BTreeSet<Capability>>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            approved: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Path of the lockfile governing a spec
+    pub fn path_for(spec: &Path) -> PathBuf {
+        spec.parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(LOCKFILE_NAME)
+    }
+    
+    /// Load a lockfile, treating a missing file as empty and upgrading older formats
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile: {:?}", path))?;
+        let document = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse lockfile: {:?}", path))?;
+        let document = schema::upgrade("Lockfile", document, SCHEMA_VERSION, &MIGRATIONS)
+            .with_context(|| format!("Failed to load lockfile: {:?}", path))?;
+        serde_json::from_value(document)
+            .with_context(|| format!("Failed to parse lockfile: {:?}", path))
+    }
+    
+    /// Write the lockfile back to disk
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write lockfile: {:?}", path))
+    }
+    
+    /// Capabilities used by a spec that have not been approved yet
+    pub fn unapproved(&self, spec: &Path, capabilities: &BTreeSet<Capability>) -> BTreeSet<Capability> {
+        match self.approved.get(&spec_key(spec)) {
+            Some(approved) => capabilities.difference(approved).copied().collect(),
+            None => capabilities.clone(),
+        }
+    }
+    
+    /// Record capabilities that need review before the spec may build
+    pub fn record_pending(&mut self, spec: &Path, capabilities: BTreeSet<Capability>) {
+        self.pending.entry(spec_key(spec)).or_default().extend(capabilities);
+    }
+    
+    /// Approve all pending capabilities for a spec, returning what was approved
+    pub fn approve(&mut self, spec: &Path) -> BTreeSet<Capability> {
+        let key = spec_key(spec);
+        let pending = self.pending.remove(&key).unwrap_or_default();
+        self.approved.entry(key).or_default().extend(pending.iter().copied());
+        pending
+    }
+    
+    /// Each spec's approved capabilities, keyed by file name relative to the lockfile
+    pub fn approvals(&self) -> impl Iterator<Item = (&str, &BTreeSet<Capability>)> {
+        self.approved.iter().map(|(spec, capabilities)| (spec.as_str(), capabilities))
+    }
+}
+
+/// Key a spec by its file name, relative to the lockfile's directory
+fn spec_key(spec: &Path) -> String {
+    spec.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
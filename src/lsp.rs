@@ -0,0 +1,408 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+
+use crate::acceptance;
+use crate::check;
+use crate::glossary::Glossary;
+
+/// Phrases that introduce a named value, e.g. `ask for a number called width` or `set total to ...`
+const DEFINITION_PATTERN: &str = r"(?i)\b(?:called|named|call\s+it|set|let|store\s+it\s+(?:in|as)|save\s+it\s+(?:in|as))\s+(?P<name>[A-Za-z_]\w*)";
+
+/// Words in a defining sentence that say what kind of value it holds
+const NUMBER_WORDS: &str = r"(?i)\b(?:number|integer|amount|value|age|price|count|total|sum|product)\b";
+const TEXT_WORDS: &str = r"(?i)\b(?:name|word|text|string|line|message)\b";
+
+/// Largest message body read, far more than any spec; a longer one is skipped without being read into memory
+const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
+
+/// LSP severities
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+const INFORMATION: u8 = 3;
+
+/// Serve the Language Server Protocol on stdin/stdout until the client exits
+///
+/// Only local analysis runs here, the same as `nhlp check`: nothing is sent
+/// to a model as the user types. Diagnostics cover vague wording, missing
+/// acceptance criteria, and an invalid glossary; hover shows glossary
+/// meanings and where a named value was introduced; go-to-definition jumps
+/// to that sentence.
+pub fn run() -> Result<()> {
+    info!("nhlp language server started");
+    serve(&mut io::stdin().lock(), &mut io::stdout().lock())
+}
+
+/// Answer the messages read from `input` on `output` until an `exit` or the end of input
+fn serve(input: &mut impl BufRead, output: &mut impl Write) -> Result<()> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("{:#}", e);
+                let reply = json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": format!("{:#}", e)}});
+                write_message(output, &reply)?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        debug!("LSP message: {}", method);
+        if method == "exit" {
+            break;
+        }
+        for reply in server.handle(method, message.get("id").cloned(), &message["params"]) {
+            write_message(output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Open documents and the patterns used to analyze them
+struct Server {
+    documents: HashMap<String, String>,
+    definition: Regex,
+    number_words: Regex,
+    text_words: Regex,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            definition: Regex::new(DEFINITION_PATTERN).expect("definition pattern is valid"),
+            number_words: Regex::new(NUMBER_WORDS).expect("number pattern is valid"),
+            text_words: Regex::new(TEXT_WORDS).expect("text pattern is valid"),
+        }
+    }
+    
+    /// Messages to send in reply to one client message
+    fn handle(&mut self, method: &str, id: Option<Value>, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": {"name": "nhlp", "version": env!("CARGO_PKG_VERSION")},
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole document
+                if let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last()).and_then(|change| change["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![notification("textDocument/publishDiagnostics", json!({"uri": uri, "diagnostics": []}))];
+            }
+            "textDocument/hover" => self.hover(&uri, &params["position"]).unwrap_or(Value::Null),
+            "textDocument/definition" => self.definition(&uri, &params["position"]).unwrap_or(Value::Null),
+            _ => {
+                // Notifications we don't use need no reply; unknown requests get an error
+                return match id {
+                    Some(id) => vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": -32601, "message": format!("Unsupported method: {}", method)},
+                    })],
+                    None => Vec::new(),
+                };
+            }
+        };
+        match id {
+            Some(id) => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            None => Vec::new(),
+        }
+    }
+    
+    /// A publishDiagnostics notification for a document
+    fn diagnostics(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(String::as_str).unwrap_or_default();
+        let lines: Vec<&str> = text.lines().collect();
+        let mut diagnostics = Vec::new();
+        
+        for (span, phrase) in check::vague_phrases(text) {
+            let line = lines[span.line - 1];
+            let start = byte_at_char(line, span.column - 1);
+            let end = byte_at_char(line, span.column - 1 + span.length);
+            diagnostics.push(diagnostic(
+                range(span.line - 1, line, start, end),
+                WARNING,
+                None,
+                format!("\"{}\" leaves a decision to the model; say exactly what should happen", phrase),
+            ));
+        }
+        
+        let has_description = lines.iter().any(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+        if has_description && acceptance::extract(text).is_empty() {
+            diagnostics.push(diagnostic(
+                range(0, "", 0, 0),
+                INFORMATION,
+                Some("NH0003"),
+                "No acceptance criteria; add sentences like \"when run with input 5 it prints 25\"".to_string(),
+            ));
+        }
+        
+        if let Some(path) = path_from_uri(uri) {
+            if let Err(e) = Glossary::load(&Glossary::path_for(&path)) {
+                diagnostics.push(diagnostic(range(0, "", 0, 0), ERROR, None, format!("{:#}", e)));
+            }
+        }
+        
+        notification("textDocument/publishDiagnostics", json!({"uri": uri, "diagnostics": diagnostics}))
+    }
+    
+    /// Glossary meaning of the term under the cursor, or where the named value under it was introduced
+    fn hover(&self, uri: &str, position: &Value) -> Option<Value> {
+        let (text, line_index, line, offset) = self.cursor(uri, position)?;
+        
+        if let Some(path) = path_from_uri(uri) {
+            let glossary = Glossary::load(&Glossary::path_for(&path)).unwrap_or_default();
+            if let Some((found, term, meaning)) = glossary.occurrences(line).into_iter().find(|(found, _, _)| found.contains(&offset)) {
+                return Some(json!({
+                    "contents": {"kind": "markdown", "value": format!("**{}** (glossary): {}", term, meaning)},
+                    "range": range(line_index, line, found.start, found.end),
+                }));
+            }
+        }
+        
+        let (word_start, word) = word_at(line, offset)?;
+        let (definition_line, _, _) = self.find_definition(text, word)?;
+        let sentence = text.lines().nth(definition_line)?.trim();
+        let kind = if self.number_words.is_match(sentence) {
+            "a number, "
+        } else if self.text_words.is_match(sentence) {
+            "text, "
+        } else {
+            ""
+        };
+        Some(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("**{}**: {}introduced on line {}\n\n> {}", word, kind, definition_line + 1, sentence),
+            },
+            "range": range(line_index, line, word_start, word_start + word.len()),
+        }))
+    }
+    
+    /// Location of the sentence introducing the named value under the cursor
+    fn definition(&self, uri: &str, position: &Value) -> Option<Value> {
+        let (text, _, line, offset) = self.cursor(uri, position)?;
+        let (_, word) = word_at(line, offset)?;
+        let (definition_line, start, end) = self.find_definition(text, word)?;
+        let definition_text = text.lines().nth(definition_line)?;
+        Some(json!({"uri": uri, "range": range(definition_line, definition_text, start, end)}))
+    }
+    
+    /// The first place `name` is introduced: its line and the byte range of the name on it
+    fn find_definition(&self, text: &str, name: &str) -> Option<(usize, usize, usize)> {
+        text.lines().enumerate().find_map(|(index, line)| {
+            self.definition
+                .captures_iter(line)
+                .filter_map(|captures| captures.name("name"))
+                .find(|found| found.as_str().eq_ignore_ascii_case(name))
+                .map(|found| (index, found.start(), found.end()))
+        })
+    }
+    
+    /// The document, line index, line text, and byte offset in the line for an LSP position
+    fn cursor<'a>(&'a self, uri: &str, position: &Value) -> Option<(&'a str, usize, &'a str, usize)> {
+        let text = self.documents.get(uri)?;
+        let line_index = position["line"].as_u64()? as usize;
+        let line = text.lines().nth(line_index)?;
+        let offset = byte_at_utf16(line, position["character"].as_u64()? as usize);
+        Some((text, line_index, line, offset))
+    }
+}
+
+/// Read one `Content-Length`-framed JSON-RPC message, or None at end of input
+///
+/// A message with a missing or malformed header, a body over the size limit,
+/// or a body that isn't JSON is returned as an error so the caller can reply
+/// and keep reading; only failures of the input itself end the session.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Result<Value>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        // After a skipped message its body runs into the next header, so look for the header anywhere on the line
+        if let Some(start) = header.find("Content-Length:") {
+            length = Some(header[start + "Content-Length:".len()..].trim().parse::<usize>());
+        }
+    }
+    
+    let length = match length {
+        Some(Ok(length)) => length,
+        Some(Err(e)) => return Ok(Some(Err(anyhow::anyhow!("Invalid Content-Length header: {}", e)))),
+        None => return Ok(Some(Err(anyhow::anyhow!("LSP message without a Content-Length header")))),
+    };
+    if length > MAX_MESSAGE_LENGTH {
+        io::copy(&mut input.by_ref().take(length as u64), &mut io::sink())?;
+        return Ok(Some(Err(anyhow::anyhow!("LSP message of {} bytes is over the {} byte limit", length, MAX_MESSAGE_LENGTH))));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body).context("Invalid LSP message")))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn diagnostic(range: Value, severity: u8, code: Option<&str>, message: String) -> Value {
+    json!({"range": range, "severity": severity, "code": code, "source": "nhlp", "message": message})
+}
+
+/// An LSP range on one line from byte offsets, converted to UTF-16 columns
+fn range(line_index: usize, line: &str, start: usize, end: usize) -> Value {
+    let column = |byte: usize| line[..byte].encode_utf16().count();
+    json!({
+        "start": {"line": line_index, "character": column(start)},
+        "end": {"line": line_index, "character": column(end)},
+    })
+}
+
+/// Byte offset of the `index`th character, or the end of the line
+fn byte_at_char(line: &str, index: usize) -> usize {
+    line.char_indices().nth(index).map_or(line.len(), |(byte, _)| byte)
+}
+
+/// Byte offset of a UTF-16 column, as LSP positions count them
+fn byte_at_utf16(line: &str, column: usize) -> usize {
+    let mut units = 0;
+    for (byte, c) in line.char_indices() {
+        if units >= column {
+            return byte;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// The identifier-like word containing a byte offset, and where it starts
+fn word_at(line: &str, offset: usize) -> Option<(usize, &str)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = line[..offset]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_word(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = line[offset..].find(|c: char| !is_word(c)).map_or(line.len(), |i| offset + i);
+    (start < end).then(|| (start, &line[start..end]))
+}
+
+/// Local path of a `file://` URI
+fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // An escape needs both hex digits, so one ending the URI early is kept as written
+        if let Some(hex) = bytes.get(i + 1..=i + 2).filter(|_| bytes[i] == b'%') {
+            let hex = std::str::from_utf8(hex).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    Some(PathBuf::from(String::from_utf8(decoded).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn framed(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    /// The replies `serve` writes for `input`
+    fn replies(input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve(&mut Cursor::new(input.as_bytes()), &mut output).expect("session ends cleanly");
+        let mut output = Cursor::new(output);
+        let mut replies = Vec::new();
+        while let Some(reply) = read_message(&mut output).unwrap() {
+            replies.push(reply.unwrap());
+        }
+        replies
+    }
+
+    #[test]
+    fn uri_escapes_are_decoded() {
+        assert_eq!(path_from_uri("file:///home/me/My%20Specs/caf%C3%A9.dshp"), Some(PathBuf::from("/home/me/My Specs/café.dshp")));
+        assert_eq!(path_from_uri("file:///tmp/%2a.dshp"), Some(PathBuf::from("/tmp/*.dshp")));
+    }
+
+    #[test]
+    fn incomplete_or_invalid_uri_escapes_are_kept() {
+        assert_eq!(path_from_uri("file:///tmp/a%2"), Some(PathBuf::from("/tmp/a%2")));
+        assert_eq!(path_from_uri("file:///tmp/a%"), Some(PathBuf::from("/tmp/a%")));
+        assert_eq!(path_from_uri("file:///tmp/100%zz"), Some(PathBuf::from("/tmp/100%zz")));
+        assert_eq!(path_from_uri("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn malformed_message_gets_a_parse_error_and_the_session_continues() {
+        let input = format!(
+            "{}{}{}",
+            framed("{not json"),
+            framed(r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#),
+            framed(r#"{"jsonrpc": "2.0", "method": "exit"}"#)
+        );
+        let replies = replies(&input);
+        assert_eq!(replies.len(), 2, "{:?}", replies);
+        assert_eq!(replies[0]["error"]["code"], -32700);
+        assert_eq!(replies[0]["id"], Value::Null);
+        assert_eq!(replies[1]["id"], 1);
+    }
+
+    #[test]
+    fn message_without_a_length_gets_a_parse_error() {
+        let input = format!("Content-Type: application/json\r\n\r\n{}", framed(r#"{"jsonrpc": "2.0", "method": "exit"}"#));
+        let replies = replies(&input);
+        assert_eq!(replies.len(), 1, "{:?}", replies);
+        assert_eq!(replies[0]["error"]["code"], -32700);
+    }
+
+    #[test]
+    fn oversized_message_gets_a_parse_error_without_being_read() {
+        let replies = replies("Content-Length: 1099511627776\r\n\r\n{}");
+        assert_eq!(replies.len(), 1, "{:?}", replies);
+        assert_eq!(replies[0]["error"]["code"], -32700);
+        assert!(replies[0]["error"]["message"].as_str().unwrap().contains("over the"));
+    }
+}
@@ -1,77 +1,907 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
 use log::{error, info, warn};
-use std::path::PathBuf;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+mod acceptance;
+mod artifacts;
+mod backend;
+mod cache;
+mod cancel;
+mod capabilities;
+mod check;
+mod checkpoint;
+mod circuit;
+mod commands;
 mod compiler;
-mod gemini;
+mod cost;
+mod counterexamples;
+mod coverage;
+mod data_formats;
+mod dataset;
+mod debugger;
+mod doctor;
+mod equivalence;
+mod error;
+mod estimate;
+mod explain;
+mod features;
+mod glossary;
+#[cfg(feature = "graph")]
+mod graph;
+mod hardening;
+mod interpreter;
+mod library;
+mod linker;
+mod lint;
+mod llm;
+mod lockfile;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod messages;
+mod notebook;
+mod offline;
+mod opt_level;
+mod patterns;
+mod policy;
+mod precedents;
+mod progress;
+mod project;
+mod provenance;
+mod redact;
+mod repl;
+mod report;
+mod report_artifact;
+mod runner;
+mod schema;
+mod signals;
+mod sorting;
+mod sourcemap;
+mod stack;
+mod templates;
+mod trace;
+mod transcript;
+mod verify;
 
-use compiler::Compiler;
+use cancel::{CancellationToken, Cancelled};
+use cache::ResponseCache;
+use capabilities::describe;
+use compiler::{CompileOptions, Compiler};
+use estimate::Estimate;
+use features::TargetFeatures;
+use glossary::Glossary;
+use interpreter::Backend;
+use lint::LintLevels;
+use library::LibraryKind;
+use linker::{LinkOptions, Linker, Strip};
+use lockfile::Lockfile;
+use messages::MessageFormat;
+use opt_level::OptLevel;
+use precedents::PrecedentIndex;
+use policy::ConstructPolicy;
+use progress::{JsonProgress, NoProgress, PlainProgress, ProgressBar, ProgressObserver};
+use project::Project;
+use report::{CompilationReport, ReportDiff};
+use templates::Template;
 
 #[derive(Parser, Debug)]
 #[clap(
     name = "nhlp",
     about = "Natural High Level Programming Language Native Compiler",
-    version
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Args {
-    /// Input .dshp file
+    #[clap(subcommand)]
+    command: Option<Commands>,
+
+    /// Input .dshp file, or a project directory or nhlp.toml
     #[clap(required = true)]
-    input_file: PathBuf,
+    input_file: Option<PathBuf>,
+
+    #[clap(flatten)]
+    compile: CompileArgs,
+
+    #[clap(flatten)]
+    output: OutputArgs,
+}
+
+/// Options controlling how nhlp writes its own messages, for every command
+#[derive(clap::Args, Debug, Clone, Copy)]
+struct OutputArgs {
+    /// Plain output for screen readers and log collectors: no colors or control sequences, one status line per step
+    #[clap(long, global = true)]
+    plain: bool,
 
+    /// Write messages as human-readable lines or as newline-delimited JSON on stderr
+    #[clap(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
+/// Options controlling how a program or project is compiled and run
+#[derive(clap::Args, Debug)]
+struct CompileArgs {
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
+
+    /// Write a compilation report to this path: JSON, or a page for review when it ends in .md or .html
+    #[clap(long)]
+    report: Option<PathBuf>,
+
+    /// Show a progress bar while compiling (one status line per step with --plain)
+    #[clap(long)]
+    progress: bool,
+
+    /// Write an in-toto/SLSA provenance document next to the executable
+    #[clap(long)]
+    provenance: bool,
+
+    /// Comma-separated models to fall back through when one keeps failing; `offline` translates specs in the offline vocabulary by rule
+    #[clap(long, value_delimiter = ',')]
+    model_ladder: Vec<String>,
+
+    /// Seconds to wait for each model request before treating it as failed
+    #[clap(long, default_value_t = 120)]
+    timeout: u64,
+
+    /// Seconds to wait for the model requests of one stage, e.g. translate=300; stages are translate, counterexamples, and explanation (repeatable, comma-separated)
+    #[clap(long = "stage-timeout", value_name = "STAGE=SECONDS", value_delimiter = ',')]
+    stage_timeouts: Vec<String>,
+
+    /// Send the program to the model without masking secrets and personal data
+    #[clap(long)]
+    no_redact: bool,
+
+    /// Additional regular expression whose matches are masked before translation (repeatable)
+    #[clap(long = "redact-pattern")]
+    redact_patterns: Vec<String>,
+
+    /// Fail if any stage attempts a network request
+    #[clap(long)]
+    air_gapped: bool,
+
+    /// Comma-separated construct=level overrides, e.g. inline-asm=allow,raw-pointer=deny
+    #[clap(long, value_delimiter = ',')]
+    construct_policy: Vec<String>,
+
+    /// Program the generated code may start, e.g. ls; once any is given, other commands fail the build (repeatable, comma-separated)
+    #[clap(long = "allow-command", value_name = "COMMAND", value_delimiter = ',')]
+    allowed_commands: Vec<String>,
+
+    /// Silence a lint, by name or code; `warnings` means every lint (repeatable, comma-separated)
+    #[clap(short = 'A', long, value_name = "LINT", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Report a lint as a warning, by name or code; `warnings` means every lint (repeatable, comma-separated)
+    #[clap(short = 'W', long, value_name = "LINT", value_delimiter = ',')]
+    warn: Vec<String>,
+
+    /// Fail the compilation on a lint, by name or code; `warnings` means every lint (repeatable, comma-separated)
+    #[clap(short = 'D', long, value_name = "LINT", value_delimiter = ',')]
+    deny: Vec<String>,
+
+    /// Instrument the program and report which sentences its run exercised
+    #[clap(long)]
+    coverage: bool,
+
+    /// Print the lines, I/O calls, allocations, and loops the generated code spends on each sentence
+    #[clap(long)]
+    cost: bool,
+
+    /// Run the program against edge-case inputs proposed by the model and report the results
+    #[clap(long)]
+    counterexamples: bool,
+
+    /// Build at -O0 and -O2 and fail if they behave differently on generated inputs
+    #[clap(long)]
+    check_equivalence: bool,
+
+    /// Run the program against the "when run with input X it prints Y" criteria in the spec and fail on mismatches
+    #[clap(long)]
+    check_acceptance: bool,
+
+    /// Capture the program's stdout, stderr, and exit code into the report instead of attaching it to the terminal
+    #[clap(long)]
+    capture_output: bool,
+
+    /// Feed this file to the program's stdin, so interactive programs can run unattended
+    #[clap(long)]
+    stdin_file: Option<PathBuf>,
+
+    /// Write textual LLVM IR for the generated program to this path instead of building and running it
+    #[clap(long, value_name = "PATH")]
+    emit_ir: Option<PathBuf>,
+
+    /// Write a relocatable object file to this path instead of linking and running the program
+    #[clap(long, value_name = "PATH", conflicts_with = "emit_ir")]
+    emit_obj: Option<PathBuf>,
+
+    /// Write human-readable assembly for the generated program to this path instead of building and running it
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["emit_ir", "emit_obj"])]
+    emit_asm: Option<PathBuf>,
+
+    /// Build a library exporting the functions the spec describes, with a C header, instead of a program:
+    /// `static` (the default) or `shared`
+    #[clap(
+        long = "lib",
+        value_enum,
+        value_name = "KIND",
+        num_args = 0..=1,
+        default_missing_value = "static",
+        conflicts_with_all = ["emit_ir", "emit_obj", "emit_asm"]
+    )]
+    library: Option<LibraryKind>,
+
+    /// Extra argument for the linker driver, e.g. -lm (repeatable)
+    #[clap(long = "link-arg", value_name = "ARG", allow_hyphen_values = true)]
+    link_args: Vec<String>,
+
+    /// System linker for the compiler driver to use: lld, mold, or cc (its default)
+    #[clap(long, value_enum)]
+    linker: Option<Linker>,
+
+    /// Link the program statically so it runs without the system's shared libraries
+    #[clap(long = "static")]
+    static_link: bool,
+
+    /// Enable link-time optimization
+    #[clap(long)]
+    lto: bool,
+
+    /// Strip the executable after linking: `symbols` (the default) or only `debuginfo`
+    #[clap(long, value_enum, value_name = "WHAT", num_args = 0..=1, default_missing_value = "symbols")]
+    strip: Option<Strip>,
+
+    /// Symbols to keep when stripping (comma-separated)
+    #[clap(long, value_name = "SYMBOL", value_delimiter = ',', requires = "strip")]
+    keep_symbols: Vec<String>,
+
+    /// Translate with built-in rules instead of a model; needs no API key or network but understands only simple programs
+    #[clap(long)]
+    offline: bool,
+
+    /// LLM provider: gemini, openai, anthropic, or ollama (default: NHLP_LLM_PROVIDER, then gemini)
+    #[clap(long)]
+    llm_provider: Option<String>,
+
+    /// Target triple to cross-compile for, e.g. aarch64-unknown-linux-gnu; the program is built but not run
+    #[clap(long)]
+    target: Option<String>,
+
+    /// How to run the program: native builds it; interp runs the spec directly in the
+    /// offline vocabulary, without a model or toolchain
+    #[clap(long, value_enum, default_value_t = Backend::Native)]
+    backend: Backend,
+
+    /// With --backend interp, report every change to this variable and the sentence that made it (repeatable)
+    #[clap(long, value_name = "VARIABLE")]
+    watch: Vec<String>,
+
+    /// Build with debug info, so gdb and lldb step through the spec's sentences (C programs)
+    #[clap(short = 'g', long = "debug-info")]
+    debug_info: bool,
+
+    /// Optimization level: 0, 1, 2, 3, or s for size (e.g. -O2)
+    #[clap(short = 'O', long = "opt-level", value_enum, value_name = "LEVEL")]
+    opt_level: Option<OptLevel>,
+
+    /// CPU features to enable or, with a leading `-`, disable for the target, e.g. avx2,-avx512f
+    #[clap(long, value_delimiter = ',', allow_hyphen_values = true)]
+    features: Vec<String>,
+
+    /// Build a hardened executable (PIE, stack canaries, full RELRO, NX stack, fortified calls) and report what it got
+    #[clap(long)]
+    harden: bool,
+
+    /// Always ask the model instead of reusing responses cached by earlier compilations
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Show the model how earlier compilations translated similar sentences, and record this compilation's for later ones
+    #[clap(long)]
+    precedents: bool,
+
+    /// Always rebuild the executable instead of reusing the one in .nhlp-cache from an identical build
+    #[clap(long)]
+    no_incremental: bool,
+
+    /// Directory to write the executable to (default: the current directory)
+    #[clap(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// Build the program without running it
+    #[clap(long)]
+    no_run: bool,
+
+    /// Write every prompt and raw model response to numbered files in this directory
+    #[clap(long, value_name = "DIR")]
+    debug_llm: Option<PathBuf>,
+
+    /// Stack size built into the compiled program, in bytes with an optional K, M, or G suffix
+    #[clap(long, value_parser = runner::parse_size)]
+    stack_size: Option<u64>,
+
+    /// Fail when the program uses network, file-write, or process-spawn capabilities not approved in nhlp.lock
+    #[clap(long)]
+    require_approval: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Compare two compilation reports
+    Diff {
+        /// Report from the earlier compilation
+        old: PathBuf,
+
+        /// Report from the later compilation
+        new: PathBuf,
+    },
+
+    /// Approve the external interactions a program's last compilation introduced
+    Approve {
+        /// The .dshp file to approve
+        input_file: PathBuf,
+    },
+
+    /// Compile and run the multi-file project described by an nhlp.toml manifest
+    Build {
+        /// Project directory containing nhlp.toml, or the manifest itself
+        #[clap(default_value = ".")]
+        project: PathBuf,
+
+        #[clap(flatten)]
+        compile: Box<CompileArgs>,
+    },
+
+    /// Create a .dshp spec from a template for a common program shape
+    Init {
+        /// Spec to create; .dshp is added if it has no extension
+        name: PathBuf,
+
+        /// Shape of program to start from
+        #[clap(long, value_enum, default_value_t = Template::CliTool)]
+        template: Template,
+
+        /// Overwrite the spec if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Build a program interactively, one statement at a time
+    Repl {
+        /// Replay a .dshpnb notebook saved from an earlier session before reading statements
+        #[clap(long, value_name = "FILE")]
+        load: Option<PathBuf>,
+
+        #[clap(flatten)]
+        compile: Box<CompileArgs>,
+    },
+
+    /// Check a spec, its glossary, and its lockfile without translating or building it
+    Check {
+        /// The .dshp file, or a project directory or nhlp.toml
+        input_file: PathBuf,
+
+        /// Extra regular expressions whose matches would be masked (repeatable)
+        #[clap(long = "redact-pattern")]
+        redact_patterns: Vec<String>,
+    },
+
+    /// Step through a spec in the offline vocabulary with the interpreter
+    Debug {
+        /// The .dshp file to debug
+        #[clap(required_unless_present = "replay")]
+        input_file: Option<PathBuf>,
+
+        /// File fed to the program's standard input instead of the terminal
+        #[clap(long, value_name = "PATH")]
+        stdin_file: Option<PathBuf>,
+
+        /// Write a trace of every step of the run to this path when the session ends
+        #[clap(long, value_name = "PATH")]
+        record: Option<PathBuf>,
+
+        /// Step forwards and backwards through a trace written by --record
+        #[clap(long, value_name = "PATH", conflicts_with_all = ["input_file", "stdin_file", "record"])]
+        replay: Option<PathBuf>,
+    },
+
+    /// Write the control-flow graph and dominator tree of a spec in the offline vocabulary as Graphviz DOT
+    #[cfg(feature = "graph")]
+    Graph {
+        /// The .dshp file to graph
+        input_file: PathBuf,
+
+        /// Include the control-flow graph of basic blocks (the default)
+        #[clap(long)]
+        cfg: bool,
+
+        /// Include the dominator tree
+        #[clap(long)]
+        domtree: bool,
+
+        /// Where to write the DOT file instead of stdout
+        #[clap(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Serve the Language Server Protocol on stdin/stdout for editing .dshp files
+    #[cfg(feature = "lsp")]
+    Lsp,
+
+    /// Check the API key, toolchains, and directories compilation depends on
+    Doctor {
+        /// Provider to check; defaults to NHLP_LLM_PROVIDER, then gemini
+        #[clap(long)]
+        llm_provider: Option<String>,
+    },
+
+    /// Convert --debug-llm transcripts into a JSONL fine-tuning dataset
+    ExportDataset {
+        /// Transcript directories written by --debug-llm
+        #[clap(required_unless_present = "lockfile")]
+        transcripts: Vec<PathBuf>,
+
+        /// Lockfiles whose capability approvals to include (repeatable)
+        #[clap(long)]
+        lockfile: Vec<PathBuf>,
+
+        /// Where to write the dataset
+        #[clap(short, long, default_value = "dataset.jsonl")]
+        output: PathBuf,
+
+        /// Only export these stages, e.g. translate (repeatable)
+        #[clap(long)]
+        stage: Vec<String>,
+    },
+
+    /// Describe a diagnostic code, with example spec text that raises it and how to rewrite it
+    Explain {
+        /// Code or name, e.g. NH0005 or program-failed; lists every code when omitted
+        code: Option<String>,
+    },
+
+    /// Manage the cache of model responses
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Manage the index of earlier translations that --precedents shows the model
+    Precedents {
+        #[clap(subcommand)]
+        action: PrecedentsAction,
+    },
+
+    /// Estimate the tokens, cost, and time of compiling a program without calling the model
+    Estimate {
+        /// The .dshp file to estimate
+        input_file: PathBuf,
+
+        /// Model to price the translation for; defaults to the first entry of the model ladder
+        #[clap(long)]
+        model: Option<String>,
+
+        /// Provider whose default model is priced when no ladder is configured
+        #[clap(long = "llm-provider")]
+        provider: Option<String>,
+
+        /// Compilation reports whose request timings inform the time estimate (repeatable)
+        #[clap(long)]
+        history: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Delete every cached response
+    Clear,
+
+    /// Print the cache directory
+    Dir,
+}
+
+#[derive(Subcommand, Debug)]
+enum PrecedentsAction {
+    /// Add the sentences and code of saved compilation reports, or of cached model responses
+    Add {
+        /// Reports written by --report
+        #[clap(required_unless_present = "from_cache")]
+        reports: Vec<PathBuf>,
+
+        /// Also add every response in the response cache
+        #[clap(long)]
+        from_cache: bool,
+    },
+
+    /// Delete the index
+    Clear,
 }
 
 fn main() -> Result<()> {
     // Load environment variables from .env file
     dotenv().ok();
     
+    let args = Args::parse();
+    let output = args.output;
+    
     // Initialize logging
     if std::env::var("RUST_LOG").is_err() {
         std::env::set_var("RUST_LOG", "info");
     }
-    env_logger::init();
+    let mut logger = env_logger::Builder::from_default_env();
+    if output.plain || output.message_format == MessageFormat::Json {
+        logger.write_style(env_logger::WriteStyle::Never);
+    }
+    if output.message_format == MessageFormat::Json {
+        logger.format(|buf, record| {
+            writeln!(buf, "{}", messages::diagnostic(record.level(), record.target(), &record.args().to_string()))
+        });
+    }
+    logger.init();
+
+    let result = match args.command {
+        Some(command) => run_command(command, output),
+        None => match args.input_file {
+            Some(input_file) => compile(&input_file, args.compile, output),
+            None => Err(anyhow::anyhow!("An input file is required without a subcommand")),
+        },
+    };
     
-    let args = Args::parse();
+    // Keep stderr parseable: the failure becomes one last diagnostic instead of anyhow's report
+    if let (Err(e), MessageFormat::Json) = (&result, output.message_format) {
+        eprintln!("{}", messages::diagnostic(log::Level::Error, "nhlp", &format!("{:#}", e)));
+        std::process::exit(1);
+    }
+    result
+}
 
+/// Compile and run a .dshp file, or the project whose manifest `input_file` names
+///
+/// `output` decides whether progress is drawn as a bar, status lines, or JSON.
+fn compile(input_file: &Path, args: CompileArgs, output: OutputArgs) -> Result<()> {
     if args.verbose {
         println!("Natural High Level Programming Language Native Compiler");
-        println!("Input file: {:?}", args.input_file);
+        println!("Input file: {:?}", input_file);
     }
 
     // Validate input file
-    if !args.input_file.exists() {
+    if !input_file.exists() {
         return Err(anyhow::anyhow!("Input file does not exist"));
     }
 
-    if args.input_file.extension().unwrap_or_default() != "dshp" {
+    let project = match Project::manifest_for(input_file) {
+        Some(manifest) => Some(Project::load(&manifest)?),
+        None if input_file.is_dir() => {
+            return Err(anyhow::anyhow!("{:?} has no {}", input_file, project::MANIFEST_NAME));
+        }
+        None => None,
+    };
+    if project.is_none() && input_file.extension().unwrap_or_default() != "dshp" {
         warn!("Input file does not have .dshp extension");
     }
     
-    // Initialize the compiler
-    let compiler = match Compiler::new() {
+    if !args.watch.is_empty() && args.backend != Backend::Interp {
+        return Err(anyhow::anyhow!("--watch needs --backend interp; native builds aren't instrumented"));
+    }
+    if args.backend == Backend::Interp {
+        info!("Running {:?} with the interpreter", input_file);
+        let program = match &project {
+            Some(project) => project.source(),
+            None => fs::read_to_string(input_file)
+                .with_context(|| format!("Failed to read input file: {:?}", input_file))?,
+        };
+        return interpreter::run(&program, args.stdin_file.as_deref(), &args.watch);
+    }
+    
+    // Only the compiled program gets the stack limit, not nhlp or its toolchains. The stack
+    // size is also built into the program, so a limit that can't be raised only warns.
+    if let Some(stack_size) = args.stack_size {
+        if let Err(e) = runner::set_stack_limit(stack_size) {
+            warn!("{:#}; the program still runs its code on a thread with the requested stack", e);
+        }
+    }
+    
+    let options = compile_options(&args, project.as_ref())?;
+    let compiler = match Compiler::new(options) {
         Ok(compiler) => compiler,
         Err(e) => {
             error!("Failed to initialize compiler: {}", e);
+            if let Some(explanation) = explain::for_error(&e) {
+                info!("For more information about this error, run `nhlp explain {}`", explanation.code);
+            }
             return Err(e);
         }
     };
     
+    // Ctrl-C cancels the compilation instead of killing it mid-stage
+    let cancel = CancellationToken::new();
+    let handler_token = cancel.clone();
+    ctrlc::set_handler(move || handler_token.cancel())?;
+    
     // Compile directly to native code and execute
-    info!("Compiling and executing: {:?}", args.input_file);
-    match compiler.execute(&args.input_file) {
-        Ok(_) => {
+    info!("Compiling and executing: {:?}", input_file);
+    let progress: &dyn ProgressObserver = match (args.progress, output.message_format, output.plain) {
+        (false, _, _) => &NoProgress,
+        (true, MessageFormat::Json, _) => &JsonProgress,
+        (true, MessageFormat::Human, false) => &ProgressBar,
+        (true, MessageFormat::Human, true) => &PlainProgress,
+    };
+    let result = match &project {
+        Some(project) => {
+            info!("Project {} with {} module(s)", project.name, project.modules.len());
+            compiler.execute_source(&project.manifest, &project.name, &project.source(), &cancel, progress)
+        }
+        None => compiler.execute(input_file, &cancel, progress),
+    };
+    match result {
+        Ok(report) => {
+            if let Some(report_path) = &args.report {
+                report.save(report_path)?;
+                info!("Compilation report written to {:?}", report_path);
+            }
+            if args.provenance {
+                let provenance_path = provenance::write(&report)?;
+                info!("Provenance written to {:?}", provenance_path);
+            }
             if args.verbose {
                 println!("Program executed successfully.");
             }
             Ok(())
         }
+        Err(e) if e.is::<Cancelled>() => {
+            warn!("Compilation cancelled");
+            if let Some(report_path) = &args.report {
+                let (source_file, source) = match &project {
+                    Some(project) => (project.manifest.as_path(), project.source()),
+                    None => (Path::new(input_file), fs::read_to_string(input_file).unwrap_or_default()),
+                };
+                let report = CompilationReport::cancelled(&source_file.display().to_string(), &source);
+                match report.save(report_path) {
+                    Ok(()) => info!("Cancelled outcome written to {:?}", report_path),
+                    Err(e) => warn!("Failed to write the report: {:#}", e),
+                }
+            }
+            std::process::exit(130);
+        }
         Err(e) => {
             error!("Compilation or execution failed: {}", e);
+            if let Some(explanation) = explain::for_error(&e) {
+                info!("For more information about this error, run `nhlp explain {}`", explanation.code);
+            }
             Err(e)
         }
     }
 }
+
+/// Run a subcommand
+fn run_command(command: Commands, output: OutputArgs) -> Result<()> {
+    match command {
+        Commands::Diff { old, new } => {
+            let old_report = CompilationReport::load(&old)?;
+            let new_report = CompilationReport::load(&new)?;
+            println!("{}", ReportDiff::new(&old_report, &new_report));
+            Ok(())
+        }
+        Commands::Approve { input_file } => {
+            let lock_path = Lockfile::path_for(&input_file);
+            let mut lockfile = Lockfile::load(&lock_path)?;
+            let approved = lockfile.approve(&input_file);
+            if approved.is_empty() {
+                println!("Nothing pending approval for {:?}", input_file);
+                return Ok(());
+            }
+            lockfile.save(&lock_path)?;
+            println!("Approved {} for {:?}", describe(&approved), input_file);
+            Ok(())
+        }
+        Commands::Build { project, compile: args } => {
+            if Project::manifest_for(&project).is_none() {
+                return Err(anyhow::anyhow!("No {} found at {:?}", project::MANIFEST_NAME, project));
+            }
+            compile(&project, *args, output)
+        }
+        Commands::Init { mut name, template, force } => {
+            if name.extension().is_none() {
+                name.set_extension("dshp");
+            }
+            template.write(&name, force)?;
+            println!("Created {:?}; edit each section, then run `nhlp {} --check-acceptance`", name, name.display());
+            Ok(())
+        }
+        Commands::Repl { load, compile: args } => {
+            // The compiler's progress messages for each statement only show with --verbose
+            if !args.verbose {
+                log::set_max_level(log::LevelFilter::Warn);
+            }
+            repl::run(compile_options(&args, None)?, load.as_deref())
+        }
+        Commands::Check { input_file, redact_patterns } => {
+            let checks = check::run(&input_file, &redact_patterns);
+            print_checks(&checks, output.message_format)?;
+            let errors = checks.iter().filter(|check| check.status == doctor::Status::Fail).count();
+            if errors > 0 {
+                return Err(anyhow::anyhow!("{:?} has {} error(s)", input_file, errors));
+            }
+            if output.message_format == MessageFormat::Human {
+                println!("No errors found.");
+            }
+            Ok(())
+        }
+        Commands::Debug { input_file, stdin_file, record, replay } => match (replay, input_file) {
+            (Some(trace), _) => debugger::replay(&trace),
+            (None, Some(input_file)) => debugger::run(&input_file, stdin_file.as_deref(), record.as_deref()),
+            (None, None) => unreachable!("clap requires an input file without --replay"),
+        },
+        #[cfg(feature = "graph")]
+        Commands::Graph { input_file, cfg, domtree, output } => {
+            let program = fs::read_to_string(&input_file)
+                .with_context(|| format!("Failed to read input file: {:?}", input_file))?;
+            let graph = graph::ControlFlow::new(&program)?;
+            let dot = graph::dot(&graph, cfg || !domtree, domtree);
+            match output {
+                Some(path) => {
+                    fs::write(&path, dot).with_context(|| format!("Failed to write {:?}", path))?;
+                    println!("Wrote {} block(s) to {:?}", graph.blocks.len(), path);
+                }
+                None => print!("{}", dot),
+            }
+            Ok(())
+        }
+        #[cfg(feature = "lsp")]
+        Commands::Lsp => lsp::run(),
+        Commands::Doctor { llm_provider } => {
+            let checks = doctor::run(backend::configured(llm_provider.as_deref())?.as_ref());
+            print_checks(&checks, output.message_format)?;
+            if checks.iter().any(|check| check.status == doctor::Status::Fail) {
+                return Err(anyhow::anyhow!("Environment is not ready to compile NHLP programs"));
+            }
+            if output.message_format == MessageFormat::Human {
+                println!("Environment is ready.");
+            }
+            Ok(())
+        }
+        Commands::ExportDataset { transcripts, lockfile, output, stage } => {
+            let records = dataset::export(&transcripts, &lockfile, &stage, &output)?;
+            println!("Wrote {} record(s) to {:?}", records, output);
+            Ok(())
+        }
+        Commands::Explain { code: None } => {
+            for entry in explain::CATALOG {
+                println!("{}  {:<24} {}", entry.code, entry.name, entry.summary);
+            }
+            Ok(())
+        }
+        Commands::Explain { code: Some(code) } => {
+            let explanation = explain::lookup(&code)
+                .ok_or_else(|| anyhow::anyhow!("Unknown diagnostic code: {}; run `nhlp explain` to list them", code))?;
+            println!("{}", explanation);
+            Ok(())
+        }
+        Commands::Cache { action } => {
+            let cache = ResponseCache::open()?;
+            match action {
+                CacheAction::Clear => {
+                    let removed = cache.clear()?;
+                    println!("Removed {} cached response(s) from {:?}", removed, cache.dir());
+                }
+                CacheAction::Dir => println!("{}", cache.dir().display()),
+            }
+            Ok(())
+        }
+        Commands::Precedents { action } => {
+            let cache = ResponseCache::open()?;
+            let mut index = PrecedentIndex::load(&PrecedentIndex::path_in(cache.dir()))?;
+            match action {
+                PrecedentsAction::Add { reports, from_cache } => {
+                    let mut recorded = 0;
+                    for path in &reports {
+                        recorded += index.record_report(&CompilationReport::load(path)?);
+                    }
+                    if from_cache {
+                        for response in cache.responses()? {
+                            recorded += index.record_response(&response);
+                        }
+                    }
+                    index.save()?;
+                    println!("Recorded {} sentence(s)", recorded);
+                }
+                PrecedentsAction::Clear => {
+                    let removed = index.clear()?;
+                    println!("Removed {} precedent(s)", removed);
+                }
+            }
+            Ok(())
+        }
+        Commands::Estimate { input_file, model, provider, history } => {
+            let input = std::fs::read_to_string(&input_file)
+                .with_context(|| format!("Failed to read input file: {:?}", input_file))?;
+            let glossary = Glossary::load(&Glossary::path_for(&input_file))?;
+            let prompt = compiler::translation_prompt(&input, &glossary, true, &[])?;
+            let backend = backend::configured(provider.as_deref())?;
+            let model = match model {
+                Some(model) => model,
+                None => llm::configured_ladder(Vec::new(), backend.as_ref())
+                    .into_iter()
+                    .next()
+                    .context("No model to estimate for; pass --model or set NHLP_MODEL_LADDER")?,
+            };
+            let history = history
+                .iter()
+                .map(CompilationReport::load)
+                .collect::<Result<Vec<_>>>()?;
+            println!("{}", Estimate::new(&input, &prompt, &model, &history));
+            Ok(())
+        }
+    }
+}
+
+/// Compiler options from the command line's compile flags, over a project manifest's settings if there is one
+fn compile_options(args: &CompileArgs, project: Option<&Project>) -> Result<CompileOptions> {
+    // Link arguments on the command line come after the manifest's, so they can override them
+    let mut link_args = project.map(|project| project.link_args.clone()).unwrap_or_default();
+    link_args.extend(args.link_args.iter().cloned());
+    
+    Ok(CompileOptions {
+        require_approval: args.require_approval,
+        model_ladder: args.model_ladder.clone(),
+        request_timeout: Duration::from_secs(args.timeout),
+        stage_timeouts: CompileOptions::parse_stage_timeouts(&args.stage_timeouts)?,
+        redact: !args.no_redact,
+        redact_patterns: args.redact_patterns.clone(),
+        air_gapped: args.air_gapped,
+        construct_policy: ConstructPolicy::from_overrides(&args.construct_policy)?,
+        allowed_commands: args.allowed_commands.clone(),
+        lints: LintLevels::from_flags(&args.allow, &args.warn, &args.deny)?,
+        coverage: args.coverage,
+        cost: args.cost,
+        counterexamples: args.counterexamples,
+        check_equivalence: args.check_equivalence,
+        check_acceptance: args.check_acceptance,
+        capture_output: args.capture_output,
+        stdin_file: args.stdin_file.clone(),
+        emit_ir: args.emit_ir.clone(),
+        emit_obj: args.emit_obj.clone(),
+        emit_asm: args.emit_asm.clone(),
+        debug_info: args.debug_info,
+        stack_size: args.stack_size,
+        link: LinkOptions {
+            args: link_args,
+            linker: args.linker.or(project.and_then(|project| project.linker)),
+            static_link: args.static_link,
+            lto: args.lto,
+            strip: args.strip,
+            keep_symbols: args.keep_symbols.clone(),
+        },
+        library: args.library,
+        opt_level: args.opt_level,
+        features: TargetFeatures::parse(&args.features, args.target.as_deref())?,
+        harden: args.harden,
+        debug_llm: args.debug_llm.clone(),
+        target: args.target.clone(),
+        llm_provider: args.llm_provider.clone(),
+        offline: args.offline,
+        cache: !args.no_cache,
+        precedents: args.precedents,
+        incremental: !args.no_incremental,
+        out_dir: args.out_dir.clone(),
+        run: !args.no_run,
+    })
+}
+
+/// Print the results of `nhlp check` or `nhlp doctor`, one per line
+fn print_checks(checks: &[doctor::Check], format: MessageFormat) -> Result<()> {
+    for check in checks {
+        match format {
+            MessageFormat::Human => println!("{}", check),
+            MessageFormat::Json => println!("{}", serde_json::to_string(check)?),
+        }
+    }
+    Ok(())
+}
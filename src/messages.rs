@@ -0,0 +1,36 @@
+use serde_json::{json, Value};
+
+use crate::explain;
+
+/// How nhlp writes its own messages
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Log lines for people
+    #[default]
+    Human,
+    /// One JSON object per line, for editors and CI tooling
+    Json,
+}
+
+/// A log record as a JSON diagnostic
+///
+/// `message` is the first line of the text and `rendered` the whole of it,
+/// including any excerpt of the spec. `code` is the diagnostic code the
+/// text carries, if any, so tools can look it up with `nhlp explain`.
+pub fn diagnostic(level: log::Level, target: &str, text: &str) -> Value {
+    let level = match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warning",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+    };
+    json!({
+        "type": "diagnostic",
+        "level": level,
+        "code": explain::code_in(text).map(|entry| entry.code),
+        "message": text.lines().next().unwrap_or_default(),
+        "rendered": text,
+        "target": target,
+    })
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::repl::Session;
+
+/// Extension of REPL notebooks
+pub const EXTENSION: &str = "dshpnb";
+
+/// Current version of the notebook format
+const FORMAT_VERSION: u32 = 1;
+
+/// A saved REPL session that can be replayed and shared
+///
+/// Statements are stored with the model that translated them and the output
+/// they added, so a replay can point out where today's build behaves
+/// differently from the recorded one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Notebook {
+    pub format_version: u32,
+    /// nhlp version that recorded the session
+    pub nhlp_version: String,
+    /// Lines fed to the program's standard input
+    #[serde(default)]
+    pub input: Vec<String>,
+    pub cells: Vec<Cell>,
+}
+
+/// One statement of a notebook
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Cell {
+    pub statement: String,
+    /// Model that translated the program when the statement was added
+    #[serde(default)]
+    pub model: String,
+    /// Output the statement added
+    #[serde(default)]
+    pub output: String,
+}
+
+impl Notebook {
+    /// Record a session's statements, models, and outputs
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            nhlp_version: env!("CARGO_PKG_VERSION").to_string(),
+            input: session.input.clone(),
+            cells: session
+                .entries
+                .iter()
+                .map(|entry| Cell {
+                    statement: entry.statement.clone(),
+                    model: entry.model.clone(),
+                    output: entry.output.clone(),
+                })
+                .collect(),
+        }
+    }
+    
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read notebook: {:?}", path))?;
+        let notebook: Self = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid notebook: {:?}", path))?;
+        if notebook.format_version > FORMAT_VERSION {
+            anyhow::bail!(
+                "{:?} was written by nhlp {} in a newer notebook format; upgrade nhlp to open it",
+                path,
+                notebook.nhlp_version
+            );
+        }
+        Ok(notebook)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write notebook: {:?}", path))
+    }
+}
+
+/// Whether `path` names a notebook rather than a plain .dshp program
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == EXTENSION)
+}
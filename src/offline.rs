@@ -0,0 +1,983 @@
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::patterns;
+use crate::verify;
+
+/// Model name recorded for programs translated without an LLM
+pub const MODEL: &str = "offline-rules";
+
+/// `print "Hello, {name}!"`
+const PRINT_TEXT: &str = r#"(?i)^(?:print|display|show|output|say|write)\s+"(?P<text>[^"]*)"$"#;
+
+/// `ask the user for a number called width`
+const ASK: &str = r"(?i)^(?:ask|prompt)\s+(?:the\s+user\s+)?(?:for\s+)?(?:(?:a|an|their|the|some)\s+)?(?P<kind>list\s+of\s+numbers|numbers|number|integer|amount|value|age|price|name|word|text|string|line)(?:,?\s*(?:and\s+)?(?:call\s+it|called|named|store\s+it\s+(?:in|as)|save\s+it\s+(?:in|as)|as)\s+(?P<name>[A-Za-z_]\w*))?$";
+
+/// `set total to the sum of price and tax`
+const SET: &str = r"(?i)^(?:set|let)\s+(?P<name>[A-Za-z_]\w*)\s+(?:to|be|=)\s+(?P<expr>.+)$";
+
+/// `print the product of width and height`
+const PRINT_EXPR: &str = r"(?i)^(?:print|display|show|output)\s+(?:the\s+)?(?:value\s+of\s+)?(?P<expr>.+)$";
+
+/// `the sum of a and b`
+const NAMED_OPERATION: &str = r"(?i)^(?:the\s+)?(?P<op>sum|difference|product|quotient|remainder)\s+of\s+(?P<a>\S+)\s+and\s+(?P<b>\S+)$";
+
+/// `a plus b`
+const INFIX_OPERATION: &str = r"(?i)^(?P<a>\S+)\s+(?P<op>plus|minus|times|multiplied\s+by|divided\s+by|modulo|\+|-|\*|/|%)\s+(?P<b>\S+)$";
+
+/// `the average of scores`, `the count of scores where it is above 50`
+const REDUCE: &str = r"(?i)^(?:the\s+)?(?P<op>sum|total|average|mean|minimum|smallest|lowest|maximum|largest|highest|count|number)\s+of\s+(?:the\s+)?(?P<list>[A-Za-z_]\w*)(?:\s+(?:where|that\s+are|which\s+are)\s+(?:it\s+is\s+|they\s+are\s+|the\s+(?:item|value|number)\s+is\s+)?(?P<cmp>greater\s+than|more\s+than|above|over|less\s+than|below|under|at\s+least|at\s+most|not\s+equal\s+to|equal\s+to|>=|<=|==|!=|>|<|=)\s*(?P<bound>\S+))?$";
+
+/// `for every file in the folder ./logs, print its name`
+const FOR_EACH_FILE: &str = r#"(?i)^for\s+(?:every|each)\s+(?:file|entry)\s+in\s+(?:the\s+)?(?:folder|directory)\s+(?P<path>"[^"]*"|\S+?)\s*,?\s*(?:print|display|show|list)\s+(?:its|the)\s+name$"#;
+
+/// `list the files in the folder ./logs`
+const LIST_FOLDER: &str = r#"(?i)^list\s+(?:all\s+)?(?:the\s+)?(?:files|entries)\s+in\s+(?:the\s+)?(?:folder|directory)\s+(?P<path>"[^"]*"|\S+)$"#;
+
+/// `create the folder ./out`
+const CREATE_FOLDER: &str = r#"(?i)^(?:create|make)\s+(?:the\s+|a\s+)?(?:folder|directory)\s+(?:called\s+|named\s+)?(?P<path>"[^"]*"|\S+)$"#;
+
+/// `print whether the file ./logs/today.txt exists`
+const EXISTS: &str = r#"(?i)^(?:print|display|show|say)\s+whether\s+(?:the\s+)?(?:file|folder|directory|path)\s+(?P<path>"[^"]*"|\S+)\s+exists$"#;
+
+/// `set report to ./logs joined with name`
+const JOIN: &str = r#"(?i)^(?:set|let)\s+(?P<name>[A-Za-z_]\w*)\s+(?:to|be|=)\s+(?:the\s+path\s+)?(?P<folder>"[^"]*"|\S+)\s+joined\s+with\s+(?P<file>"[^"]*"|\S+)$"#;
+
+/// `if line matches a date, print "ok", otherwise print "not a date"`; the match phrase is read by `patterns`
+const IF_MATCHES: &str = r#"(?i)^if\s+(?:the\s+)?(?P<name>[A-Za-z_]\w*)\s+(?P<pattern>match(?:es)?\s+.+?)\s*,?\s*(?:then\s+)?(?:print|display|show|say)\s+"(?P<then>[^"]*)"(?:\s*,?\s*(?:otherwise|else)\s*,?\s*(?:print|display|show|say)\s+"(?P<otherwise>[^"]*)")?$"#;
+
+/// Acceptance criteria describe the program rather than instruct it
+const ACCEPTANCE: &str = r"(?i)^when\s+(?:it\s+is\s+|the\s+program\s+is\s+)?run\s+with";
+
+/// Helpers every translated program starts with
+const PRELUDE: &str = r#"#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Drop the rest of a line too long for the buffer, so the next read starts on the next line */
+static void finish_line(const char *buffer) {
+    if (!strchr(buffer, '\n')) {
+        int c;
+        while ((c = getchar()) != EOF && c != '\n') {
+        }
+    }
+}
+
+static double read_number(const char *label) {
+    char line[256];
+    char *end;
+    printf("Enter %s: ", label);
+    fflush(stdout);
+    if (!fgets(line, sizeof line, stdin)) {
+        fprintf(stderr, "Expected a number for %s\n", label);
+        exit(1);
+    }
+    finish_line(line);
+    double value = strtod(line, &end);
+    if (end == line) {
+        fprintf(stderr, "Expected a number for %s\n", label);
+        exit(1);
+    }
+    return value;
+}
+
+static void read_text(const char *label, char *buffer, size_t size) {
+    printf("Enter %s: ", label);
+    fflush(stdout);
+    if (!fgets(buffer, (int)size, stdin)) {
+        buffer[0] = '\0';
+        return;
+    }
+    finish_line(buffer);
+    buffer[strcspn(buffer, "\n")] = '\0';
+}
+
+/* A number converted to a whole number, saturating at the ends of the range and 0 for NaN */
+static long long whole(double value) {
+    if (value != value) {
+        return 0;
+    }
+    if (value <= (double)LLONG_MIN) {
+        return LLONG_MIN;
+    }
+    if (value >= (double)LLONG_MAX) {
+        return LLONG_MAX;
+    }
+    return (long long)value;
+}
+
+static double remainder_of(double a, double b) {
+    long long divisor = whole(b);
+    if (divisor == 0) {
+        fprintf(stderr, "Remainder after dividing by zero\n");
+        exit(1);
+    }
+    if (divisor == -1) {
+        return 0;
+    }
+    return (double)(whole(a) % divisor);
+}
+
+"#;
+
+/// Helpers added for programs that work with folders and paths
+const FOLDER_PRELUDE: &str = r#"#include <dirent.h>
+#include <errno.h>
+#include <sys/stat.h>
+#ifdef _WIN32
+#include <direct.h>
+#define make_folder(path) _mkdir(path)
+#else
+#define make_folder(path) mkdir(path, 0777)
+#endif
+
+static void list_folder(const char *path) {
+    DIR *folder = opendir(path);
+    if (!folder) {
+        fprintf(stderr, "Can't open the folder %s\n", path);
+        exit(1);
+    }
+    char **names = NULL;
+    size_t count = 0;
+    struct dirent *entry;
+    while ((entry = readdir(folder))) {
+        if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) {
+            continue;
+        }
+        char **grown = realloc(names, (count + 1) * sizeof *names);
+        if (!grown) {
+            fprintf(stderr, "Out of memory listing %s\n", path);
+            exit(1);
+        }
+        names = grown;
+        names[count++] = strdup(entry->d_name);
+    }
+    closedir(folder);
+    for (size_t i = 1; i < count; i++) {
+        char *name = names[i];
+        size_t j = i;
+        while (j > 0 && strcmp(names[j - 1], name) > 0) {
+            names[j] = names[j - 1];
+            j--;
+        }
+        names[j] = name;
+    }
+    for (size_t i = 0; i < count; i++) {
+        printf("%s\n", names[i]);
+        free(names[i]);
+    }
+    free(names);
+}
+
+static void create_folder(const char *path) {
+    if (make_folder(path) != 0 && errno != EEXIST) {
+        fprintf(stderr, "Can't create the folder %s\n", path);
+        exit(1);
+    }
+}
+
+static int path_exists(const char *path) {
+    struct stat info;
+    return stat(path, &info) == 0;
+}
+
+static void join_path(char *buffer, size_t size, const char *folder, const char *name) {
+    char joined[256];
+    size_t length = strlen(folder);
+    snprintf(joined, sizeof joined, "%s%s%s", folder, length > 0 && folder[length - 1] != '/' ? "/" : "", name);
+    snprintf(buffer, size, "%s", joined);
+}
+
+"#;
+
+/// Helpers added for programs that reduce lists of numbers
+///
+/// An unfiltered sum keeps four running totals, one per position modulo 4,
+/// which compilers can keep in one vector register without reordering any
+/// single total's additions; the interpreter adds in the same order.
+const LIST_PRELUDE: &str = r#"typedef struct {
+    double *items;
+    size_t count;
+} number_list;
+
+enum reduction { SUM, AVERAGE, MINIMUM, MAXIMUM, COUNT };
+enum comparison { EVERY, ABOVE, BELOW, AT_LEAST, AT_MOST, EQUAL, NOT_EQUAL };
+
+static number_list read_numbers(const char *label) {
+    number_list list = { NULL, 0 };
+    size_t capacity = 0;
+    char line[256];
+    char *end;
+    printf("Enter %s, one per line, then an empty line: ", label);
+    fflush(stdout);
+    while (fgets(line, sizeof line, stdin) && line[strspn(line, " \t\r\n")] != '\0') {
+        finish_line(line);
+        double value = strtod(line, &end);
+        if (end == line) {
+            fprintf(stderr, "Expected a number for %s\n", label);
+            exit(1);
+        }
+        if (list.count == capacity) {
+            capacity = capacity ? capacity * 2 : 16;
+            double *grown = realloc(list.items, capacity * sizeof *grown);
+            if (!grown) {
+                fprintf(stderr, "Out of memory reading %s\n", label);
+                exit(1);
+            }
+            list.items = grown;
+        }
+        list.items[list.count++] = value;
+    }
+    return list;
+}
+
+static int passes(double item, enum comparison comparison, double bound) {
+    switch (comparison) {
+    case ABOVE: return item > bound;
+    case BELOW: return item < bound;
+    case AT_LEAST: return item >= bound;
+    case AT_MOST: return item <= bound;
+    case EQUAL: return item == bound;
+    case NOT_EQUAL: return item != bound;
+    default: return 1;
+    }
+}
+
+static double sum_numbers(const number_list *list) {
+    double lanes[4] = { 0, 0, 0, 0 };
+    size_t i = 0;
+    for (; i + 4 <= list->count; i += 4) {
+        lanes[0] += list->items[i];
+        lanes[1] += list->items[i + 1];
+        lanes[2] += list->items[i + 2];
+        lanes[3] += list->items[i + 3];
+    }
+    for (; i < list->count; i++) {
+        lanes[i % 4] += list->items[i];
+    }
+    return (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]);
+}
+
+static double reduce_numbers(const number_list *list, enum reduction reduction, enum comparison comparison, double bound, const char *label) {
+    if (comparison == EVERY && reduction == SUM) {
+        return sum_numbers(list);
+    }
+    if (comparison == EVERY && reduction == AVERAGE && list->count > 0) {
+        return sum_numbers(list) / (double)list->count;
+    }
+    double total = 0;
+    double best = 0;
+    size_t count = 0;
+    for (size_t i = 0; i < list->count; i++) {
+        double item = list->items[i];
+        if (!passes(item, comparison, bound)) {
+            continue;
+        }
+        if (count == 0 || (reduction == MINIMUM && item < best) || (reduction == MAXIMUM && item > best)) {
+            best = item;
+        }
+        total += item;
+        count++;
+    }
+    if (count == 0 && (reduction == AVERAGE || reduction == MINIMUM || reduction == MAXIMUM)) {
+        fprintf(stderr, "No numbers in %s to reduce\n", label);
+        exit(1);
+    }
+    switch (reduction) {
+    case SUM: return total;
+    case AVERAGE: return total / (double)count;
+    case MINIMUM:
+    case MAXIMUM: return best;
+    default: return (double)count;
+    }
+}
+
+"#;
+
+/// Helper added for programs that match text against patterns
+const MATCH_PRELUDE: &str = r#"#include <regex.h>
+
+static int matches(const char *text, const char *pattern) {
+    regex_t compiled;
+    if (regcomp(&compiled, pattern, REG_EXTENDED | REG_NOSUB) != 0) {
+        fprintf(stderr, "Invalid pattern %s\n", pattern);
+        exit(1);
+    }
+    int found = regexec(&compiled, text, 0, NULL, 0) == 0;
+    regfree(&compiled);
+    return found;
+}
+
+"#;
+
+/// What a program variable holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Number,
+    Text,
+    /// Numbers read one per line until an empty line
+    List,
+}
+
+/// A number literal or a numeric variable
+#[derive(Debug, Clone)]
+pub enum Operand {
+    Literal(f64),
+    Variable(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    /// Remainder after converting both operands to whole numbers; a zero divisor stops the program
+    Remainder,
+}
+
+/// How a list of numbers is reduced to one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    Sum,
+    Average,
+    Minimum,
+    Maximum,
+    Count,
+}
+
+/// Which items of a list a reduction takes, compared against a bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    Below,
+    AtLeast,
+    AtMost,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn holds(self, item: f64, bound: f64) -> bool {
+        match self {
+            Comparison::Above => item > bound,
+            Comparison::Below => item < bound,
+            Comparison::AtLeast => item >= bound,
+            Comparison::AtMost => item <= bound,
+            Comparison::Equal => item == bound,
+            Comparison::NotEqual => item != bound,
+        }
+    }
+}
+
+/// A numeric phrase: one operand, arithmetic on two, or a reduction of a list
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Value(Operand),
+    Binary(Operand, Op, Operand),
+    /// A reduction of the named list, over only the items passing the filter if there is one
+    Reduce { reduction: Reduction, list: String, filter: Option<(Comparison, Operand)> },
+}
+
+/// A path or part of one: text as written, or a text variable
+#[derive(Debug, Clone)]
+pub enum PathPart {
+    Literal(String),
+    Variable(String),
+}
+
+/// Part of printed text
+#[derive(Debug, Clone)]
+pub enum Piece {
+    Text(String),
+    /// A `{name}` placeholder
+    Variable(String, Kind),
+}
+
+/// What one understood sentence does
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// Print text, filling in `{name}` placeholders
+    Print(Vec<Piece>),
+    /// Prompt for a value and read it from standard input
+    Ask { name: String, kind: Kind, declared: bool },
+    /// Set a numeric variable
+    Set { name: String, value: Expr, declared: bool },
+    /// Print a text variable
+    PrintText(String),
+    /// Print a number
+    PrintNumber(Expr),
+    /// Print the name of every entry in a folder, sorted, one per line
+    ListFolder(PathPart),
+    /// Create a folder unless it already exists
+    CreateFolder(PathPart),
+    /// Print yes or no for whether a file or folder exists
+    PrintExists(PathPart),
+    /// Set a text variable to a folder joined with a name, with one `/` between them
+    Join { name: String, folder: PathPart, file: PathPart, declared: bool },
+    /// Print one text or another depending on whether a text variable matches a POSIX extended regular expression
+    PrintIfMatches { name: String, regex: String, then: Vec<Piece>, otherwise: Option<Vec<Piece>> },
+}
+
+impl Statement {
+    /// Whether the statement needs the folder helpers
+    fn uses_folders(&self) -> bool {
+        matches!(
+            self,
+            Statement::ListFolder(_) | Statement::CreateFolder(_) | Statement::PrintExists(_) | Statement::Join { .. }
+        )
+    }
+    
+    /// Whether the statement needs the list helpers
+    fn uses_lists(&self) -> bool {
+        match self {
+            Statement::Ask { kind, .. } => *kind == Kind::List,
+            Statement::Set { value, .. } | Statement::PrintNumber(value) => matches!(value, Expr::Reduce { .. }),
+            _ => false,
+        }
+    }
+    
+    /// Whether the statement needs the pattern matching helper
+    fn uses_matching(&self) -> bool {
+        matches!(self, Statement::PrintIfMatches { .. })
+    }
+}
+
+/// A sentence of the program and what it does
+#[derive(Debug, Clone)]
+pub struct Sentence {
+    /// Line of the program the sentence is on, counting from 1
+    pub line: usize,
+    pub text: String,
+    pub statement: Statement,
+}
+
+/// Translate a program written in the supported sentence forms to C
+///
+/// Only a small, fixed vocabulary is understood: printing text with
+/// `{variable}` placeholders, asking for numbers and text, setting variables
+/// to arithmetic on two operands or to the sum, average, minimum, maximum,
+/// or count of a list of numbers, printing values, listing, creating, and
+/// checking folders and paths, and printing depending on whether text
+/// matches a pattern. Any other sentence is an error rather than a guess.
+///
+/// A remainder whose divisor `verify` proves can't be 0 or -1 is computed
+/// directly, without `remainder_of`'s runtime checks.
+pub fn translate(program: &str) -> Result<String> {
+    let sentences = parse(program)?;
+    let verification = verify::verify(&sentences);
+    let mut body = String::new();
+    for (index, sentence) in sentences.iter().enumerate() {
+        let _ = writeln!(body, "    // {}", sentence.text.replace('\n', " "));
+        body.push_str(&c_statement(&sentence.statement, verification.unchecked_remainder(index)));
+    }
+    let folders = if sentences.iter().any(|sentence| sentence.statement.uses_folders()) { FOLDER_PRELUDE } else { "" };
+    let lists = if sentences.iter().any(|sentence| sentence.statement.uses_lists()) { LIST_PRELUDE } else { "" };
+    let matching = if sentences.iter().any(|sentence| sentence.statement.uses_matching()) { MATCH_PRELUDE } else { "" };
+    Ok(format!("{}{}{}{}int main(void) {{\n{}    return 0;\n}}\n", PRELUDE, folders, lists, matching, body))
+}
+
+/// Parse a program written in the supported sentence forms
+///
+/// Fails, listing them, if any sentence is outside the vocabulary.
+pub fn parse(program: &str) -> Result<Vec<Sentence>> {
+    let rules = Rules::new();
+    let mut variables = BTreeMap::new();
+    let mut parsed = Vec::new();
+    let mut unsupported = Vec::new();
+    
+    for (line, sentence) in sentences(program) {
+        if rules.acceptance.is_match(&sentence) {
+            continue;
+        }
+        match rules.statement(&sentence, &mut variables) {
+            Some(statement) => parsed.push(Sentence { line, text: sentence, statement }),
+            None => unsupported.push(sentence),
+        }
+    }
+    
+    if !unsupported.is_empty() {
+        anyhow::bail!(
+            "Offline mode can't translate {} sentence(s):\n  {}\nUse print \"...\", ask for a number/text called NAME, set NAME to ..., print NAME, \
+             a folder sentence such as list the files in the folder PATH, or if NAME matches a date, print \"...\".",
+            unsupported.len(),
+            unsupported.join("\n  ")
+        );
+    }
+    if parsed.is_empty() {
+        anyhow::bail!("Offline mode found no statements to translate");
+    }
+    Ok(parsed)
+}
+
+/// The compiled sentence patterns
+struct Rules {
+    print_text: Regex,
+    ask: Regex,
+    set: Regex,
+    print_expr: Regex,
+    named_operation: Regex,
+    infix_operation: Regex,
+    reduce: Regex,
+    for_each_file: Regex,
+    list_folder: Regex,
+    create_folder: Regex,
+    exists: Regex,
+    join: Regex,
+    if_matches: Regex,
+    acceptance: Regex,
+}
+
+impl Rules {
+    fn new() -> Self {
+        let compile = |pattern| Regex::new(pattern).expect("offline pattern is valid");
+        Self {
+            print_text: compile(PRINT_TEXT),
+            ask: compile(ASK),
+            set: compile(SET),
+            print_expr: compile(PRINT_EXPR),
+            named_operation: compile(NAMED_OPERATION),
+            infix_operation: compile(INFIX_OPERATION),
+            reduce: compile(REDUCE),
+            for_each_file: compile(FOR_EACH_FILE),
+            list_folder: compile(LIST_FOLDER),
+            create_folder: compile(CREATE_FOLDER),
+            exists: compile(EXISTS),
+            join: compile(JOIN),
+            if_matches: compile(IF_MATCHES),
+            acceptance: compile(ACCEPTANCE),
+        }
+    }
+
+    /// What one sentence does, or None if the sentence isn't understood
+    fn statement(&self, sentence: &str, variables: &mut BTreeMap<String, Kind>) -> Option<Statement> {
+        if let Some(captures) = self.print_text.captures(sentence) {
+            return pieces(&captures["text"], variables).map(Statement::Print);
+        }
+        
+        if let Some(captures) = self.ask.captures(sentence) {
+            let kind_word = captures["kind"].to_ascii_lowercase();
+            let name = captures.name("name").map_or(kind_word.as_str(), |m| m.as_str()).to_string();
+            let kind = match kind_word.as_str() {
+                "name" | "word" | "text" | "string" | "line" => Kind::Text,
+                "numbers" => Kind::List,
+                word if word.starts_with("list") => Kind::List,
+                _ => Kind::Number,
+            };
+            let name = if kind == Kind::List && captures.name("name").is_none() { "numbers".to_string() } else { name };
+            if variables.get(&name).is_some_and(|existing| *existing != kind) {
+                return None;
+            }
+            let declared = variables.insert(name.clone(), kind).is_some();
+            return Some(Statement::Ask { name, kind, declared });
+        }
+        
+        if let Some(captures) = self.for_each_file.captures(sentence).or_else(|| self.list_folder.captures(sentence)) {
+            return path_part(&captures["path"], variables).map(Statement::ListFolder);
+        }
+        
+        if let Some(captures) = self.create_folder.captures(sentence) {
+            return path_part(&captures["path"], variables).map(Statement::CreateFolder);
+        }
+        
+        if let Some(captures) = self.exists.captures(sentence) {
+            return path_part(&captures["path"], variables).map(Statement::PrintExists);
+        }
+        
+        if let Some(captures) = self.join.captures(sentence) {
+            let name = captures["name"].to_string();
+            let folder = path_part(&captures["folder"], variables)?;
+            let file = path_part(&captures["file"], variables)?;
+            if variables.get(&name).is_some_and(|existing| *existing != Kind::Text) {
+                return None;
+            }
+            let declared = variables.insert(name.clone(), Kind::Text).is_some();
+            return Some(Statement::Join { name, folder, file, declared });
+        }
+        
+        if let Some(captures) = self.if_matches.captures(sentence) {
+            let name = captures["name"].to_string();
+            if variables.get(&name) != Some(&Kind::Text) {
+                return None;
+            }
+            let regex = match patterns::extract(&captures["pattern"]).ok()?.as_slice() {
+                [pattern] => pattern.regex.clone(),
+                _ => return None,
+            };
+            let then = pieces(&captures["then"], variables)?;
+            let otherwise = match captures.name("otherwise") {
+                Some(text) => Some(pieces(text.as_str(), variables)?),
+                None => None,
+            };
+            return Some(Statement::PrintIfMatches { name, regex, then, otherwise });
+        }
+        
+        if let Some(captures) = self.set.captures(sentence) {
+            let name = captures["name"].to_string();
+            let value = self.expression(&captures["expr"], variables)?;
+            if variables.get(&name).is_some_and(|existing| *existing != Kind::Number) {
+                return None;
+            }
+            let declared = variables.insert(name.clone(), Kind::Number).is_some();
+            return Some(Statement::Set { name, value, declared });
+        }
+        
+        if let Some(captures) = self.print_expr.captures(sentence) {
+            let expr = &captures["expr"];
+            if variables.get(expr) == Some(&Kind::Text) {
+                return Some(Statement::PrintText(expr.to_string()));
+            }
+            return self.expression(expr, variables).map(Statement::PrintNumber);
+        }
+        
+        None
+    }
+
+    /// A numeric phrase
+    fn expression(&self, phrase: &str, variables: &BTreeMap<String, Kind>) -> Option<Expr> {
+        let phrase = phrase.trim();
+        let (a, op, b) = if let Some(captures) = self.named_operation.captures(phrase) {
+            let op = match captures["op"].to_ascii_lowercase().as_str() {
+                "sum" => Op::Add,
+                "difference" => Op::Subtract,
+                "product" => Op::Multiply,
+                "quotient" => Op::Divide,
+                _ => Op::Remainder,
+            };
+            (captures["a"].to_string(), op, captures["b"].to_string())
+        } else if let Some(captures) = self.infix_operation.captures(phrase) {
+            let op = match captures["op"].to_ascii_lowercase().split_whitespace().next().unwrap_or_default() {
+                "plus" | "+" => Op::Add,
+                "minus" | "-" => Op::Subtract,
+                "times" | "multiplied" | "*" => Op::Multiply,
+                "divided" | "/" => Op::Divide,
+                _ => Op::Remainder,
+            };
+            (captures["a"].to_string(), op, captures["b"].to_string())
+        } else if let Some(captures) = self.reduce.captures(phrase) {
+            return reduction(&captures, variables);
+        } else {
+            return operand(phrase, variables).map(Expr::Value);
+        };
+        
+        Some(Expr::Binary(operand(&a, variables)?, op, operand(&b, variables)?))
+    }
+}
+
+/// A reduction of a list variable, with its filter
+fn reduction(captures: &regex::Captures, variables: &BTreeMap<String, Kind>) -> Option<Expr> {
+    let list = captures["list"].to_string();
+    if variables.get(&list) != Some(&Kind::List) {
+        return None;
+    }
+    let reduction = match captures["op"].to_ascii_lowercase().as_str() {
+        "sum" | "total" => Reduction::Sum,
+        "average" | "mean" => Reduction::Average,
+        "minimum" | "smallest" | "lowest" => Reduction::Minimum,
+        "maximum" | "largest" | "highest" => Reduction::Maximum,
+        _ => Reduction::Count,
+    };
+    let filter = match captures.name("cmp") {
+        Some(comparison) => {
+            let comparison = match comparison.as_str().to_ascii_lowercase().split_whitespace().collect::<Vec<_>>().join(" ").as_str() {
+                "greater than" | "more than" | "above" | "over" | ">" => Comparison::Above,
+                "less than" | "below" | "under" | "<" => Comparison::Below,
+                "at least" | ">=" => Comparison::AtLeast,
+                "at most" | "<=" => Comparison::AtMost,
+                "not equal to" | "!=" => Comparison::NotEqual,
+                _ => Comparison::Equal,
+            };
+            Some((comparison, operand(&captures["bound"], variables)?))
+        }
+        None => None,
+    };
+    Some(Expr::Reduce { reduction, list, filter })
+}
+
+/// A number literal or a numeric variable
+///
+/// Literals must be finite, since `inf` and `nan` parse as numbers but aren't C literals.
+fn operand(word: &str, variables: &BTreeMap<String, Kind>) -> Option<Operand> {
+    let word = word.trim();
+    if let Some(literal) = word.parse::<f64>().ok().filter(|literal| literal.is_finite()) {
+        return Some(Operand::Literal(literal));
+    }
+    match variables.get(word) {
+        Some(Kind::Number) => Some(Operand::Variable(word.to_string())),
+        _ => None,
+    }
+}
+
+/// A quoted or bare path, or a text variable; numeric variables can't be paths
+fn path_part(word: &str, variables: &BTreeMap<String, Kind>) -> Option<PathPart> {
+    if let Some(quoted) = word.strip_prefix('"').and_then(|word| word.strip_suffix('"')) {
+        return Some(PathPart::Literal(quoted.to_string()));
+    }
+    match variables.get(word) {
+        Some(Kind::Text) => Some(PathPart::Variable(word.to_string())),
+        Some(Kind::Number | Kind::List) => None,
+        None => Some(PathPart::Literal(word.to_string())),
+    }
+}
+
+/// Text split at its `{variable}` placeholders
+fn pieces(text: &str, variables: &BTreeMap<String, Kind>) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let close = open + rest[open..].find('}')?;
+        pieces.push(Piece::Text(rest[..open].to_string()));
+        let name = rest[open + 1..close].trim();
+        let kind = variables.get(name).filter(|kind| **kind != Kind::List)?;
+        pieces.push(Piece::Variable(name.to_string(), *kind));
+        rest = &rest[close + 1..];
+    }
+    pieces.push(Piece::Text(rest.to_string()));
+    Some(pieces)
+}
+
+/// C statements for one sentence, with its remainder unchecked if `unchecked` is set
+fn c_statement(statement: &Statement, unchecked: bool) -> String {
+    match statement {
+        Statement::Print(pieces) => format!("    {}\n", c_print(pieces)),
+        Statement::Ask { name, kind, declared } => {
+            let ident = identifier(name);
+            let label = c_string(name);
+            match (kind, declared) {
+                (Kind::Number, true) => format!("    {} = read_number({});\n", ident, label),
+                (Kind::Number, false) => format!("    double {} = read_number({});\n", ident, label),
+                (Kind::Text, true) => format!("    read_text({}, {}, sizeof {});\n", label, ident, ident),
+                (Kind::Text, false) => format!("    char {}[256];\n    read_text({}, {}, sizeof {});\n", ident, label, ident, ident),
+                (Kind::List, true) => format!("    free({}.items);\n    {} = read_numbers({});\n", ident, ident, label),
+                (Kind::List, false) => format!("    number_list {} = read_numbers({});\n", ident, label),
+            }
+        }
+        Statement::Set { name, value, declared } => {
+            if *declared {
+                format!("    {} = {};\n", identifier(name), c_expression(value, unchecked))
+            } else {
+                format!("    double {} = {};\n", identifier(name), c_expression(value, unchecked))
+            }
+        }
+        Statement::PrintText(name) => format!("    printf(\"%s\\n\", {});\n", identifier(name)),
+        Statement::PrintNumber(value) => format!("    printf(\"%g\\n\", (double)({}));\n", c_expression(value, unchecked)),
+        Statement::ListFolder(path) => format!("    list_folder({});\n", c_path(path)),
+        Statement::CreateFolder(path) => format!("    create_folder({});\n", c_path(path)),
+        Statement::PrintExists(path) => format!("    printf(\"%s\\n\", path_exists({}) ? \"yes\" : \"no\");\n", c_path(path)),
+        Statement::Join { name, folder, file, declared } => {
+            let ident = identifier(name);
+            let declaration = if *declared { String::new() } else { format!("    char {}[256];\n", ident) };
+            format!("{}    join_path({}, sizeof {}, {}, {});\n", declaration, ident, ident, c_path(folder), c_path(file))
+        }
+        Statement::PrintIfMatches { name, regex, then, otherwise } => {
+            let mut code = format!("    if (matches({}, {})) {{\n        {}\n    }}", identifier(name), c_string(regex), c_print(then));
+            if let Some(otherwise) = otherwise {
+                let _ = write!(code, " else {{\n        {}\n    }}", c_print(otherwise));
+            }
+            code.push('\n');
+            code
+        }
+    }
+}
+
+/// A printf call for text with `{variable}` placeholders, ending the line
+fn c_print(pieces: &[Piece]) -> String {
+    let mut format = String::new();
+    let mut arguments = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => format.push_str(&escape(text).replace('%', "%%")),
+            Piece::Variable(name, kind) => {
+                format.push_str(if *kind == Kind::Number { "%g" } else { "%s" });
+                let _ = write!(arguments, ", {}", identifier(name));
+            }
+        }
+    }
+    format!("printf(\"{}\\n\"{});", format, arguments)
+}
+
+fn c_path(path: &PathPart) -> String {
+    match path {
+        PathPart::Literal(literal) => c_string(literal),
+        PathPart::Variable(name) => identifier(name),
+    }
+}
+
+fn c_expression(expr: &Expr, unchecked: bool) -> String {
+    let c_operand = |operand: &Operand| match operand {
+        Operand::Literal(literal) => c_number(*literal),
+        Operand::Variable(name) => identifier(name),
+    };
+    match expr {
+        Expr::Value(operand) => c_operand(operand),
+        Expr::Binary(a, Op::Remainder, b) if unchecked => format!("(double)(whole({}) % whole({}))", c_operand(a), c_operand(b)),
+        Expr::Binary(a, Op::Remainder, b) => format!("remainder_of({}, {})", c_operand(a), c_operand(b)),
+        Expr::Binary(a, op, b) => {
+            let symbol = match op {
+                Op::Add => "+",
+                Op::Subtract => "-",
+                Op::Multiply => "*",
+                _ => "/",
+            };
+            format!("({} {} {})", c_operand(a), symbol, c_operand(b))
+        }
+        Expr::Reduce { reduction, list, filter } => {
+            let reduction = match reduction {
+                Reduction::Sum => "SUM",
+                Reduction::Average => "AVERAGE",
+                Reduction::Minimum => "MINIMUM",
+                Reduction::Maximum => "MAXIMUM",
+                Reduction::Count => "COUNT",
+            };
+            let (comparison, bound) = match filter {
+                Some((comparison, bound)) => {
+                    let comparison = match comparison {
+                        Comparison::Above => "ABOVE",
+                        Comparison::Below => "BELOW",
+                        Comparison::AtLeast => "AT_LEAST",
+                        Comparison::AtMost => "AT_MOST",
+                        Comparison::Equal => "EQUAL",
+                        Comparison::NotEqual => "NOT_EQUAL",
+                    };
+                    (comparison, c_operand(bound))
+                }
+                None => ("EVERY", "0".to_string()),
+            };
+            format!("reduce_numbers(&{}, {}, {}, {}, {})", identifier(list), reduction, comparison, bound, c_string(list))
+        }
+    }
+}
+
+/// Split a program into sentences with their line numbers, keeping quoted text intact
+pub fn sentences(program: &str) -> Vec<(usize, String)> {
+    let mut sentences = Vec::new();
+    for (index, line) in program.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+        
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            let ends = !quoted && matches!(c, '.' | '!' | '?' | ';') && chars.peek().is_none_or(|next| next.is_whitespace());
+            if ends {
+                push_sentence(&mut sentences, index + 1, &current);
+                current.clear();
+            } else {
+                current.push(c);
+            }
+        }
+        push_sentence(&mut sentences, index + 1, &current);
+    }
+    sentences
+}
+
+fn push_sentence(sentences: &mut Vec<(usize, String)>, line: usize, sentence: &str) {
+    let sentence = sentence.trim().trim_start_matches(['-', '*']).trim();
+    if !sentence.is_empty() {
+        sentences.push((line, sentence.to_string()));
+    }
+}
+
+/// C identifier for a program variable, prefixed so names can't collide with keywords
+fn identifier(name: &str) -> String {
+    format!("v_{}", name)
+}
+
+/// A C double literal for a number
+///
+/// Written from the parsed value rather than as the spec spells it, since C
+/// reads a leading zero as octal and `010` would otherwise be 8.
+fn c_number(value: f64) -> String {
+    format!("{:?}", value)
+}
+
+/// A C string literal
+fn c_string(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
+
+/// Escape text for use inside a C string literal
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statements(program: &str) -> Vec<Statement> {
+        parse(program).expect("program is in the vocabulary").into_iter().map(|sentence| sentence.statement).collect()
+    }
+
+    #[test]
+    fn sentences_end_at_punctuation_outside_quotes() {
+        let program = "Print \"Done. Bye!\". Set x to 2.\n\n# a comment\n- Print x.";
+        assert_eq!(
+            sentences(program),
+            [(1, "Print \"Done. Bye!\"".to_string()), (1, "Set x to 2".to_string()), (4, "Print x".to_string())]
+        );
+    }
+
+    #[test]
+    fn arithmetic_phrases_become_binary_expressions() {
+        let parsed = statements("Set x to the remainder of 7 and 3.\nSet y to x divided by 2.");
+        assert!(matches!(
+            &parsed[0],
+            Statement::Set { value: Expr::Binary(Operand::Literal(a), Op::Remainder, Operand::Literal(b)), declared: false, .. }
+                if *a == 7.0 && *b == 3.0
+        ));
+        assert!(matches!(
+            &parsed[1],
+            Statement::Set { value: Expr::Binary(Operand::Variable(x), Op::Divide, Operand::Literal(_)), .. } if x == "x"
+        ));
+    }
+
+    #[test]
+    fn reassigning_a_variable_keeps_its_declaration() {
+        let parsed = statements("Ask for a number called n.\nSet n to n times 2.");
+        assert!(matches!(&parsed[0], Statement::Ask { kind: Kind::Number, declared: false, .. }));
+        assert!(matches!(&parsed[1], Statement::Set { declared: true, .. }));
+    }
+
+    #[test]
+    fn changing_a_variables_kind_is_not_understood() {
+        assert!(parse("Ask for a name called who.\nSet who to 3 plus 4.").is_err());
+    }
+
+    #[test]
+    fn unsupported_sentences_are_listed() {
+        let error = parse("Print \"hi\".\nMake it fast.\nBe nice.").unwrap_err().to_string();
+        assert!(error.contains("2 sentence(s)"), "{}", error);
+        assert!(error.contains("Make it fast") && error.contains("Be nice"), "{}", error);
+    }
+
+    #[test]
+    fn placeholders_print_with_their_kinds_format() {
+        let code = translate("Ask for a name called who.\nAsk for a number called n.\nPrint \"{who} has 100% of {n}\".").unwrap();
+        assert!(code.contains("printf(\"%s has 100%% of %g\\n\", v_who, v_n);"), "{}", code);
+    }
+
+    #[test]
+    fn literals_are_written_from_their_value() {
+        let code = translate("Set x to 010 plus 1e5.").unwrap();
+        assert!(code.contains("double v_x = (10.0 + 100000.0);"), "{}", code);
+        assert!(parse("Set x to inf plus 1.").is_err());
+    }
+
+    #[test]
+    fn helpers_are_included_only_when_used() {
+        let code = translate("Print \"hi\".").unwrap();
+        assert!(!code.contains("list_folder") && !code.contains("regcomp"));
+        let code = translate("List the files in the folder ./logs.").unwrap();
+        assert!(code.contains("list_folder(\"./logs\");"), "{}", code);
+    }
+}
@@ -0,0 +1,44 @@
+/// Optimization level the generated program is compiled at
+///
+/// Without `-O`, the toolchains' defaults apply, which for gcc, clang, and
+/// rustc is no optimization.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization; fastest to build and easiest to debug
+    #[value(name = "0")]
+    O0,
+    /// Basic optimizations
+    #[value(name = "1")]
+    O1,
+    /// Most optimizations that don't trade size for speed
+    #[value(name = "2")]
+    O2,
+    /// Everything in 2 plus aggressive inlining and vectorization
+    #[value(name = "3")]
+    O3,
+    /// Optimize for size
+    #[value(name = "s")]
+    Size,
+}
+
+impl OptLevel {
+    /// The level as the toolchains spell it after `-O` or `opt-level=`
+    fn name(self) -> &'static str {
+        match self {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+            OptLevel::O3 => "3",
+            OptLevel::Size => "s",
+        }
+    }
+    
+    /// Toolchain arguments selecting this level when compiling `language`
+    pub fn driver_args(self, language: &str) -> Vec<String> {
+        if language == "rust" {
+            vec!["-C".to_string(), format!("opt-level={}", self.name())]
+        } else {
+            vec![format!("-O{}", self.name())]
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `matches a date like 2024-01-31`, `matches the pattern "[A-Z]+"`, or `matches /^[0-9]+$/`
+const MATCH_PATTERN: &str = r#"(?i)\bmatch(?:es|ing)?\s+(?:(?:the\s+)?(?:pattern|regex|regular\s+expression)\s+(?:"(?P<quoted>[^"]+)"|`(?P<ticked>[^`]+)`)|/(?P<slashed>[^/\s][^/]*)/|(?:an?\s+)?(?P<named>date|time|email\s+address|email|number|ip\s+address|hex\s+colou?r))(?:\s+like\s+(?P<example>[^\s,;]+?))?[.,;]?(?:\s|$)"#;
+
+static MATCH: LazyLock<Regex> = LazyLock::new(|| Regex::new(MATCH_PATTERN).expect("match pattern is valid"));
+
+/// Kinds of text specs describe by name, keyed by their first word, as POSIX
+/// extended regular expressions
+///
+/// Each is also valid for the regex crate, which checks examples against it,
+/// and is anchored since `matches a date` means the whole text is one.
+const NAMED: &[(&str, &str)] = &[
+    ("date", "^[0-9]{4}-[0-9]{2}-[0-9]{2}$"),
+    ("time", "^[0-9]{2}:[0-9]{2}(:[0-9]{2})?$"),
+    ("email", "^[^@[:space:]]+@[^@[:space:]]+\\.[A-Za-z]+$"),
+    ("number", "^-?[0-9]+(\\.[0-9]+)?$"),
+    ("ip", "^[0-9]{1,3}(\\.[0-9]{1,3}){3}$"),
+    ("hex", "^#[0-9A-Fa-f]{6}$"),
+];
+
+/// Syntax the regex crate accepts but POSIX regcomp doesn't, with what to write instead
+const NOT_POSIX: &[(&str, &str)] = &[("\\d", "[0-9]"), ("\\D", "[^0-9]"), ("(?", "a plain group"), ("*?", "*"), ("+?", "+")];
+
+/// A pattern a spec matches text against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// The spec text describing the match
+    pub phrase: String,
+    /// POSIX extended regular expression for it
+    pub regex: String,
+}
+
+/// Patterns the spec matches text against, checked before anything is translated
+///
+/// Fails on an expression that doesn't compile or uses syntax regcomp lacks,
+/// or on an example given with `like` that the pattern doesn't match, since
+/// the program would be built around a pattern that can't be what the spec
+/// meant.
+pub fn extract(description: &str) -> Result<Vec<Pattern>> {
+    let mut found: Vec<Pattern> = Vec::new();
+    for captures in MATCH.captures_iter(description) {
+        let phrase = captures[0].trim().trim_end_matches(['.', ',', ';']).to_string();
+        let regex = if let Some(named) = captures.name("named") {
+            let name = named.as_str().split_whitespace().next().unwrap_or_default().to_lowercase();
+            NAMED.iter().find(|(known, _)| *known == name).map_or(String::new(), |(_, regex)| regex.to_string())
+        } else {
+            let literal = captures.name("quoted").or_else(|| captures.name("ticked")).or_else(|| captures.name("slashed"));
+            literal.map_or("", |m| m.as_str()).to_string()
+        };
+        
+        if let Some((syntax, instead)) = NOT_POSIX.iter().find(|(syntax, _)| regex.contains(syntax)) {
+            anyhow::bail!("\"{}\" uses {}, which POSIX regular expressions don't have; write {} instead", phrase, syntax, instead);
+        }
+        let compiled = Regex::new(&regex).map_err(|e| anyhow::anyhow!("\"{}\" has an invalid pattern: {}", phrase, e))?;
+        if let Some(example) = captures.name("example") {
+            if !compiled.is_match(example.as_str()) {
+                anyhow::bail!("\"{}\": the pattern {} doesn't match the example {}", phrase, regex, example.as_str());
+            }
+        }
+        if !found.iter().any(|existing| existing.regex == regex) {
+            found.push(Pattern { phrase, regex });
+        }
+    }
+    Ok(found)
+}
+
+/// Instructions added to the translation prompt for programs that match patterns
+///
+/// C programs use the POSIX regex functions from libc, compiled once at
+/// startup; Rust programs can't use the regex crate, so each pattern becomes
+/// a hand-written matcher.
+pub fn prompt_note(language: &str, patterns: &[Pattern]) -> String {
+    let how = if language == "rust" {
+        "The regex crate is not available: write one matcher function per pattern that accepts exactly \
+         what the expression accepts, and returns the matched parts when the description uses them."
+    } else {
+        "Use <regex.h>: compile each pattern once at startup with regcomp and REG_EXTENDED, exiting with \
+         an error if regcomp fails; match with regexec, reading regmatch_t offsets when the description \
+         extracts parts; call regfree before exiting."
+    };
+    let mut note = format!(
+        "\nPATTERNS: match text with these POSIX extended regular expressions, exactly as given; one \
+         anchored with ^ and $ must match the whole text, others may match anywhere in it. {}\n",
+        how
+    );
+    for pattern in patterns {
+        note.push_str(&format!("- \"{}\": {}\n", pattern.phrase, pattern.regex));
+    }
+    note
+}
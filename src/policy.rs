@@ -0,0 +1,162 @@
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+static PATTERNS: LazyLock<[Regex; 4]> = LazyLock::new(|| Construct::ALL.map(|construct| Regex::new(construct.pattern()).expect("construct pattern is valid")));
+
+/// Potentially unsafe constructs that can appear in generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Construct {
+    RawPointer,
+    InlineAsm,
+    UncheckedIndexing,
+    ExternalCall,
+}
+
+impl Construct {
+    const ALL: [Construct; 4] = [
+        Construct::RawPointer,
+        Construct::InlineAsm,
+        Construct::UncheckedIndexing,
+        Construct::ExternalCall,
+    ];
+    
+    /// Pattern matching the construct in generated C or Rust code
+    fn pattern(self) -> &'static str {
+        match self {
+            // Rust raw pointer types and C casts to pointer types
+            Construct::RawPointer => r"\*(?:const|mut)\s|\(\s*(?:const\s+)?\w+\s*\*+\s*\)",
+            Construct::InlineAsm => r"\basm!|\b__asm__\b|\basm\s*(?:volatile\s*)?\(",
+            // Accesses and copies that skip bounds checks
+            Construct::UncheckedIndexing => r"\bget_unchecked(?:_mut)?\b|\b(?:gets|strcpy|strcat|sprintf|vsprintf)\s*\(",
+            // Foreign function interfaces and dynamic loading
+            Construct::ExternalCall => r#"\bextern\s+"C"|\b(?:dlopen|dlsym|LoadLibrary\w*|GetProcAddress)\s*\("#,
+        }
+    }
+    
+    /// Level applied when the project doesn't configure one
+    fn default_level(self) -> Level {
+        match self {
+            Construct::InlineAsm => Level::Deny,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl fmt::Display for Construct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Construct::RawPointer => "raw-pointer",
+            Construct::InlineAsm => "inline-asm",
+            Construct::UncheckedIndexing => "unchecked-indexing",
+            Construct::ExternalCall => "external-call",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Construct {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        Construct::ALL
+            .into_iter()
+            .find(|construct| construct.to_string() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown construct: {}", s))
+    }
+}
+
+/// How strictly a construct is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(Level::Allow),
+            "warn" => Ok(Level::Warn),
+            "deny" => Ok(Level::Deny),
+            _ => Err(anyhow::anyhow!("Unknown policy level: {} (expected allow, warn, or deny)", s)),
+        }
+    }
+}
+
+/// A construct found in generated code
+pub struct Finding {
+    pub construct: Construct,
+    pub level: Level,
+    /// Line number in the generated code, counting from 1
+    pub line: usize,
+    pub snippet: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}: `{}`", self.construct, self.line, self.snippet)
+    }
+}
+
+/// Allow/warn/deny levels for each construct
+#[derive(Debug, Clone, Default)]
+pub struct ConstructPolicy {
+    overrides: BTreeMap<Construct, Level>,
+}
+
+impl ConstructPolicy {
+    /// Parse overrides such as `inline-asm=allow`
+    pub fn from_overrides(overrides: &[String]) -> Result<Self> {
+        let mut policy = Self::default();
+        for entry in overrides {
+            let (construct, level) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid construct policy `{}`, expected construct=level", entry))?;
+            policy.overrides.insert(construct.trim().parse()?, level.trim().parse()?);
+        }
+        Ok(policy)
+    }
+    
+    /// Level in effect for a construct
+    pub fn level(&self, construct: Construct) -> Level {
+        self.overrides.get(&construct).copied().unwrap_or_else(|| construct.default_level())
+    }
+    
+    /// Find every construct in `code` that isn't allowed
+    pub fn check(&self, code: &str) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        
+        for construct in Construct::ALL {
+            let level = self.level(construct);
+            if level == Level::Allow {
+                continue;
+            }
+            
+            let pattern = &PATTERNS[construct as usize];
+            for (index, line) in code.lines().enumerate() {
+                let trimmed = line.trim();
+                if trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with('*') {
+                    continue;
+                }
+                if pattern.is_match(line) {
+                    findings.push(Finding {
+                        construct,
+                        level,
+                        line: index + 1,
+                        snippet: trimmed.to_string(),
+                    });
+                }
+            }
+        }
+        
+        findings.sort_by_key(|finding| finding.line);
+        findings
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use crate::offline;
+use crate::redact::{self, Redactor};
+use crate::report::CompilationReport;
+use crate::sourcemap::SourceMap;
+
+/// Most precedents shown to the model for one program
+const MAX_PRECEDENTS: usize = 4;
+
+/// Cosine similarity below which a past sentence isn't a precedent
+const MIN_SIMILARITY: f64 = 0.6;
+
+/// Sentences whose code runs longer than this make poor examples and aren't recorded
+const MAX_LINES: usize = 30;
+
+/// Entries kept in the index, dropping the oldest beyond it
+const MAX_ENTRIES: usize = 5000;
+
+/// Words too common to say anything about what a sentence does
+const STOP_WORDS: &[&str] = &["a", "an", "the", "to", "of", "and", "or", "in", "on", "it", "its", "is", "be", "for", "with", "then"];
+
+/// One sentence of an earlier compilation and the code written for it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Precedent {
+    /// Spec the sentence came from, empty when unknown
+    #[serde(default)]
+    pub spec: String,
+    pub sentence: String,
+    /// Language of the code, "c" or "rust"
+    pub language: String,
+    pub code: String,
+}
+
+/// Sentences of earlier compilations and their code, kept in the cache directory
+///
+/// Sentences are compared as word-count vectors by cosine similarity, which
+/// needs no embedding model and so works offline and without quota.
+pub struct PrecedentIndex {
+    path: PathBuf,
+    entries: Vec<Precedent>,
+}
+
+impl PrecedentIndex {
+    /// Path of the index kept in the response cache directory `cache_dir`
+    pub fn path_in(cache_dir: &Path) -> PathBuf {
+        cache_dir.join("precedents").join("index.json")
+    }
+
+    /// Load the index, treating a missing file as empty
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Failed to parse precedent index: {:?}", path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read precedent index: {:?}", path)),
+        };
+        Ok(Self { path: path.to_path_buf(), entries })
+    }
+
+    /// Write the index back to disk, replacing it atomically
+    pub fn save(&self) -> Result<()> {
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create precedent directory: {:?}", dir))?;
+        let mut file = NamedTempFile::new_in(dir)?;
+        file.write_all(serde_json::to_string(&self.entries)?.as_bytes())?;
+        file.persist(&self.path)
+            .with_context(|| format!("Failed to write precedent index: {:?}", self.path))?;
+        Ok(())
+    }
+
+    /// Record the sentences of `code`, replacing what was recorded for the same sentence of the same spec
+    ///
+    /// Returns how many sentences were recorded.
+    pub fn record(&mut self, spec: &str, code: &str, language: &str) -> usize {
+        let precedents = split(spec, code, language);
+        let recorded = precedents.len();
+        for precedent in precedents {
+            self.entries.retain(|entry| {
+                entry.spec != precedent.spec || entry.sentence != precedent.sentence || entry.language != precedent.language
+            });
+            self.entries.push(precedent);
+        }
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+        recorded
+    }
+
+    /// Record the generated code of a saved compilation report
+    ///
+    /// Reports hold the code with secrets restored, so it is redacted again
+    /// first, and sentences whose code held a secret are left out.
+    pub fn record_report(&mut self, report: &CompilationReport) -> usize {
+        let redactor = Redactor::new(&[]).expect("built-in redaction patterns are valid");
+        let (code, _) = redactor.redact(&report.generated_code);
+        let spec = fs::canonicalize(&report.source_file).map_or(report.source_file.clone(), |path| path.display().to_string());
+        self.record(&spec, &code, &report.language)
+    }
+
+    /// Record a raw model response from the response cache, whose spec isn't known
+    pub fn record_response(&mut self, response: &str) -> usize {
+        let Some(language) = response_language(response) else {
+            return 0;
+        };
+        self.record("", response, language)
+    }
+
+    /// The past sentences closest to those of `source`, most similar first
+    ///
+    /// Precedents recorded from `spec` itself are skipped, so recompiling an
+    /// unchanged spec sends the same prompt and can reuse a cached response.
+    pub fn retrieve(&self, spec: &str, source: &str, language: &str) -> Vec<&Precedent> {
+        let sentences: Vec<BTreeMap<String, f64>> = offline::sentences(source).iter().map(|(_, sentence)| vector(sentence)).collect();
+        let mut scored: Vec<(f64, &Precedent)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.language == language && entry.spec != spec)
+            .filter_map(|entry| {
+                let past = vector(&entry.sentence);
+                let best = sentences.iter().map(|sentence| cosine(sentence, &past)).fold(0.0, f64::max);
+                (best >= MIN_SIMILARITY).then_some((best, entry))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.sentence.cmp(&b.1.sentence)).then_with(|| a.1.code.cmp(&b.1.code)));
+
+        let mut chosen: Vec<&Precedent> = Vec::new();
+        for (_, entry) in scored {
+            if chosen.len() == MAX_PRECEDENTS {
+                break;
+            }
+            if !chosen.iter().any(|precedent| precedent.sentence.eq_ignore_ascii_case(&entry.sentence)) {
+                chosen.push(entry);
+            }
+        }
+        chosen
+    }
+
+    /// Delete the index, returning how many entries it held
+    pub fn clear(self) -> Result<usize> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(self.entries.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove precedent index: {:?}", self.path)),
+        }
+    }
+}
+
+/// Instructions added to the translation prompt showing how similar sentences were translated before
+pub fn prompt_note(language: &str, precedents: &[&Precedent]) -> String {
+    let mut note = String::from(
+        "\nPRECEDENTS: earlier programs in this codebase translated similar sentences as shown below. \
+         Where a sentence here means the same thing, translate it the same way so the programs stay consistent. \
+         Don't add code for anything this program doesn't describe.\n",
+    );
+    for precedent in precedents {
+        let _ = write!(note, "\nSentence: {}\n```{}\n{}\n```\n", precedent.sentence, language, precedent.code);
+    }
+    note
+}
+
+/// The code written for each sentence of `code`, as its sentence comments attribute it
+fn split(spec: &str, code: &str, language: &str) -> Vec<Precedent> {
+    let source_map = SourceMap::new("", code);
+    let mut precedents: Vec<Precedent> = Vec::new();
+    for (index, line) in code.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with("```") {
+            continue;
+        }
+        let Some(sentence) = source_map.sentence_for_line(index + 1) else {
+            continue;
+        };
+        match precedents.iter_mut().find(|precedent| precedent.sentence == sentence) {
+            Some(precedent) => {
+                precedent.code.push('\n');
+                precedent.code.push_str(line);
+            }
+            None => precedents.push(Precedent {
+                spec: spec.to_string(),
+                sentence: sentence.to_string(),
+                language: language.to_string(),
+                code: line.to_string(),
+            }),
+        }
+    }
+    // A redaction placeholder means nothing outside the compilation it came from
+    precedents.retain(|precedent| precedent.code.lines().count() <= MAX_LINES && !precedent.code.contains(redact::PLACEHOLDER_PREFIX));
+    precedents
+}
+
+/// Language of a cached response, from its code fence or, failing that, its code
+fn response_language(response: &str) -> Option<&'static str> {
+    let fence = response.find("```").map(|start| response[start + 3..].lines().next().unwrap_or_default().trim());
+    match fence {
+        Some("c") => Some("c"),
+        Some("rust" | "rs") => Some("rust"),
+        _ if response.contains("#include") => Some("c"),
+        _ if response.contains("fn main") => Some("rust"),
+        _ => None,
+    }
+}
+
+/// Counts of the meaningful words of a sentence
+fn vector(sentence: &str) -> BTreeMap<String, f64> {
+    let mut counts = BTreeMap::new();
+    for word in sentence.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+        let word = word.to_lowercase();
+        if !STOP_WORDS.contains(&word.as_str()) {
+            *counts.entry(word).or_insert(0.0) += 1.0;
+        }
+    }
+    counts
+}
+
+fn cosine(a: &BTreeMap<String, f64>, b: &BTreeMap<String, f64>) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(word, count)| b.get(word).map(|other| count * other)).sum();
+    let norm = |vector: &BTreeMap<String, f64>| vector.values().map(|count| count * count).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
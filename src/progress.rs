@@ -0,0 +1,171 @@
+use serde_json::json;
+use std::fmt;
+
+/// Width of the CLI progress bar in characters
+const BAR_WIDTH: usize = 30;
+
+/// Stages of a compilation, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Translating,
+    Checking,
+    Building,
+    Running,
+}
+
+impl Stage {
+    /// Share of the overall compilation this stage covers, as a percent range
+    ///
+    /// Translation dominates wall time since it waits on the LLM, so it gets
+    /// the largest slice.
+    fn percent_range(self) -> (u8, u8) {
+        match self {
+            Stage::Translating => (0, 60),
+            Stage::Checking => (60, 70),
+            Stage::Building => (70, 95),
+            Stage::Running => (95, 100),
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Translating => "translating",
+            Stage::Checking => "checking",
+            Stage::Building => "building",
+            Stage::Running => "running",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A progress update within a stage
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub stage: Stage,
+    /// Sub-step being started, counting from 1
+    pub step: usize,
+    /// Number of sub-steps in the stage
+    pub total: usize,
+    pub message: String,
+}
+
+impl Progress {
+    pub fn new(stage: Stage, step: usize, total: usize, message: impl Into<String>) -> Self {
+        Self { stage, step, total, message: message.into() }
+    }
+    
+    /// Heuristic estimate of overall completion when this step starts
+    pub fn percent(&self) -> u8 {
+        let (start, end) = self.stage.percent_range();
+        let done = self.step.saturating_sub(1).min(self.total);
+        let span = (end - start) as usize;
+        start + (span * done / self.total.max(1)) as u8
+    }
+}
+
+/// A choice made about the program during a stage, such as a model fallback or
+/// a prompt section added for what the spec describes
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub stage: Stage,
+    pub message: String,
+}
+
+impl Decision {
+    pub fn new(stage: Stage, message: impl Into<String>) -> Self {
+        Self { stage, message: message.into() }
+    }
+}
+
+/// Receives progress updates and decisions as a compilation moves through its stages
+///
+/// Both are reported when they happen, so a long translation shows what
+/// it is doing instead of explaining itself afterwards.
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, progress: &Progress);
+    
+    fn on_decision(&self, _decision: &Decision) {}
+}
+
+/// Observer that ignores all updates
+pub struct NoProgress;
+
+impl ProgressObserver for NoProgress {
+    fn on_progress(&self, _progress: &Progress) {}
+}
+
+/// Observer that draws a progress bar line on stderr for each update
+pub struct ProgressBar;
+
+impl ProgressObserver for ProgressBar {
+    fn on_progress(&self, progress: &Progress) {
+        let percent = progress.percent();
+        let filled = BAR_WIDTH * percent as usize / 100;
+        eprintln!(
+            "[{}{}] {:>3}% {} ({}/{}): {}",
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            percent,
+            progress.stage,
+            progress.step,
+            progress.total,
+            progress.message
+        );
+    }
+    
+    fn on_decision(&self, decision: &Decision) {
+        eprintln!("{:>width$} {}", "->", decision.message, width = BAR_WIDTH + 2);
+    }
+}
+
+/// Observer that writes one stable, undecorated status line on stderr per update
+///
+/// For screen readers and log collectors, which gain nothing from a bar
+/// of `#` characters; the format is `nhlp: <stage> <step>/<total> (<percent>%): <message>`,
+/// and `nhlp: <stage> decision: <message>` for decisions.
+pub struct PlainProgress;
+
+impl ProgressObserver for PlainProgress {
+    fn on_progress(&self, progress: &Progress) {
+        eprintln!(
+            "nhlp: {} {}/{} ({}%): {}",
+            progress.stage,
+            progress.step,
+            progress.total,
+            progress.percent(),
+            progress.message
+        );
+    }
+    
+    fn on_decision(&self, decision: &Decision) {
+        eprintln!("nhlp: {} decision: {}", decision.stage, decision.message);
+    }
+}
+
+/// Observer that writes each update as a JSON object on its own stderr line
+pub struct JsonProgress;
+
+impl ProgressObserver for JsonProgress {
+    fn on_progress(&self, progress: &Progress) {
+        let line = json!({
+            "type": "progress",
+            "stage": progress.stage.to_string(),
+            "step": progress.step,
+            "total": progress.total,
+            "percent": progress.percent(),
+            "message": progress.message,
+        });
+        eprintln!("{}", line);
+    }
+    
+    fn on_decision(&self, decision: &Decision) {
+        let line = json!({
+            "type": "decision",
+            "stage": decision.stage.to_string(),
+            "message": decision.message,
+        });
+        eprintln!("{}", line);
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::linker::Linker;
+
+/// File name of the manifest at the root of a multi-file project
+pub const MANIFEST_NAME: &str = "nhlp.toml";
+
+/// The `nhlp.toml` manifest
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    project: ProjectSection,
+    #[serde(default)]
+    link: LinkSection,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ProjectSection {
+    /// Name of the executable
+    name: String,
+    /// .dshp files making up the program, relative to the manifest, in the order they are read
+    modules: Vec<PathBuf>,
+}
+
+/// How this project's executable is linked, for environments that need a
+/// particular linker or libraries
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct LinkSection {
+    linker: Option<Linker>,
+    /// Passed to the linker driver like `--link-arg`, before any given on the command line
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+/// A program described across several .dshp modules
+///
+/// ```toml
+/// [project]
+/// name = "inventory"
+/// modules = ["items.dshp", "report.dshp", "main.dshp"]
+///
+/// [link]
+/// linker = "mold"
+/// args = ["-lm"]
+/// ```
+///
+/// The modules are compiled together as one program, so a name defined in
+/// one module can be used by the others, and the result is a single executable.
+pub struct Project {
+    /// Path of the manifest, which stands in for the spec when locating the glossary and lockfile
+    pub manifest: PathBuf,
+    pub name: String,
+    /// Each module's path and text
+    pub modules: Vec<(PathBuf, String)>,
+    /// Linker named by the `[link]` section
+    pub linker: Option<Linker>,
+    /// Linker driver arguments from the `[link]` section
+    pub link_args: Vec<String>,
+}
+
+impl Project {
+    /// The manifest governing `path`: the file itself if it is a manifest, or
+    /// the manifest inside it if it is a directory
+    pub fn manifest_for(path: &Path) -> Option<PathBuf> {
+        if path.is_dir() {
+            let manifest = path.join(MANIFEST_NAME);
+            return manifest.is_file().then_some(manifest);
+        }
+        (path.file_name().is_some_and(|name| name == MANIFEST_NAME)).then(|| path.to_path_buf())
+    }
+    
+    /// Load a manifest and read every module it lists
+    pub fn load(manifest: &Path) -> Result<Self> {
+        let text = fs::read_to_string(manifest)
+            .with_context(|| format!("Failed to read project manifest: {:?}", manifest))?;
+        let parsed: Manifest = toml::from_str(&text)
+            .with_context(|| format!("Invalid project manifest: {:?}", manifest))?;
+        let ProjectSection { name, modules } = parsed.project;
+        let LinkSection { linker, args: link_args } = parsed.link;
+        if name.trim().is_empty() {
+            anyhow::bail!("{:?} must give the project a name", manifest);
+        }
+        if modules.is_empty() {
+            anyhow::bail!("{:?} lists no modules", manifest);
+        }
+        
+        let root = manifest.parent().unwrap_or_else(|| Path::new(""));
+        let mut loaded = Vec::new();
+        for module in modules {
+            let path = root.join(&module);
+            if loaded.iter().any(|(existing, _)| *existing == path) {
+                anyhow::bail!("{:?} lists module {:?} more than once", manifest, module);
+            }
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read module {:?} listed in {:?}", module, manifest))?;
+            loaded.push((path, text));
+        }
+        
+        Ok(Self { manifest: manifest.to_path_buf(), name, modules: loaded, linker, link_args })
+    }
+    
+    /// The modules merged into one program description, each under a heading
+    /// naming its file
+    ///
+    /// Headings are `#` lines, which the offline translator skips.
+    pub fn source(&self) -> String {
+        let mut source = format!(
+            "# This program is made of {} modules that together form one executable. \
+             Names defined in one module may be used by the others.\n",
+            self.modules.len()
+        );
+        for (path, text) in &self.modules {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let _ = write!(source, "\n# Module {}\n{}\n", name, text.trim());
+        }
+        source
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::report::CompilationReport;
+
+/// Build type identifying NHLP compilations in provenance documents
+const BUILD_TYPE: &str = "https://github.com/g-halcyon/naturalhlp/build/v1";
+
+/// Build an in-toto statement with SLSA provenance describing a compiled artifact
+pub fn statement(report: &CompilationReport) -> Result<serde_json::Value> {
+    let artifact = fs::read(&report.executable)
+        .with_context(|| format!("Failed to read artifact: {}", report.executable))?;
+    
+    let artifact_name = Path::new(&report.executable)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| report.executable.clone());
+    
+    Ok(json!({
+        "_type": "https://in-toto.io/Statement/v1",
+        "subject": [{
+            "name": artifact_name,
+            "digest": { "sha256": format!("{:x}", Sha256::digest(&artifact)) }
+        }],
+        "predicateType": "https://slsa.dev/provenance/v1",
+        "predicate": {
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": {
+                    "source": {
+                        "uri": report.source_file,
+                        "digest": { "sha256": report.source_hash }
+                    }
+                },
+                "internalParameters": {
+                    "provider": report.provider,
+                    "model": report.model,
+                    "promptsDigest": { "sha256": report.prompt_hash },
+                    "language": report.language,
+                    "toolchain": report.toolchain
+                },
+                "resolvedDependencies": []
+            },
+            "runDetails": {
+                "builder": {
+                    "id": "nhlp",
+                    "version": { "nhlp": env!("CARGO_PKG_VERSION") }
+                },
+                "metadata": {
+                    "environment": {
+                        "os": env::consts::OS,
+                        "arch": env::consts::ARCH
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Write the provenance document next to the artifact, returning its path
+pub fn write(report: &CompilationReport) -> Result<PathBuf> {
+    let path = PathBuf::from(format!("{}.intoto.json", report.executable));
+    let json = serde_json::to_string_pretty(&statement(report)?)?;
+    fs::write(&path, json)
+        .with_context(|| format!("Failed to write provenance: {:?}", path))?;
+    Ok(path)
+}
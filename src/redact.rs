@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use crate::error::NhlpError;
+
+/// Patterns for well-known credential formats and PII
+const BUILTIN_PATTERNS: &[&str] = &[
+    // Private key blocks
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    // Cloud and SaaS API keys
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bAIza[0-9A-Za-z_\-]{35}\b",
+    r"\bsk-[A-Za-z0-9_\-]{20,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{36}\b",
+    r"\bxox[abprs]-[A-Za-z0-9\-]{10,}",
+    // Email addresses
+    r"\b[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}\b",
+];
+
+/// Values assigned to words like "password" or "token" are secrets whatever their format
+///
+/// The `is`, `=`, or `:` is required, so a sentence that only mentions a
+/// password or a token keeps the words after it. A period ending the value
+/// ends the sentence rather than being part of the secret.
+const ASSIGNMENT_PATTERN: &str =
+    r#"(?i)\b(?:password|passwd|passphrase|secret|token|api[_ -]?key)\b(?:\s+is\s+|\s*[=:]\s*)["']?([^\s"',;]*[^\s"',;.])"#;
+
+/// Candidate tokens for entropy detection
+const TOKEN_PATTERN: &str = r"[A-Za-z0-9+/=_\-]{20,}";
+
+static BUILTIN: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    BUILTIN_PATTERNS
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("built-in redaction pattern is valid"))
+        .collect()
+});
+static ASSIGNMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(ASSIGNMENT_PATTERN).expect("assignment pattern is valid"));
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| Regex::new(TOKEN_PATTERN).expect("token pattern is valid"));
+
+/// Shannon entropy, in bits per character, above which a token looks random
+const ENTROPY_THRESHOLD: f64 = 4.0;
+
+/// Prefix of the placeholders substituted for secrets
+pub const PLACEHOLDER_PREFIX: &str = "NHLP_REDACTED_";
+
+/// Masks sensitive substrings before text is sent to a provider
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Create a redactor using the built-in patterns plus `extra_patterns`
+    pub fn new(extra_patterns: &[String]) -> Result<Self> {
+        let mut patterns = BUILTIN.clone();
+        
+        for pattern in extra_patterns {
+            patterns.push(
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid redaction pattern: {}", pattern))?,
+            );
+        }
+        
+        Ok(Self { patterns })
+    }
+    
+    /// Replace secrets in `text` with placeholders
+    pub fn redact(&self, text: &str) -> (String, Redaction) {
+        let mut redaction = Redaction::default();
+        let mut text = text.to_string();
+        
+        for pattern in &self.patterns {
+            text = pattern
+                .replace_all(&text, |caps: &regex::Captures| redaction.placeholder(&caps[0]))
+                .into_owned();
+        }
+        
+        // Only the value is secret, so keep the "password is" part readable
+        text = ASSIGNMENT
+            .replace_all(&text, |caps: &regex::Captures| {
+                let whole = caps.get(0).expect("match has a whole group");
+                let value = caps.get(1).expect("assignment pattern captures a value");
+                if value.as_str().starts_with(PLACEHOLDER_PREFIX) {
+                    return whole.as_str().to_string();
+                }
+                let prefix = &whole.as_str()[..value.start() - whole.start()];
+                format!("{}{}", prefix, redaction.placeholder(value.as_str()))
+            })
+            .into_owned();
+        
+        text = TOKEN
+            .replace_all(&text, |caps: &regex::Captures| {
+                let token = &caps[0];
+                if looks_random(token) {
+                    redaction.placeholder(token)
+                } else {
+                    token.to_string()
+                }
+            })
+            .into_owned();
+        
+        (text, redaction)
+    }
+}
+
+/// Secrets removed from a text, keyed by the placeholders that replaced them
+#[derive(Default)]
+pub struct Redaction {
+    secrets: Vec<String>,
+    placeholders: HashMap<String, String>,
+}
+
+impl Redaction {
+    /// Number of secrets that were masked
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+    
+    /// Whether nothing was masked
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+    
+    /// Put the original secrets back where generated code refers to their placeholders
+    ///
+    /// Placeholders sit in string literals, so each secret is escaped for a
+    /// literal in `language`: `c`, `rust`, or `json` for structured responses.
+    /// Fails on a secret the literal can't hold, such as a NUL in a C string.
+    pub fn restore(&self, code: &str, language: &str) -> Result<String> {
+        // Replace later placeholders first so NHLP_REDACTED_1 doesn't clobber NHLP_REDACTED_10
+        let mut code = code.to_string();
+        for (index, secret) in self.secrets.iter().enumerate().rev() {
+            let placeholder = placeholder_name(index + 1);
+            if code.contains(&placeholder) {
+                code = code.replace(&placeholder, &escape(secret, language).with_context(|| format!("Can't restore {}", placeholder))?);
+            }
+        }
+        Ok(code)
+    }
+    
+    /// Put the original secrets back into prose, such as an explanation, as they were written
+    pub fn restore_text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (index, secret) in self.secrets.iter().enumerate().rev() {
+            text = text.replace(&placeholder_name(index + 1), secret);
+        }
+        text
+    }
+    
+    /// Placeholder for a secret, reusing it when the same secret appears again
+    fn placeholder(&mut self, secret: &str) -> String {
+        if let Some(existing) = self.placeholders.get(secret) {
+            return existing.clone();
+        }
+        
+        self.secrets.push(secret.to_string());
+        let name = placeholder_name(self.secrets.len());
+        self.placeholders.insert(secret.to_string(), name.clone());
+        name
+    }
+}
+
+fn placeholder_name(index: usize) -> String {
+    format!("{}{}", PLACEHOLDER_PREFIX, index)
+}
+
+/// A secret as the inside of a string literal in `language`
+///
+/// C gets octal escapes for control characters and bytes outside ASCII, so
+/// the literal means the same bytes whatever the source charset; Rust gets
+/// `\u{..}` escapes for control characters.
+fn escape(secret: &str, language: &str) -> Result<String> {
+    if language == "json" {
+        let quoted = serde_json::to_string(secret)?;
+        return Ok(quoted[1..quoted.len() - 1].to_string());
+    }
+    if language != "c" && language != "rust" {
+        return Err(NhlpError::UnsupportedLanguage(language.to_string()).into());
+    }
+    let mut escaped = String::new();
+    for c in secret.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' if language == "c" => anyhow::bail!("The secret holds a NUL character, which would end a C string"),
+            _ if language == "c" && (c.is_control() || !c.is_ascii()) => {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    let _ = write!(escaped, "\\{:03o}", byte);
+                }
+            }
+            _ if c.is_control() => {
+                let _ = write!(escaped, "\\u{{{:x}}}", c as u32);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    Ok(escaped)
+}
+
+/// Whether a token looks like a generated secret rather than a word
+///
+/// Random keys mix letters and digits; long identifiers made of words rarely
+/// contain digits even when their entropy is high.
+fn looks_random(token: &str) -> bool {
+    !token.contains(PLACEHOLDER_PREFIX)
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().any(|c| c.is_ascii_alphabetic())
+        && shannon_entropy(token) > ENTROPY_THRESHOLD
+}
+
+/// Shannon entropy of a string in bits per character
+fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = HashMap::new();
+    for c in text.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    
+    let length = text.chars().count() as f64;
+    counts.values()
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str) -> (String, Redaction) {
+        Redactor::new(&[]).expect("built-in patterns are valid").redact(text)
+    }
+
+    #[test]
+    fn mentioning_a_password_masks_nothing() {
+        let (text, redaction) = redact("Ask the user for a password and check it.");
+        assert_eq!(text, "Ask the user for a password and check it.");
+        assert!(redaction.is_empty());
+    }
+
+    #[test]
+    fn mentioning_a_token_masks_nothing() {
+        let (text, redaction) = redact("Split the line into a token list.");
+        assert_eq!(text, "Split the line into a token list.");
+        assert!(redaction.is_empty());
+    }
+
+    #[test]
+    fn assigned_password_is_masked_and_restored() {
+        let (text, redaction) = redact("The password is hunter2.");
+        assert_eq!(text, "The password is NHLP_REDACTED_1.");
+        assert_eq!(redaction.len(), 1);
+        assert_eq!(redaction.restore("strcmp(input, \"NHLP_REDACTED_1\")", "c").unwrap(), "strcmp(input, \"hunter2\")");
+    }
+
+    #[test]
+    fn restored_secrets_are_escaped_for_the_literal() {
+        let mut redaction = Redaction::default();
+        redaction.placeholder("a\\b\"c\nd\u{1}é");
+        assert_eq!(redaction.restore("\"NHLP_REDACTED_1\"", "c").unwrap(), r#""a\\b\"c\nd\001\303\251""#);
+        assert_eq!(redaction.restore("\"NHLP_REDACTED_1\"", "rust").unwrap(), r#""a\\b\"c\nd\u{1}é""#);
+        assert_eq!(redaction.restore("\"NHLP_REDACTED_1\"", "json").unwrap(), r#""a\\b\"c\nd\u0001é""#);
+        assert_eq!(redaction.restore_text("NHLP_REDACTED_1"), "a\\b\"c\nd\u{1}é");
+    }
+
+    #[test]
+    fn secret_a_c_string_cant_hold_is_an_error() {
+        let mut redaction = Redaction::default();
+        redaction.placeholder("pass\0word");
+        assert!(redaction.restore("\"NHLP_REDACTED_1\"", "c").is_err());
+        assert_eq!(redaction.restore("\"NHLP_REDACTED_1\"", "rust").unwrap(), "\"pass\\u{0}word\"");
+        assert_eq!(redaction.restore("no placeholder", "c").unwrap(), "no placeholder");
+    }
+
+    #[test]
+    fn assigned_api_key_is_masked() {
+        let (text, _) = redact("Send requests with api_key=abc123 and token: xyz.");
+        assert_eq!(text, "Send requests with api_key=NHLP_REDACTED_1 and token: NHLP_REDACTED_2.");
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cancel::CancellationToken;
+use crate::compiler::{CompileOptions, Compiler};
+use crate::notebook::{self, Notebook};
+use crate::progress::NoProgress;
+use crate::runner;
+
+/// Time each run of the session's program may take
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+const HELP: &str = "Type a statement to add it to the program and run it. Commands:
+  :show          print the program so far, numbering its statements
+  :edit N TEXT   replace statement N with TEXT and rerun the program
+  :undo          remove the last statement
+  :input TEXT    add a line to the program's standard input
+  :reset         start over with an empty program and input
+  :save FILE     write the program to a .dshp file, or the session to a .dshpnb notebook
+  :load FILE     replay a .dshpnb notebook in place of the current session
+  :quit          leave (or press Ctrl-D)";
+
+/// One statement typed into the session and what it printed when added or last edited
+pub struct Entry {
+    pub statement: String,
+    /// Model that translated the program when the statement was added or edited
+    pub model: String,
+    pub output: String,
+}
+
+/// A program built up one statement at a time
+///
+/// Every statement is compiled together with the ones before it, so later
+/// statements can refer to variables and results introduced earlier. Only
+/// the output a statement adds is shown.
+#[derive(Default)]
+pub struct Session {
+    pub entries: Vec<Entry>,
+    /// Lines fed to the program's standard input on every run
+    pub input: Vec<String>,
+    /// Standard output of the last successful run
+    last_stdout: String,
+}
+
+impl Session {
+    /// The program formed by every statement so far
+    pub fn program(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| entry.statement.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    /// Add `statement`, then rebuild and run the program, returning the output
+    /// the statement added; the statement is dropped again if the program no
+    /// longer compiles
+    pub fn add(&mut self, compiler: &Compiler, dir: &Path, statement: &str) -> Result<String> {
+        self.entries.push(Entry { statement: statement.to_string(), model: String::new(), output: String::new() });
+        match self.run(compiler, dir) {
+            Ok((output, model)) => {
+                let entry = self.entries.last_mut().expect("statement was just added");
+                entry.output = output.clone();
+                entry.model = model;
+                Ok(output)
+            }
+            Err(e) => {
+                self.entries.pop();
+                Err(e)
+            }
+        }
+    }
+    
+    /// Replace the statement at `index` with `statement`, then rebuild and run
+    /// the whole program, returning everything it printed
+    ///
+    /// Later statements are kept, so values they compute from the replaced one
+    /// are recomputed. The old statement is put back if the program no longer
+    /// compiles.
+    pub fn edit(&mut self, compiler: &Compiler, dir: &Path, index: usize, statement: &str) -> Result<String> {
+        let entry = self.entries.get_mut(index).context("No statement with that number")?;
+        let previous = std::mem::replace(&mut entry.statement, statement.to_string());
+        let last_stdout = std::mem::take(&mut self.last_stdout);
+        match self.run(compiler, dir) {
+            Ok((output, model)) => {
+                self.entries[index].output = output.clone();
+                self.entries[index].model = model;
+                Ok(output)
+            }
+            Err(e) => {
+                self.entries[index].statement = previous;
+                self.last_stdout = last_stdout;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Drop the last statement, returning it
+    pub fn undo(&mut self, compiler: &Compiler, dir: &Path) -> Option<String> {
+        let entry = self.entries.pop()?;
+        self.last_stdout.clear();
+        if !self.entries.is_empty() {
+            // Rerun so the next statement's output is measured against the right baseline
+            let _ = self.run(compiler, dir);
+        }
+        Some(entry.statement)
+    }
+    
+    /// Compile and run the program, returning the output beyond the last run's
+    /// and the model that translated it
+    fn run(&mut self, compiler: &Compiler, dir: &Path) -> Result<(String, String)> {
+        let spec = dir.join("session.dshp");
+        fs::write(&spec, self.program())
+            .with_context(|| format!("Failed to write session program: {:?}", spec))?;
+        let report = compiler.execute(&spec, &CancellationToken::new(), &NoProgress)?;
+        
+        let stdin: String = self.input.iter().map(|line| format!("{}\n", line)).collect();
+        let output = runner::run_captured(&report.executable, &stdin, Some(RUN_TIMEOUT))?;
+        
+        // Earlier statements print the same things again; show only what's new
+        let new_stdout = match output.stdout.strip_prefix(self.last_stdout.as_str()) {
+            Some(rest) => rest.to_string(),
+            None => output.stdout.clone(),
+        };
+        let mut shown = new_stdout;
+        if !output.stderr.is_empty() {
+            shown.push_str(&output.stderr);
+        }
+        if !shown.is_empty() && !shown.ends_with('\n') {
+            shown.push('\n');
+        }
+        if !output.success() {
+            shown.push_str(&format!("(program {})\n", output.outcome()));
+        }
+        self.last_stdout = output.stdout;
+        Ok((shown, report.model))
+    }
+}
+
+/// Replay a notebook into a new session, showing each statement and its output
+///
+/// Output that differs from what the notebook recorded is pointed out, since
+/// a different model or toolchain may build a statement differently. A
+/// statement that no longer compiles ends the replay there.
+fn replay(compiler: &Compiler, dir: &Path, path: &Path) -> Result<Session> {
+    let notebook = Notebook::load(path)?;
+    let mut session = Session { input: notebook.input, ..Session::default() };
+    for cell in &notebook.cells {
+        println!("nhlp> {}", cell.statement);
+        let output = match session.add(compiler, dir, &cell.statement) {
+            Ok(output) => output,
+            Err(e) => {
+                println!("error: {:#}\n(replay stopped after {} of {} statement(s))", e, session.entries.len(), notebook.cells.len());
+                return Ok(session);
+            }
+        };
+        print!("{}", output);
+        let model = &session.entries.last().expect("statement was just added").model;
+        if output != cell.output {
+            print!("(recorded output was different:)\n{}", cell.output);
+            if !cell.model.is_empty() && *model != cell.model {
+                println!("(recorded with {}, replayed with {})", cell.model, model);
+            }
+        }
+    }
+    println!("Replayed {} statement(s) from {:?}", session.entries.len(), path);
+    Ok(session)
+}
+
+/// Read statements from the terminal until :quit or end of input, first
+/// replaying `notebook` if one is given
+pub fn run(options: CompileOptions, notebook: Option<&Path>) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let compiler = Compiler::new(CompileOptions {
+        out_dir: Some(dir.path().to_path_buf()),
+        run: false,
+        ..options
+    })?;
+    
+    println!("nhlp REPL. Type :help for commands.");
+    let mut session = match notebook {
+        Some(path) => replay(&compiler, dir.path(), path)?,
+        None => Session::default(),
+    };
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("nhlp> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(());
+        };
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match command {
+            ":quit" | ":q" | ":exit" => return Ok(()),
+            ":help" | ":h" => println!("{}", HELP),
+            ":show" => {
+                for (number, entry) in session.entries.iter().enumerate() {
+                    println!("{:>3}  {}", number + 1, entry.statement);
+                }
+            }
+            ":edit" => {
+                let (number, statement) = argument.split_once(' ').unwrap_or((argument, ""));
+                match number.parse::<usize>() {
+                    Ok(number) if number >= 1 && !statement.trim().is_empty() => {
+                        match session.edit(&compiler, dir.path(), number - 1, statement.trim()) {
+                            Ok(output) => print!("{}", output),
+                            Err(e) => println!("error: {:#}\n(statement not changed)", e),
+                        }
+                    }
+                    _ => println!("Usage: :edit N TEXT, where N is a statement number from :show"),
+                }
+            }
+            ":undo" => match session.undo(&compiler, dir.path()) {
+                Some(statement) => println!("Removed: {}", statement),
+                None => println!("Nothing to undo"),
+            },
+            ":input" => session.input.push(argument.to_string()),
+            ":reset" => session = Session::default(),
+            ":save" if !argument.is_empty() => {
+                let path = PathBuf::from(argument);
+                let saved = if notebook::is_notebook(&path) {
+                    Notebook::from_session(&session).save(&path)
+                } else {
+                    fs::write(&path, session.program() + "\n")
+                        .with_context(|| format!("Failed to save program: {:?}", path))
+                };
+                match saved {
+                    Ok(()) => println!("Saved {} statement(s) to {:?}", session.entries.len(), path),
+                    Err(e) => println!("error: {:#}", e),
+                }
+            }
+            ":load" if !argument.is_empty() => match replay(&compiler, dir.path(), Path::new(argument)) {
+                Ok(replayed) => session = replayed,
+                Err(e) => println!("error: {:#}", e),
+            },
+            _ if command.starts_with(':') => println!("Unknown command {}; type :help", command),
+            _ => match session.add(&compiler, dir.path(), line) {
+                Ok(output) => print!("{}", output),
+                Err(e) => println!("error: {:#}\n(statement not added)", e),
+            },
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::acceptance::AcceptanceResult;
+use crate::capabilities::{self, Capability};
+use crate::cost::SentenceCost;
+use crate::counterexamples::CounterexampleResult;
+use crate::coverage::SentenceCoverage;
+use crate::equivalence::EquivalenceReport;
+use crate::hardening::HardeningSummary;
+use crate::llm::EgressRecord;
+use crate::report_artifact;
+use crate::runner::ProgramOutput;
+use crate::schema::{self, Migration};
+
+/// Current version of the report format
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades from each earlier report version, oldest first
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [from_unversioned];
+
+/// How a compilation ended
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// The program was built, and run unless the build was the last step
+    #[default]
+    Completed,
+    /// The compilation was cancelled, by Ctrl-C or whoever held its token
+    Cancelled,
+}
+
+/// A record of how a .dshp program was compiled
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CompilationReport {
+    /// Version of the report format, see [`SCHEMA_VERSION`]
+    #[serde(default)]
+    pub schema_version: u32,
+    /// How the compilation ended; a cancelled one records only the spec
+    #[serde(default)]
+    pub outcome: Outcome,
+    /// Path of the .dshp source file
+    pub source_file: String,
+    /// SHA-256 of the .dshp source
+    pub source_hash: String,
+    /// The .dshp source as compiled
+    #[serde(default)]
+    pub source: String,
+    /// LLM provider that translated the program
+    #[serde(default)]
+    pub provider: String,
+    /// Model that translated the program
+    #[serde(default)]
+    pub model: String,
+    /// Position of that model in the fallback ladder, 0 being the primary
+    #[serde(default)]
+    pub tier: usize,
+    /// SHA-256 of the prompt sent to the model
+    #[serde(default)]
+    pub prompt_hash: String,
+    /// Language handed to the native toolchain ("c" or "rust")
+    pub language: String,
+    /// Toolchain that produced the executable
+    pub toolchain: String,
+    /// Path of the produced executable
+    pub executable: String,
+    /// Code produced by the Neural Compiler Engine
+    pub generated_code: String,
+    /// External interactions found in the generated code
+    #[serde(default)]
+    pub capabilities: BTreeSet<Capability>,
+    /// Every external request made while compiling
+    #[serde(default)]
+    pub egress: Vec<EgressRecord>,
+    /// Lines, I/O calls, allocations, and loops the generated code spends on each sentence
+    #[serde(default)]
+    pub costs: Vec<SentenceCost>,
+    /// Per-sentence coverage from an instrumented run
+    #[serde(default)]
+    pub coverage: Vec<SentenceCoverage>,
+    /// Results of running model-proposed edge-case inputs
+    #[serde(default)]
+    pub counterexamples: Vec<CounterexampleResult>,
+    /// Comparison of -O0 and -O2 builds, when requested
+    #[serde(default)]
+    pub equivalence: Option<EquivalenceReport>,
+    /// Results of the acceptance criteria written in the spec
+    #[serde(default)]
+    pub acceptance: Vec<AcceptanceResult>,
+    /// Output of the program's run, when it was captured
+    #[serde(default)]
+    pub output: Option<ProgramOutput>,
+    /// Hardening read back from the executable built with --harden
+    #[serde(default)]
+    pub hardening: Option<HardeningSummary>,
+    /// Choices made about the program while translating it, in order
+    #[serde(default)]
+    pub decisions: Vec<String>,
+    /// Code generation flags the toolchain was given: features, hardening, optimization, and debug info
+    #[serde(default)]
+    pub build_flags: Vec<String>,
+    /// Link flags the toolchain was given: linker, static linking, LTO, library output, and link arguments
+    #[serde(default)]
+    pub link_flags: Vec<String>,
+}
+
+impl CompilationReport {
+    /// The report of a compilation of `source` that was cancelled before it finished
+    pub fn cancelled(source_file: &str, source: &str) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            outcome: Outcome::Cancelled,
+            source_file: source_file.to_string(),
+            source_hash: hash_source(source),
+            source: source.to_string(),
+            ..Self::default()
+        }
+    }
+    
+    /// Load a report from a JSON file, upgrading reports written by older versions
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read report: {:?}", path.as_ref()))?;
+        let document = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse report: {:?}", path.as_ref()))?;
+        let document = schema::upgrade("Report", document, SCHEMA_VERSION, &MIGRATIONS)
+            .with_context(|| format!("Failed to load report: {:?}", path.as_ref()))?;
+        serde_json::from_value(document)
+            .with_context(|| format!("Failed to parse report: {:?}", path.as_ref()))
+    }
+    
+    /// Write the report as pretty-printed JSON, or as a page for review when
+    /// the path ends in `.md` or `.html`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let contents = match extension.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => report_artifact::markdown(self),
+            #[cfg(feature = "graph")]
+            "html" | "htm" => report_artifact::html(self),
+            #[cfg(not(feature = "graph"))]
+            "html" | "htm" => anyhow::bail!("This nhlp was built without the `graph` feature, so it can't write HTML reports; use .md or .json"),
+            _ => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write report: {:?}", path.as_ref()))
+    }
+    
+    /// Headers and crates the generated code pulls in
+    pub fn imports(&self) -> BTreeSet<String> {
+        self.generated_code
+            .lines()
+            .map(str::trim)
+            .filter(|line| {
+                line.starts_with("#include") || line.starts_with("use ") || line.starts_with("extern crate")
+            })
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Reports from before versioning were all translated by Gemini, but may not say so
+fn from_unversioned(document: &mut Value) {
+    if let Some(fields) = document.as_object_mut() {
+        fields.entry("provider").or_insert_with(|| Value::from("gemini"));
+    }
+}
+
+/// Hash source text the same way reports do
+pub fn hash_source(source: &str) -> String {
+    format!("{:x}", Sha256::digest(source.as_bytes()))
+}
+
+/// Differences between two compilation reports
+pub struct ReportDiff {
+    pub source_changed: bool,
+    pub language: Option<(String, String)>,
+    pub toolchain: Option<(String, String)>,
+    pub model: Option<(String, String)>,
+    pub added_imports: Vec<String>,
+    pub removed_imports: Vec<String>,
+    pub added_capabilities: BTreeSet<Capability>,
+    pub removed_capabilities: BTreeSet<Capability>,
+    /// Decisions made only for the new compilation, then only for the old one
+    pub added_decisions: Vec<String>,
+    pub removed_decisions: Vec<String>,
+    pub added_build_flags: Vec<String>,
+    pub removed_build_flags: Vec<String>,
+    pub added_link_flags: Vec<String>,
+    pub removed_link_flags: Vec<String>,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+}
+
+impl ReportDiff {
+    /// Compare an old report against a new one
+    pub fn new(old: &CompilationReport, new: &CompilationReport) -> Self {
+        let changed = |a: &String, b: &String| (a != b).then(|| (a.clone(), b.clone()));
+        
+        let old_imports = old.imports();
+        let new_imports = new.imports();
+        
+        // Compare lines in order, ignoring blank lines and indentation, so moved and repeated lines count too
+        let old_lines: Vec<&str> = old.generated_code.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let new_lines: Vec<&str> = new.generated_code.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let common = common_subsequence_length(&old_lines, &new_lines);
+        
+        Self {
+            source_changed: old.source_hash != new.source_hash,
+            language: changed(&old.language, &new.language),
+            toolchain: changed(&old.toolchain, &new.toolchain),
+            model: changed(&old.model, &new.model),
+            added_imports: new_imports.difference(&old_imports).cloned().collect(),
+            removed_imports: old_imports.difference(&new_imports).cloned().collect(),
+            added_capabilities: new.capabilities.difference(&old.capabilities).copied().collect(),
+            removed_capabilities: old.capabilities.difference(&new.capabilities).copied().collect(),
+            added_decisions: only_in(&new.decisions, &old.decisions),
+            removed_decisions: only_in(&old.decisions, &new.decisions),
+            added_build_flags: only_in(&new.build_flags, &old.build_flags),
+            removed_build_flags: only_in(&old.build_flags, &new.build_flags),
+            added_link_flags: only_in(&new.link_flags, &old.link_flags),
+            removed_link_flags: only_in(&old.link_flags, &new.link_flags),
+            added_lines: new_lines.len() - common,
+            removed_lines: old_lines.len() - common,
+        }
+    }
+    
+    /// Whether the generated code is the same, line for line
+    pub fn same_code(&self) -> bool {
+        self.added_lines == 0 && self.removed_lines == 0
+    }
+    
+    /// Whether the two compilations agree on everything compared
+    pub fn is_empty(&self) -> bool {
+        self.language.is_none()
+            && self.toolchain.is_none()
+            && self.model.is_none()
+            && self.added_capabilities.is_empty()
+            && self.removed_capabilities.is_empty()
+            && self.added_decisions.is_empty()
+            && self.removed_decisions.is_empty()
+            && self.added_build_flags.is_empty()
+            && self.removed_build_flags.is_empty()
+            && self.added_link_flags.is_empty()
+            && self.removed_link_flags.is_empty()
+            && self.same_code()
+    }
+}
+
+/// Entries of `items` that `others` doesn't have, counting repeats, in order
+fn only_in(items: &[String], others: &[String]) -> Vec<String> {
+    let mut unmatched: Vec<&String> = others.iter().collect();
+    items
+        .iter()
+        .filter(|item| match unmatched.iter().position(|other| other == item) {
+            Some(index) => {
+                unmatched.swap_remove(index);
+                false
+            }
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Length of the longest sequence of lines both sides have in the same order
+fn common_subsequence_length(old: &[&str], new: &[&str]) -> usize {
+    let mut previous = vec![0; new.len() + 1];
+    let mut current = vec![0; new.len() + 1];
+    for old_line in old {
+        for (index, new_line) in new.iter().enumerate() {
+            current[index + 1] = if old_line == new_line {
+                previous[index] + 1
+            } else {
+                current[index].max(previous[index + 1])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[new.len()]
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Source: {}", if self.source_changed { "changed" } else { "unchanged" })?;
+        
+        if let Some((old, new)) = &self.language {
+            writeln!(f, "Language: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = &self.toolchain {
+            writeln!(f, "Toolchain: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = &self.model {
+            writeln!(f, "Model: {} -> {}", old, new)?;
+        }
+        
+        for import in &self.added_imports {
+            writeln!(f, "+ {}", import)?;
+        }
+        for import in &self.removed_imports {
+            writeln!(f, "- {}", import)?;
+        }
+        
+        if !self.added_capabilities.is_empty() {
+            writeln!(f, "New external interactions: {}", capabilities::describe(&self.added_capabilities))?;
+        }
+        if !self.removed_capabilities.is_empty() {
+            writeln!(f, "Dropped external interactions: {}", capabilities::describe(&self.removed_capabilities))?;
+        }
+        
+        for decision in &self.added_decisions {
+            writeln!(f, "+ decision: {}", decision)?;
+        }
+        for decision in &self.removed_decisions {
+            writeln!(f, "- decision: {}", decision)?;
+        }
+        for flag in &self.added_build_flags {
+            writeln!(f, "+ build flag: {}", flag)?;
+        }
+        for flag in &self.removed_build_flags {
+            writeln!(f, "- build flag: {}", flag)?;
+        }
+        for flag in &self.added_link_flags {
+            writeln!(f, "+ link flag: {}", flag)?;
+        }
+        for flag in &self.removed_link_flags {
+            writeln!(f, "- link flag: {}", flag)?;
+        }
+        
+        if self.same_code() {
+            write!(f, "Generated code: identical")?;
+        } else {
+            write!(
+                f,
+                "Generated code: {} line(s) added, {} line(s) removed",
+                self.added_lines, self.removed_lines
+            )?;
+        }
+        if self.is_empty() {
+            write!(f, "\nNo other differences")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_report_records_its_outcome() {
+        let dir = tempfile::tempdir().expect("temporary directory");
+        let path = dir.path().join("report.json");
+        CompilationReport::cancelled("spec.dshp", "Print \"hi\".\n").save(&path).expect("report saved");
+        let report = CompilationReport::load(&path).expect("report loads");
+        assert_eq!(report.outcome, Outcome::Cancelled);
+        assert_eq!(report.source_hash, hash_source("Print \"hi\".\n"));
+        assert!(report.generated_code.is_empty());
+    }
+
+    #[test]
+    fn reports_without_an_outcome_completed() {
+        let report: CompilationReport = serde_json::from_str(
+            r#"{"source_file": "a.dshp", "source_hash": "", "language": "c", "toolchain": "gcc", "executable": "a", "generated_code": ""}"#,
+        )
+        .expect("report parses");
+        assert_eq!(report.outcome, Outcome::Completed);
+    }
+
+    fn report(code: &str) -> CompilationReport {
+        CompilationReport { model: "m".to_string(), generated_code: code.to_string(), ..CompilationReport::default() }
+    }
+
+    #[test]
+    fn identical_reports_have_an_empty_diff() {
+        let diff = ReportDiff::new(&report("int x;\n"), &report("int x;\n"));
+        assert!(diff.is_empty());
+        assert!(diff.to_string().ends_with("Generated code: identical\nNo other differences"));
+    }
+
+    #[test]
+    fn changed_build_and_link_flags_are_reported() {
+        let old = CompilationReport { build_flags: vec!["-O2".to_string()], link_flags: vec!["-fuse-ld=bfd".to_string()], ..report("") };
+        let new = CompilationReport {
+            build_flags: vec!["-O3".to_string(), "-fstack-protector-strong".to_string()],
+            link_flags: vec!["-fuse-ld=lld".to_string(), "-s".to_string()],
+            ..report("")
+        };
+        let diff = ReportDiff::new(&old, &new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added_build_flags, ["-O3", "-fstack-protector-strong"]);
+        assert_eq!(diff.removed_build_flags, ["-O2"]);
+        assert_eq!(diff.added_link_flags, ["-fuse-ld=lld", "-s"]);
+        assert_eq!(diff.removed_link_flags, ["-fuse-ld=bfd"]);
+        assert!(diff.to_string().contains("+ build flag: -O3"));
+    }
+
+    #[test]
+    fn changed_decisions_are_reported() {
+        let old = CompilationReport { decisions: vec!["sort scores with insertion sort".to_string()], ..report("") };
+        let new = CompilationReport { decisions: vec!["sort scores with merge sort".to_string()], ..report("") };
+        let diff = ReportDiff::new(&old, &new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added_decisions, ["sort scores with merge sort"]);
+        assert_eq!(diff.removed_decisions, ["sort scores with insertion sort"]);
+    }
+
+    #[test]
+    fn model_and_capability_changes_make_the_diff_non_empty() {
+        let new = CompilationReport { model: "other".to_string(), ..report("") };
+        assert!(!ReportDiff::new(&report(""), &new).is_empty());
+        let new = CompilationReport { capabilities: BTreeSet::from([Capability::Network]), ..report("") };
+        let diff = ReportDiff::new(&report(""), &new);
+        assert!(!diff.is_empty());
+        assert!(diff.same_code());
+    }
+
+    #[test]
+    fn repeated_and_reordered_lines_are_counted() {
+        let diff = ReportDiff::new(&report("a();\nb();\n"), &report("a();\nb();\nb();\n"));
+        assert_eq!((diff.added_lines, diff.removed_lines), (1, 0));
+        let diff = ReportDiff::new(&report("a();\nb();\n"), &report("b();\na();\n"));
+        assert_eq!((diff.added_lines, diff.removed_lines), (1, 1));
+        assert!(!diff.is_empty());
+        let diff = ReportDiff::new(&report("a();\n  b();\n\n"), &report("a();\nb();\n"));
+        assert!(diff.same_code());
+    }
+}
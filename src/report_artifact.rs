@@ -0,0 +1,445 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::capabilities;
+use crate::check;
+use crate::doctor::Status;
+#[cfg(feature = "graph")]
+use crate::graph::{self, ControlFlow};
+use crate::offline::{self, Kind, Statement};
+use crate::project::Project;
+use crate::report::{self, CompilationReport, Outcome};
+use crate::sorting;
+use crate::sourcemap::SourceMap;
+
+/// Part of a section of the page
+enum Block {
+    Text(String),
+    List(Vec<String>),
+    Table(&'static [&'static str], Vec<Vec<String>>),
+    /// Code with the language to highlight it as
+    Code(&'static str, String),
+}
+
+struct Section {
+    title: &'static str,
+    blocks: Vec<Block>,
+}
+
+/// The report as a Markdown page explaining how the program was derived from its spec
+pub fn markdown(report: &CompilationReport) -> String {
+    let mut page = format!("# Compilation report: {}\n", report.source_file);
+    for section in sections(report) {
+        let _ = write!(page, "\n## {}\n", section.title);
+        for block in section.blocks {
+            page.push('\n');
+            match block {
+                Block::Text(text) => page.push_str(&format!("{}\n", text)),
+                Block::List(items) => items.iter().for_each(|item| page.push_str(&format!("- {}\n", item))),
+                Block::Table(headers, rows) => {
+                    let _ = writeln!(page, "| {} |", headers.join(" | "));
+                    let _ = writeln!(page, "|{}", "---|".repeat(headers.len()));
+                    for row in rows {
+                        let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|").replace('\n', " ")).collect();
+                        let _ = writeln!(page, "| {} |", cells.join(" | "));
+                    }
+                }
+                Block::Code(language, code) => {
+                    let fence = fence(&code);
+                    let _ = write!(page, "{}{}\n{}\n{}\n", fence, language, code.trim_end(), fence);
+                }
+            }
+        }
+    }
+    page
+}
+
+/// The report as a self-contained HTML page explaining how the program was derived from its spec
+#[cfg(feature = "graph")]
+pub fn html(report: &CompilationReport) -> String {
+    let title = format!("Compilation report: {}", escape(&report.source_file));
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: sans-serif; max-width: 60em; margin: 2em auto; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }}\n\
+         pre {{ background: #f4f4f4; padding: 1em; overflow-x: auto; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, title
+    );
+    for section in sections(report) {
+        let _ = writeln!(page, "<h2>{}</h2>", section.title);
+        for block in section.blocks {
+            match block {
+                Block::Text(text) => {
+                    let _ = writeln!(page, "<p>{}</p>", escape(&text));
+                }
+                Block::List(items) => {
+                    page.push_str("<ul>\n");
+                    items.iter().for_each(|item| page.push_str(&format!("<li>{}</li>\n", escape(item))));
+                    page.push_str("</ul>\n");
+                }
+                Block::Table(headers, rows) => {
+                    page.push_str("<table>\n<tr>");
+                    headers.iter().for_each(|header| page.push_str(&format!("<th>{}</th>", header)));
+                    page.push_str("</tr>\n");
+                    for row in rows {
+                        page.push_str("<tr>");
+                        row.iter().for_each(|cell| page.push_str(&format!("<td>{}</td>", escape(cell))));
+                        page.push_str("</tr>\n");
+                    }
+                    page.push_str("</table>\n");
+                }
+                Block::Code(language, code) => {
+                    let _ = writeln!(page, "<pre><code class=\"language-{}\">{}</code></pre>", language, escape(code.trim_end()));
+                }
+            }
+        }
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+fn sections(report: &CompilationReport) -> Vec<Section> {
+    let mut sections = vec![Section { title: "Summary", blocks: vec![summary(report)] }];
+    if !report.source.is_empty() {
+        sections.push(Section { title: "Spec", blocks: vec![Block::Code("text", report.source.clone())] });
+        sections.push(Section { title: "What the spec asks for", blocks: vec![intent(report)] });
+    }
+    sections.push(Section {
+        title: "Decisions",
+        blocks: vec![match report.decisions.is_empty() {
+            true => Block::Text("The spec needed no handling beyond a plain translation.".to_string()),
+            false => Block::List(report.decisions.clone()),
+        }],
+    });
+    if !report.source.is_empty() {
+        sections.push(Section { title: "Resolved ambiguities", blocks: vec![ambiguities(report)] });
+        sections.push(Section { title: "Variables and types", blocks: vec![types(&report.source)] });
+        sections.push(Section { title: "Control flow", blocks: control_flow(&report.source) });
+        sections.push(Section { title: "Sentences and code", blocks: vec![sentences(report)] });
+    }
+    sections.push(Section { title: "Build", blocks: build(report) });
+    sections.push(Section { title: "Optimizations", blocks: vec![optimizations(report)] });
+    if !report.acceptance.is_empty() {
+        let rows = report
+            .acceptance
+            .iter()
+            .map(|result| vec![result.sentence.clone(), if result.passed { "passed" } else { "failed" }.to_string(), result.outcome.clone()])
+            .collect();
+        sections.push(Section { title: "Acceptance criteria", blocks: vec![Block::Table(&["Criterion", "Result", "Outcome"], rows)] });
+    }
+    let language = if report.language == "rust" { "rust" } else { "c" };
+    sections.push(Section { title: "Generated code", blocks: vec![Block::Code(language, report.generated_code.clone())] });
+    sections
+}
+
+fn summary(report: &CompilationReport) -> Block {
+    let translated_by = match report.tier {
+        0 => format!("{} ({})", report.model, report.provider),
+        tier => format!("{} ({}), fallback tier {}", report.model, report.provider, tier + 1),
+    };
+    let outcome = match report.outcome {
+        Outcome::Completed => "completed",
+        Outcome::Cancelled => "cancelled before the program was built",
+    };
+    let rows = [
+        ("Outcome", outcome.to_string()),
+        ("Spec", report.source_file.clone()),
+        ("Spec SHA-256", report.source_hash.clone()),
+        ("Translated by", translated_by),
+        ("Language", report.language.clone()),
+        ("Toolchain", report.toolchain.clone()),
+        ("Output", report.executable.clone()),
+    ];
+    Block::Table(&["Field", "Value"], rows.into_iter().map(|(name, value)| vec![name.to_string(), value]).collect())
+}
+
+/// What `nhlp check` finds in the spec, when the spec on disk is still the one compiled
+fn intent(report: &CompilationReport) -> Block {
+    let path = Path::new(&report.source_file);
+    let current = match Project::manifest_for(path) {
+        Some(manifest) => Project::load(&manifest).ok().map(|project| project.source()),
+        None => fs::read_to_string(path).ok(),
+    };
+    let unchanged = current.is_some_and(|source| report::hash_source(&source) == report.source_hash);
+    if !unchanged {
+        return Block::Text(format!("{} has changed since it was compiled; run `nhlp check` on it instead.", report.source_file));
+    }
+    let rows = check::run(path, &[])
+        .into_iter()
+        .map(|check| {
+            let status = match check.status {
+                Status::Ok => "ok",
+                Status::Warn => "warning",
+                Status::Fail => "failure",
+            };
+            let detail = match check.fix {
+                Some(fix) => format!("{} (fix: {})", check.detail, fix),
+                None => check.detail,
+            };
+            vec![check.name.to_string(), status.to_string(), detail]
+        })
+        .collect();
+    Block::Table(&["Check", "Status", "Finding"], rows)
+}
+
+/// Each variable's type, for specs in the offline vocabulary, where it is inferred from the sentences
+fn types(source: &str) -> Block {
+    let Ok(parsed) = offline::parse(source) else {
+        return Block::Text("The spec uses sentences outside the offline vocabulary, so the model chose the types.".to_string());
+    };
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for sentence in &parsed {
+        let (name, kind) = match &sentence.statement {
+            Statement::Ask { name, kind, declared: false } => (name, *kind),
+            Statement::Set { name, declared: false, .. } => (name, Kind::Number),
+            Statement::Join { name, declared: false, .. } => (name, Kind::Text),
+            _ => continue,
+        };
+        let kind = match kind {
+            Kind::Number => "number",
+            Kind::Text => "text",
+            Kind::List => "list of numbers",
+        };
+        rows.push(vec![name.clone(), kind.to_string(), format!("line {}: {}", sentence.line, sentence.text)]);
+    }
+    if rows.is_empty() {
+        return Block::Text("The program has no variables.".to_string());
+    }
+    Block::Table(&["Variable", "Type", "Inferred from"], rows)
+}
+
+/// Wording that left a decision open, how it was settled, and why
+///
+/// Sorts are settled by nhlp's own rules, which give their reason. Vague
+/// wording is settled by the model, whose answer is the code written for
+/// the sentence.
+fn ambiguities(report: &CompilationReport) -> Block {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for sort in sorting::extract(&report.source).sorts {
+        let mut resolution = sort.algorithm.to_string();
+        if let Some(key) = &sort.key {
+            resolution.push_str(&format!(" by {}", key));
+        }
+        resolution.push_str(if sort.descending { ", descending" } else { ", ascending" });
+        rows.push(vec![sort.phrase.clone(), resolution, sort.reason()]);
+    }
+    let source_map = SourceMap::new(&report.source, &report.generated_code);
+    let ranges = ranges(report);
+    for (span, phrase) in check::vague_phrases(&report.source) {
+        let written = ranges
+            .iter()
+            .filter(|(sentence, _, _)| source_map.locate(sentence).is_some_and(|located| located.line == span.line))
+            .map(|(_, start, end)| format!("{}-{}", start, end))
+            .collect::<Vec<_>>();
+        let resolution = match written.is_empty() {
+            true => "left to the model; the code doesn't mark which part implements it".to_string(),
+            false => format!("generated lines {}", written.join(", ")),
+        };
+        rows.push(vec![
+            format!("\"{}\" (line {})", phrase, span.line),
+            resolution,
+            "the spec doesn't say, so the model chose; check that the code does what was meant".to_string(),
+        ]);
+    }
+    if rows.is_empty() {
+        return Block::Text("The spec's wording left no decisions open.".to_string());
+    }
+    Block::Table(&["Wording", "Resolved as", "Reasoning"], rows)
+}
+
+/// The runs of generated lines written for each sentence, in order
+fn ranges(report: &CompilationReport) -> Vec<(String, usize, usize)> {
+    let source_map = SourceMap::new(&report.source, &report.generated_code);
+    let mut ranges: Vec<(String, usize, usize)> = Vec::new();
+    for line in 1..=report.generated_code.lines().count() {
+        let Some(sentence) = source_map.sentence_for_line(line) else {
+            continue;
+        };
+        match ranges.last_mut() {
+            Some((last, _, end)) if last == sentence && *end + 1 == line => *end = line,
+            _ => ranges.push((sentence.to_string(), line, line)),
+        }
+    }
+    ranges
+}
+
+/// The spec's control-flow graph as Graphviz DOT, for specs in the offline vocabulary
+#[cfg(feature = "graph")]
+fn control_flow(source: &str) -> Vec<Block> {
+    let Ok(graph) = ControlFlow::new(source) else {
+        return vec![Block::Text("The spec uses sentences outside the offline vocabulary, so its control flow isn't known before translation.".to_string())];
+    };
+    vec![
+        Block::Text(format!(
+            "{} block(s) and {} edge(s); render with `dot -Tsvg`. The dominator tree is alongside, with dashed edges to each block's dominance frontier.",
+            graph.blocks.len(), graph.edges.len()
+        )),
+        Block::Code("dot", graph::dot(&graph, true, true)),
+    ]
+}
+
+#[cfg(not(feature = "graph"))]
+fn control_flow(_source: &str) -> Vec<Block> {
+    vec![Block::Text("This nhlp was built without the `graph` feature, so the report has no control-flow graph.".to_string())]
+}
+
+/// The lines of generated code written for each sentence, and what they cost
+fn sentences(report: &CompilationReport) -> Block {
+    let source_map = SourceMap::new(&report.source, &report.generated_code);
+    let ranges = ranges(report);
+    if ranges.is_empty() {
+        return Block::Text("The generated code doesn't mark which sentence each part implements.".to_string());
+    }
+    let rows = ranges
+        .into_iter()
+        .map(|(sentence, start, end)| {
+            let spec_line = source_map.locate(&sentence).map_or("reworded".to_string(), |span| span.line.to_string());
+            let cost = report.costs.iter().find(|cost| cost.sentence == sentence).map_or(String::new(), |cost| {
+                format!("{} line(s), {} I/O call(s), {} allocation(s), {} loop(s)", cost.lines, cost.io_calls, cost.allocations, cost.loops)
+            });
+            vec![sentence, spec_line, format!("{}-{}", start, end), cost]
+        })
+        .collect();
+    Block::Table(&["Sentence", "Spec line", "Generated lines", "Cost"], rows)
+}
+
+/// Flags the toolchain was given, and what they produced
+fn build(report: &CompilationReport) -> Vec<Block> {
+    let flags: Vec<&str> = report.build_flags.iter().chain(&report.link_flags).map(String::as_str).collect();
+    let mut blocks = vec![match flags.is_empty() {
+        true => Block::Text(format!("Built by {} with its default flags.", report.toolchain)),
+        false => Block::Text(format!("Built by {} with: {}", report.toolchain, flags.join(" "))),
+    }];
+    if let Some(hardening) = &report.hardening {
+        blocks.push(Block::Code("text", hardening.to_string()));
+    }
+    blocks.push(Block::Text(match report.capabilities.is_empty() {
+        true => "The program has no external interactions.".to_string(),
+        false => format!("External interactions: {}", capabilities::describe(&report.capabilities)),
+    }));
+    blocks
+}
+
+/// What each code generation and link flag the toolchain was given does
+fn optimizations(report: &CompilationReport) -> Block {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut flags = report.build_flags.iter().chain(&report.link_flags);
+    while let Some(flag) = flags.next() {
+        // rustc takes codegen options as `-C name=value` and output kinds as `--crate-type kind`
+        let flag = match flag.as_str() {
+            "-C" | "--crate-type" => format!("{} {}", flag, flags.next().map_or("", String::as_str)),
+            _ => flag.clone(),
+        };
+        let effect = flag_effect(flag.rsplit(' ').next().unwrap_or_default()).to_string();
+        rows.push(vec![flag, effect]);
+    }
+    let optimized = rows.iter().any(|row| row[0].starts_with("-O") || row[0].contains("opt-level="));
+    if !optimized {
+        rows.push(vec![
+            "(none)".to_string(),
+            format!("no optimization level was given, so {} used its default, which doesn't optimize", report.toolchain),
+        ]);
+    }
+    Block::Table(&["Flag", "Effect"], rows)
+}
+
+/// What a driver flag, or a rustc codegen option without its `-C`, does to the program
+fn flag_effect(flag: &str) -> &'static str {
+    match flag {
+        "-O0" | "opt-level=0" => "no optimization, for the fastest build and most faithful debugging",
+        "-O1" | "opt-level=1" => "basic optimization",
+        "-O2" | "opt-level=2" => "full optimization without trading size for speed",
+        "-O3" | "opt-level=3" => "aggressive optimization, including vectorization and more inlining",
+        "-Os" | "opt-level=s" => "optimization for size",
+        "-Oz" | "opt-level=z" => "optimization for size above all",
+        "-flto" | "lto" => "link-time optimization across the whole program",
+        "-g" | "debuginfo=2" => "debug info",
+        "-pthread" => "POSIX threads, for running main on the stack from --stack-size",
+        "-fPIE" | "-pie" | "relocation-model=pie" => "position-independent executable, for address space layout randomization",
+        "-fstack-protector-strong" => "stack canaries in functions with arrays or address-taken locals",
+        "-U_FORTIFY_SOURCE" => "clears any fortification level the toolchain sets by default",
+        "-D_FORTIFY_SOURCE=2" => "bounds-checked versions of string and memory calls",
+        "overflow-checks=on" => "integer overflow checks",
+        "-Wl,-z,relro,-z,now" | "link-arg=-Wl,-z,relro,-z,now" => "read-only relocations, resolved at startup",
+        "-Wl,-z,noexecstack" | "link-arg=-Wl,-z,noexecstack" => "non-executable stack",
+        "-static" | "target-feature=+crt-static" => "static linking",
+        "-shared" | "-fPIC" | "cdylib" | "staticlib" | "-c" => "library output",
+        flag if flag.starts_with("-fuse-ld=") || flag.starts_with("link-arg=-fuse-ld=") => "linker choice",
+        flag if flag.starts_with("-march=") || flag.starts_with("-m") || flag.starts_with("target-feature=") => "CPU features for code generation",
+        _ => "passed to the toolchain as given",
+    }
+}
+
+/// A Markdown code fence longer than any run of backticks in `code`
+fn fence(code: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Text safe to place in HTML
+#[cfg(feature = "graph")]
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(source: &str, generated_code: &str) -> CompilationReport {
+        serde_json::from_value(serde_json::json!({
+            "source_file": "spec.dshp",
+            "source_hash": report::hash_source(source),
+            "source": source,
+            "language": "c",
+            "toolchain": "gcc",
+            "executable": "spec",
+            "generated_code": generated_code,
+            "build_flags": ["-O2", "-pthread"],
+            "link_flags": ["-flto"],
+        }))
+        .expect("report parses")
+    }
+
+    #[test]
+    fn fence_outruns_the_longest_backtick_run() {
+        assert_eq!(fence("int main(void) { return 0; }"), "```");
+        assert_eq!(fence("/* ``` */"), "````");
+        assert_eq!(fence("/* ````` */"), "``````");
+        let page = markdown(&report("Print \"hi\".\n", "/* ````` */\nint main(void) { return 0; }\n"));
+        assert!(page.contains("``````c\n/* ````` */"));
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn page_shows_control_flow_and_flags() {
+        let source = "Ask for a list of numbers called scores.\nSet total to the sum of scores.\nPrint total.\n";
+        let page = markdown(&report(source, "int main(void) { return 0; }\n"));
+        assert!(page.contains("## Control flow"));
+        assert!(page.contains("```dot\ndigraph spec {"));
+        assert!(page.contains("| -O2 | full optimization"));
+        assert!(page.contains("| -flto | link-time optimization"));
+        
+        let page = markdown(&report("Read the scores and so on.\n", "int main(void) { return 0; }\n"));
+        assert!(page.contains("outside the offline vocabulary, so its control flow"));
+    }
+
+    #[test]
+    fn ambiguities_give_their_resolution_and_reasoning() {
+        let source = "Read the scores and so on.\nSort the players by wins.\n";
+        let page = markdown(&report(source, "int main(void) { return 0; }\n"));
+        assert!(page.contains("\"and so on\" (line 1)"));
+        assert!(page.contains("| Sort the players by wins | merge sort by wins, ascending | records are sorted by wins"));
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn html_escapes_code() {
+        let page = html(&report("Print \"hi\".\n", "#include <stdio.h>\n"));
+        assert!(page.contains("#include &lt;stdio.h&gt;"));
+        assert!(!page.contains("<stdio.h>"));
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running program is polled for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Output of a program run with captured stdio
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProgramOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code, or None if the program was killed by a signal or timed out
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl ProgramOutput {
+    /// Whether the program ran to completion and exited with status 0
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+    
+    /// Short description of how the program ended
+    pub fn outcome(&self) -> String {
+        match (self.timed_out, self.exit_code) {
+            (true, _) => "timed out".to_string(),
+            (false, Some(0)) => "exited successfully".to_string(),
+            (false, Some(code)) => format!("exited with status {}", code),
+            (false, None) => "killed by a signal".to_string(),
+        }
+    }
+}
+
+/// Runtime that executes WebAssembly programs built for WASI
+pub const WASM_RUNTIME: &str = "wasmtime";
+
+/// Command that starts a compiled program; `.wasm` modules run under the WASI runtime
+///
+/// Native programs get the stack limit set by `set_stack_limit`.
+pub fn command(path: &str) -> Command {
+    if path.ends_with(".wasm") {
+        let mut command = Command::new(WASM_RUNTIME);
+        command.arg("run").arg(path);
+        command
+    } else {
+        let mut command = Command::new(path);
+        limit_stack(&mut command);
+        command
+    }
+}
+
+/// Run a program feeding it `stdin`, capturing its output and killing it after `timeout`
+pub fn run_captured(path: &str, stdin: &str, timeout: Option<Duration>) -> Result<ProgramOutput> {
+    let mut child = command(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute the compiled program: {}", path))?;
+    
+    // Feed stdin and drain stdout/stderr on threads so a chatty program can't block on a full pipe
+    let mut child_stdin = child.stdin.take().expect("stdin is piped");
+    let input = stdin.to_string();
+    let writer = thread::spawn(move || {
+        // The program may exit without reading everything; that's not an error
+        let _ = child_stdin.write_all(input.as_bytes());
+    });
+    let stdout = read_to_end(child.stdout.take().expect("stdout is piped"));
+    let stderr = read_to_end(child.stderr.take().expect("stderr is piped"));
+    
+    let started = Instant::now();
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            timed_out = true;
+            let _ = child.kill();
+            break child.wait()?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    
+    let _ = writer.join();
+    Ok(ProgramOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        exit_code: if timed_out { None } else { status.code() },
+        timed_out,
+    })
+}
+
+/// Parse a byte count with an optional K, M, or G suffix, e.g. `8M`
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1 << 10),
+        Some('M') => (&value[..value.len() - 1], 1 << 20),
+        Some('G') => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid size `{}`, expected bytes with an optional K, M, or G suffix", value))
+}
+
+/// Main-thread stack limit for compiled programs, as the soft and hard limits to set
+#[cfg(unix)]
+static PROGRAM_STACK: OnceLock<(libc::rlim_t, libc::rlim_t)> = OnceLock::new();
+
+/// Give every compiled program started through `command` a main-thread stack of `bytes`
+///
+/// The limit is set in the child between fork and exec, so nhlp itself and
+/// the toolchains it runs keep their own stacks.
+#[cfg(unix)]
+pub fn set_stack_limit(bytes: u64) -> Result<()> {
+    let limit = current_stack_limit()?;
+    let requested = bytes as libc::rlim_t;
+    if limit.rlim_max != libc::RLIM_INFINITY && requested > limit.rlim_max {
+        anyhow::bail!("Requested stack size of {} bytes exceeds the hard limit of {} bytes", bytes, limit.rlim_max);
+    }
+    let _ = PROGRAM_STACK.set((requested, limit.rlim_max));
+    Ok(())
+}
+
+/// Stack limits can only be raised for child processes on Unix
+#[cfg(not(unix))]
+pub fn set_stack_limit(_bytes: u64) -> Result<()> {
+    anyhow::bail!("The main-thread stack limit can only be raised on Unix")
+}
+
+/// The main-thread stack compiled programs get, in bytes, or None if it is unlimited or unknown
+#[cfg(unix)]
+pub fn stack_limit() -> Option<u64> {
+    let soft = match PROGRAM_STACK.get() {
+        Some((soft, _)) => *soft,
+        None => current_stack_limit().ok()?.rlim_cur,
+    };
+    (soft != libc::RLIM_INFINITY).then_some(soft as u64)
+}
+
+#[cfg(not(unix))]
+pub fn stack_limit() -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn current_stack_limit() -> Result<libc::rlimit> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes the struct passed to it
+    if unsafe { libc::getrlimit(libc::RLIMIT_STACK, &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to read the stack limit");
+    }
+    Ok(limit)
+}
+
+/// Apply the stack limit from `set_stack_limit`, if any, to a program about to be started
+#[cfg(unix)]
+fn limit_stack(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    let Some(&(soft, hard)) = PROGRAM_STACK.get() else {
+        return;
+    };
+    // SAFETY: the closure only calls setrlimit, which is async-signal-safe, and allocates nothing
+    unsafe {
+        command.pre_exec(move || {
+            let limit = libc::rlimit { rlim_cur: soft, rlim_max: hard };
+            if libc::setrlimit(libc::RLIMIT_STACK, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn limit_stack(_command: &mut Command) {}
+
+/// Whether a program was killed by a segmentation fault, the usual symptom of a stack overflow
+#[cfg(unix)]
+pub fn crashed_with_segfault(status: &std::process::ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(libc::SIGSEGV)
+}
+
+#[cfg(not(unix))]
+pub fn crashed_with_segfault(_status: &std::process::ExitStatus) -> bool {
+    false
+}
+
+/// Read a pipe to the end on a background thread
+fn read_to_end<R: Read + Send + 'static>(mut pipe: R) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use serde_json::Value;
+
+/// Field holding the schema version of a saved document
+const VERSION_FIELD: &str = "schema_version";
+
+/// Rewrites a document from one schema version to the next
+pub type Migration = fn(&mut Value);
+
+/// Bring a saved document up to the `current` schema version
+///
+/// Documents written before versioning was introduced count as version 0.
+/// `migrations[n]` upgrades a document from version n to n + 1, so the slice
+/// must hold one entry per version below `current`.
+pub fn upgrade(kind: &str, mut document: Value, current: u32, migrations: &[Migration]) -> Result<Value> {
+    debug_assert_eq!(migrations.len(), current as usize);
+    
+    let version = document.get(VERSION_FIELD).and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(current) {
+        anyhow::bail!(
+            "{} uses schema version {}, but this nhlp only understands up to version {}; upgrade nhlp to read it",
+            kind, version, current
+        );
+    }
+    
+    for migration in &migrations[version as usize..] {
+        migration(&mut document);
+    }
+    if let Some(fields) = document.as_object_mut() {
+        fields.insert(VERSION_FIELD.to_string(), Value::from(current));
+    }
+    
+    Ok(document)
+}
+
+/// Migration for versions whose format didn't change beyond new defaulted fields
+pub fn unchanged(_document: &mut Value) {}
@@ -0,0 +1,82 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+static PATTERNS: LazyLock<[Regex; 3]> = LazyLock::new(|| Signal::ALL.map(|signal| Regex::new(signal.pattern()).expect("signal pattern is valid")));
+
+/// Signals a spec can describe a reaction to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Signal {
+    /// Ctrl-C at the terminal
+    Interrupt,
+    /// A polite request to stop, e.g. from `kill` or a service manager
+    Terminate,
+    /// The controlling terminal went away
+    Hangup,
+}
+
+impl Signal {
+    const ALL: [Signal; 3] = [Signal::Interrupt, Signal::Terminate, Signal::Hangup];
+
+    /// Phrases in a spec that describe the signal arriving
+    fn pattern(self) -> &'static str {
+        match self {
+            Signal::Interrupt => r"(?i)\b(?:presses?\s+)?ctrl[- ]?c\b|\bsigint\b|\b(?:is|gets)\s+interrupted\b",
+            Signal::Terminate => r"(?i)\bsigterm\b|\b(?:is|gets)\s+terminated\b|\b(?:is\s+)?asked\s+to\s+(?:stop|terminate|shut\s+down)\b",
+            Signal::Hangup => r"(?i)\bsighup\b|\b(?:terminal|connection)\s+hangs?\s+up\b",
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Signal::Interrupt => "SIGINT",
+            Signal::Terminate => "SIGTERM",
+            Signal::Hangup => "SIGHUP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Signals the spec describes reactions to, each with the phrase that described it first
+pub fn extract(description: &str) -> BTreeMap<Signal, String> {
+    Signal::ALL
+        .into_iter()
+        .filter_map(|signal| {
+            let phrase = PATTERNS[signal as usize].find(description)?;
+            Some((signal, phrase.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Instructions added to the translation prompt for a program that handles signals
+///
+/// Almost nothing is safe to call from a signal handler, so the handler
+/// only sets a flag and the program does the described work, such as saving
+/// and exiting, from its main loop once it sees the flag.
+pub fn prompt_note(language: &str, signals: &BTreeMap<Signal, String>) -> String {
+    let names: Vec<String> = signals.keys().map(Signal::to_string).collect();
+    let install = if language == "rust" {
+        "Without external crates, declare `extern \"C\" { fn signal(signum: i32, handler: usize) -> usize; }`, \
+         and give each signal an `extern \"C\" fn(i32)` handler whose only action is storing true into a \
+         `static AtomicBool`."
+    } else {
+        "Install each handler with sigaction (not signal()) and without SA_RESTART, so blocking reads return \
+         with EINTR. The handler's only action is setting a `static volatile sig_atomic_t` flag."
+    };
+    format!(
+        "\nSIGNALS: the program must react to {} as the description says. {} \
+         Never call printf, malloc, exit, or file functions inside a handler. \
+         Check the flag in every loop and after every blocking read, and when it is set, do what the \
+         description says (such as saving and exiting cleanly) from normal code.\n",
+        names.join(", "),
+        install,
+    )
+}
+
+/// Whether generated code installs a signal handler at all
+pub fn installs_handler(code: &str) -> bool {
+    code.contains("sigaction(") || code.contains("signal(") || code.contains("SetConsoleCtrlHandler(")
+}
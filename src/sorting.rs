@@ -0,0 +1,209 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// `sort the list of scores descending`, `rank the players by wins`
+const SORT_PATTERN: &str = r"(?i)\b(?:sort|rank)(?:s|ing)?\s+(?:the\s+|all\s+|its\s+)?(?P<what>[\w\s]+?)(?:\s+by\s+(?P<key>\w+))?(?:\s+(?:in\s+)?(?P<direction>descending|ascending|reverse|decreasing|increasing|largest\s+first|smallest\s+first|highest\s+first|lowest\s+first)(?:\s+order)?)?(?:[.,;\n]|$)";
+
+/// `find the position of`, `look up the index of`
+const SEARCH_PATTERN: &str = r"(?i)\b(?:find|look\s+up|search\s+for)\s+(?:the\s+)?(?:position|index|place)\s+of\b";
+
+/// Phrases asking for equal items to keep their original order
+const STABLE_PATTERN: &str = r"(?i)\bstable\b|\bstably\b|\b(?:keep|keeping|preserve|preserving)\s+(?:the\s+)?(?:original|input|existing)\s+order\b|\bties\s+(?:keep|stay\s+in)\s+(?:their\s+)?(?:original\s+)?order\b";
+
+/// The most of something a spec says it handles, e.g. `up to 10 million scores`
+const SIZE_PATTERN: &str = r"(?i)\b(?:up\s+to|at\s+most|no\s+more\s+than|fewer\s+than|less\s+than|about|around|roughly)\s+(?P<count>\d[\d,]*)\s*(?P<scale>thousand|million|billion)?\s+(?P<noun>[a-z]\w*)";
+
+static SORT: LazyLock<Regex> = LazyLock::new(|| Regex::new(SORT_PATTERN).expect("sort pattern is valid"));
+static SEARCH: LazyLock<Regex> = LazyLock::new(|| Regex::new(SEARCH_PATTERN).expect("search pattern is valid"));
+static STABLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(STABLE_PATTERN).expect("stable pattern is valid"));
+static SIZE: LazyLock<Regex> = LazyLock::new(|| Regex::new(SIZE_PATTERN).expect("size pattern is valid"));
+
+/// Nouns a size hint can use for whatever is sorted
+const GENERIC_NOUNS: &[&str] = &["item", "element", "entry", "value"];
+
+/// Below this many items, insertion sort beats anything with more bookkeeping
+const SMALL: u64 = 32;
+
+/// The sort a generated program is told to write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// For inputs the spec says are tiny; stable
+    Insertion,
+    /// For when equal items must keep their order, or records are sorted by a key
+    Merge,
+    /// Quicksort with median-of-three pivots, insertion sort for short ranges,
+    /// and heapsort past a depth limit, in the style of pdqsort
+    PatternDefeating,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Algorithm::Insertion => "insertion sort",
+            Algorithm::Merge => "merge sort",
+            Algorithm::PatternDefeating => "pattern-defeating quicksort",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A sort the spec asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    /// The spec text asking for it
+    pub phrase: String,
+    pub descending: bool,
+    /// The field records are sorted by, if the spec names one
+    pub key: Option<String>,
+    /// The most items the spec says are sorted, from a size hint naming them
+    pub size: Option<u64>,
+    pub algorithm: Algorithm,
+}
+
+/// What the spec says about sorting and searching
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sorting {
+    pub sorts: Vec<Sort>,
+    /// Whether the spec finds the position of an item, which is done with binary search
+    pub searches: bool,
+}
+
+impl Sort {
+    /// Why its algorithm was chosen, following the rules of [`extract`]
+    pub fn reason(&self) -> String {
+        match (self.algorithm, self.size, &self.key) {
+            (Algorithm::Insertion, Some(size), _) => {
+                format!("the spec sorts at most {} items, too few to pay for more bookkeeping than insertion sort", size)
+            }
+            (Algorithm::Merge, _, Some(key)) => {
+                format!("records are sorted by {}, and sorting by one key after another needs equal items to keep their order", key)
+            }
+            (Algorithm::Merge, _, None) => "the spec asks for equal items to keep their original order".to_string(),
+            _ => "nothing needs equal items to keep their order, so the fastest general sort applies".to_string(),
+        }
+    }
+}
+
+impl Sorting {
+    pub fn is_empty(&self) -> bool {
+        self.sorts.is_empty() && !self.searches
+    }
+}
+
+/// The sorts and searches in a spec, each with the algorithm chosen for it
+///
+/// A size hint naming what is sorted, or items in general, that caps it at
+/// a few dozen picks insertion sort; `retry at most 3 times` says nothing
+/// about a list of scores. Otherwise
+/// equal items keep their order when the spec asks for that, and when
+/// records are sorted by a key, since sorting by one key after another
+/// relies on it; anything else gets the quicksort variant.
+pub fn extract(description: &str) -> Sorting {
+    let stable = STABLE.is_match(description);
+    let hints = size_hints(description);
+    let sorts = SORT
+        .captures_iter(description)
+        .map(|captures| {
+            let key = captures.name("key").map(|key| key.as_str().to_lowercase());
+            let sorted: Vec<String> = captures["what"].split_whitespace().map(stem).collect();
+            let size = hints
+                .iter()
+                .filter(|(_, noun)| sorted.contains(noun) || GENERIC_NOUNS.contains(&noun.as_str()))
+                .map(|(count, _)| *count)
+                .max();
+            let algorithm = match size {
+                Some(size) if size <= SMALL => Algorithm::Insertion,
+                _ if stable || key.is_some() => Algorithm::Merge,
+                _ => Algorithm::PatternDefeating,
+            };
+            let descending = captures.name("direction").is_some_and(|direction| {
+                let direction = direction.as_str().to_lowercase();
+                ["descending", "reverse", "decreasing", "largest", "highest"].iter().any(|word| direction.starts_with(word))
+            });
+            Sort {
+                phrase: captures[0].trim().trim_end_matches(['.', ',', ';']).to_string(),
+                descending,
+                key,
+                size,
+                algorithm,
+            }
+        })
+        .collect();
+    let searches = SEARCH.is_match(description);
+    Sorting { sorts, searches }
+}
+
+/// Each upper bound the spec gives, with the stem of the noun it counts, e.g. (10000000, "score") for `up to 10 million scores`
+fn size_hints(description: &str) -> Vec<(u64, String)> {
+    SIZE
+        .captures_iter(description)
+        .filter_map(|captures| {
+            let count: u64 = captures["count"].replace(',', "").parse().ok()?;
+            let scale = match captures.name("scale").map(|scale| scale.as_str().to_lowercase()) {
+                Some(scale) if scale == "thousand" => 1_000,
+                Some(scale) if scale == "million" => 1_000_000,
+                Some(scale) if scale == "billion" => 1_000_000_000,
+                _ => 1,
+            };
+            Some((count.checked_mul(scale)?, stem(&captures["noun"])))
+        })
+        .collect()
+}
+
+/// A word lowercased and made singular, so `scores` and `score` compare equal
+fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    if let Some(stem) = word.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+    match word.strip_suffix('s') {
+        Some(singular) if !singular.is_empty() && !singular.ends_with('s') => singular.to_string(),
+        _ => word,
+    }
+}
+
+/// Instructions added to the translation prompt for programs that sort or search
+///
+/// Each sort names the algorithm chosen for it, so the model writes that
+/// one instead of whichever it reaches for first, and finding a position
+/// becomes a binary search over the sorted data.
+pub fn prompt_note(language: &str, sorting: &Sorting) -> String {
+    let mut note = String::from("\nSORTING: write the sorts the description asks for as follows.\n");
+    for sort in &sorting.sorts {
+        let order = if sort.descending { "descending" } else { "ascending" };
+        let by = sort.key.as_ref().map(|key| format!(" by {}", key)).unwrap_or_default();
+        let how = match (sort.algorithm, language) {
+            (Algorithm::Insertion, _) => "an insertion sort".to_string(),
+            (Algorithm::Merge, "rust") => "slice::sort_by, which is a stable merge sort".to_string(),
+            (Algorithm::Merge, _) => {
+                "a bottom-up merge sort with one scratch buffer allocated once, taking from the left run on ties so \
+                 equal items keep their order"
+                    .to_string()
+            }
+            (Algorithm::PatternDefeating, "rust") => "slice::sort_unstable_by, which is pattern-defeating quicksort".to_string(),
+            (Algorithm::PatternDefeating, _) => {
+                "a quicksort that picks the median of three as pivot, insertion-sorts ranges of 16 items or fewer, \
+                 recurses into the smaller side and loops on the larger, and switches to heapsort past a depth of \
+                 2*log2(n), instead of qsort"
+                    .to_string()
+            }
+        };
+        let size = sort.size.map(|size| format!(" Expect up to {} items; size buffers for that and grow them as needed.", size)).unwrap_or_default();
+        note.push_str(&format!("- \"{}\": {}{} with {}.{}\n", sort.phrase, order, by, how, size));
+    }
+    if sorting.searches {
+        let search = if language == "rust" {
+            "slice::partition_point, or binary_search_by"
+        } else {
+            "a hand-written lower-bound binary search computing the midpoint as low + (high - low) / 2"
+        };
+        note.push_str(&format!(
+            "To find the position of an item, use {} on data sorted by the same key and order, sorting it first \
+             if it isn't; report the first matching position counting from 1, and say so when the item is missing.\n",
+            search
+        ));
+    }
+    note.push_str("Comparisons must be consistent: compare numbers as numbers and text with strcmp or Ord, never by subtracting.\n");
+    note
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+
+use crate::coverage;
+
+/// Label for code that comes before any sentence comment
+pub const UNATTRIBUTED: &str = "(code not attributed to a sentence)";
+
+/// Where in the spec a sentence was written, counting lines and columns from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    /// Length in characters
+    pub length: usize,
+}
+
+/// Maps generated code back to the sentences of the spec it implements
+///
+/// The model writes each sentence of the spec as a comment above the code
+/// for it, as coverage and cost attribution also rely on. Consecutive comment
+/// lines are one sentence, and every code line belongs to the sentence above it.
+pub struct SourceMap<'a> {
+    spec: &'a str,
+    /// Sentence comment governing each line of generated code
+    sentences: Vec<Option<String>>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(spec: &'a str, code: &str) -> Self {
+        let mut sentences = Vec::new();
+        let mut current: Option<String> = None;
+        let mut in_comment_run = false;
+        for line in code.lines() {
+            let line = line.trim();
+            match line.strip_prefix("//").map(|comment| comment.trim().trim_matches('"')) {
+                Some(comment) if !coverage::is_provenance_comment(comment) => {
+                    match (&mut current, in_comment_run) {
+                        (Some(sentence), true) => {
+                            sentence.push(' ');
+                            sentence.push_str(comment);
+                        }
+                        _ => current = Some(comment.to_string()),
+                    }
+                    in_comment_run = true;
+                }
+                _ => in_comment_run = false,
+            }
+            sentences.push(current.clone());
+        }
+        Self { spec, sentences }
+    }
+    
+    /// The sentence comment above a line of generated code, counting from 1
+    pub fn sentence_for_line(&self, line: usize) -> Option<&str> {
+        self.sentences.get(line.checked_sub(1)?)?.as_deref()
+    }
+    
+    /// Where `sentence` appears in the spec, ignoring case and surrounding punctuation
+    ///
+    /// Returns None when the model reworded the sentence in its comment.
+    pub fn locate(&self, sentence: &str) -> Option<Span> {
+        let needle = sentence.trim().trim_end_matches(['.', '!', '?', ';']).to_lowercase();
+        if needle.is_empty() {
+            return None;
+        }
+        self.spec.lines().enumerate().find_map(|(index, line)| {
+            let line = line.to_lowercase();
+            let offset = line.find(&needle)?;
+            Some(Span {
+                line: index + 1,
+                column: line[..offset].chars().count() + 1,
+                length: needle.chars().count(),
+            })
+        })
+    }
+    
+    /// The span of the sentence a line of generated code implements
+    pub fn span_for_line(&self, line: usize) -> Option<Span> {
+        self.locate(self.sentence_for_line(line)?)
+    }
+    
+    /// `code` with a `#line` directive before each line written for a sentence,
+    /// attributing it to that sentence's line of the spec at `path`
+    ///
+    /// C compilers carry the directives into their debug info, so debuggers
+    /// step through the spec rather than the generated code. Lines before the
+    /// first sentence comment keep their own numbering, and continuation lines
+    /// of macros get no directive, since one would end the macro.
+    pub fn with_line_directives(&self, code: &str, path: &str) -> String {
+        let path = path.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut annotated = String::new();
+        let mut spec_line = None;
+        let mut continued = false;
+        for (index, line) in code.lines().enumerate() {
+            if let Some(span) = self.span_for_line(index + 1) {
+                spec_line = Some(span.line);
+            }
+            if let (Some(spec_line), false) = (spec_line, continued) {
+                let _ = writeln!(annotated, "#line {} \"{}\"", spec_line, path);
+            }
+            let _ = writeln!(annotated, "{}", line);
+            continued = line.ends_with('\\');
+        }
+        annotated
+    }
+    
+    /// A rustc-style excerpt underlining a span of the spec at `path`
+    pub fn render(&self, path: &str, span: Span) -> String {
+        let text = self.spec.lines().nth(span.line - 1).unwrap_or_default();
+        let gutter = " ".repeat(span.line.to_string().len());
+        let mut excerpt = String::new();
+        let _ = writeln!(excerpt, "{}--> {}:{}:{}", gutter, path, span.line, span.column);
+        let _ = writeln!(excerpt, "{} |", gutter);
+        let _ = writeln!(excerpt, "{} | {}", span.line, text);
+        let _ = write!(excerpt, "{} | {}{}", gutter, " ".repeat(span.column - 1), "^".repeat(span.length.max(1)));
+        excerpt
+    }
+}
@@ -0,0 +1,351 @@
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::LazyLock;
+
+/// A C function definition header, `static long fib(int n) {`
+const C_FUNCTION_PATTERN: &str = r"^(?:[A-Za-z_][\w\s\*]*?[\s\*])(?P<name>[A-Za-z_]\w*)\s*\((?P<params>[^;{}]*)\)\s*(?:\{.*)?$";
+
+/// A Rust function definition header, `fn fib(n: u64) -> u64 {`
+const RUST_FUNCTION_PATTERN: &str = r#"^(?:pub(?:\([\w:]+\))?\s+)?(?:const\s+|unsafe\s+|extern\s+(?:"[^"]*"\s+)?)*fn\s+(?P<name>[A-Za-z_]\w*)\s*(?:<[^>]*>)?\s*\((?P<params>[^{}]*)\)"#;
+
+/// A local C array, `char line[256]` or `double values[16][4]`
+const C_ARRAY_PATTERN: &str = r"\b(?P<type>char|short|int|long|float|double|size_t|int\d+_t|uint\d+_t|[A-Za-z_]\w*\s*\*)\s+[A-Za-z_]\w*\s*(?P<dims>(?:\[\s*\d+\s*\])+)";
+
+/// A local Rust array, `[0u8; 256]` or `[0.0; 16]`
+const RUST_ARRAY_PATTERN: &str = r"\[\s*[^;\[\]]*?(?P<type>u8|i8|u16|i16|u32|i32|f32|u64|i64|f64|usize|isize|char)?\s*;\s*(?P<count>\d+)\s*\]";
+
+/// A local scalar, one per declaration
+const C_LOCAL_PATTERN: &str = r"^(?:const\s+|static\s+|unsigned\s+|signed\s+)*(?:char|short|int|long|float|double|size_t|bool|int\d+_t|uint\d+_t|FILE|struct\s+\w+)\b";
+
+/// Calls, `name(`
+const CALL_PATTERN: &str = r"\b(?P<name>[A-Za-z_]\w*)\s*\(";
+
+/// String and character literals, whose contents aren't code
+const LITERAL_PATTERN: &str = r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)'"#;
+
+static C_FUNCTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(C_FUNCTION_PATTERN).expect("valid function pattern"));
+static RUST_FUNCTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(RUST_FUNCTION_PATTERN).expect("valid function pattern"));
+static C_ARRAY: LazyLock<Regex> = LazyLock::new(|| Regex::new(C_ARRAY_PATTERN).expect("valid C array pattern"));
+static RUST_ARRAY: LazyLock<Regex> = LazyLock::new(|| Regex::new(RUST_ARRAY_PATTERN).expect("valid Rust array pattern"));
+static C_LOCAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(C_LOCAL_PATTERN).expect("valid local pattern"));
+static CALL: LazyLock<Regex> = LazyLock::new(|| Regex::new(CALL_PATTERN).expect("valid call pattern"));
+static LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(LITERAL_PATTERN).expect("valid literal pattern"));
+
+/// Words that look like calls or definitions but aren't
+const KEYWORDS: &[&str] = &["if", "while", "for", "switch", "return", "sizeof", "else", "do", "match", "loop"];
+
+/// Return address and saved frame pointer, pushed for every call
+const CALL_OVERHEAD: u64 = 16;
+
+/// Stack assumed for each parameter and scalar local, a register-sized slot
+const SLOT: u64 = 8;
+
+/// Frames are aligned to this many bytes
+const FRAME_ALIGNMENT: u64 = 16;
+
+/// Stack used by the generated program's deepest chain of calls
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackEstimate {
+    /// The chain, from `main` to the deepest function
+    pub path: Vec<String>,
+    /// Bytes of stack the chain uses, counting each recursive function once;
+    /// `u64::MAX` when the arrays on it are too large to count
+    pub bytes: u64,
+    /// Functions that call themselves, directly or through others, so no depth bounds them
+    pub recursive: Vec<String>,
+}
+
+struct Function {
+    frame: u64,
+    calls: BTreeSet<String>,
+}
+
+/// Estimate the stack the generated code uses from its call graph and frame sizes
+///
+/// Frames are sized from parameters, scalar locals, and fixed-size local
+/// arrays, which is what overflows a stack in practice; the estimate leaves
+/// out spills and padding the compiler adds, so treat it as a lower bound.
+/// Library calls aren't counted.
+pub fn analyze(code: &str, language: &str) -> StackEstimate {
+    let functions = functions(code, language);
+    let mut recursive = BTreeSet::new();
+    let mut deepest: BTreeMap<String, (u64, Vec<String>)> = BTreeMap::new();
+    let mut path = Vec::new();
+    let Some((bytes, path_from_main)) = depth("main", &functions, &mut path, &mut deepest, &mut recursive) else {
+        return StackEstimate::default();
+    };
+    StackEstimate { path: path_from_main, bytes, recursive: recursive.into_iter().collect() }
+}
+
+impl StackEstimate {
+    /// Whether the chain declares arrays too large to count, which no stack holds
+    pub fn is_unbounded(&self) -> bool {
+        self.bytes == u64::MAX
+    }
+}
+
+/// Bytes and chain of the deepest call path from `name`, breaking cycles where they close
+fn depth(
+    name: &str,
+    functions: &BTreeMap<String, Function>,
+    path: &mut Vec<String>,
+    deepest: &mut BTreeMap<String, (u64, Vec<String>)>,
+    recursive: &mut BTreeSet<String>,
+) -> Option<(u64, Vec<String>)> {
+    let function = functions.get(name)?;
+    if let Some(found) = deepest.get(name) {
+        return Some(found.clone());
+    }
+    path.push(name.to_string());
+    let mut below: (u64, Vec<String>) = (0, Vec::new());
+    for callee in &function.calls {
+        if let Some(start) = path.iter().position(|on_path| on_path == callee) {
+            recursive.extend(path[start..].iter().cloned());
+            continue;
+        }
+        if let Some(found) = depth(callee, functions, path, deepest, recursive) {
+            if found.0 > below.0 {
+                below = found;
+            }
+        }
+    }
+    path.pop();
+
+    let mut chain = vec![name.to_string()];
+    chain.extend(below.1);
+    let found = (function.frame.saturating_add(below.0), chain);
+    // A function on an open cycle may be deeper when reached another way, so only settled results are kept
+    if !path.iter().any(|on_path| recursive.contains(on_path)) {
+        deepest.insert(name.to_string(), found.clone());
+    }
+    Some(found)
+}
+
+/// Each function defined in the code, with its frame size and the defined functions it calls
+fn functions(code: &str, language: &str) -> BTreeMap<String, Function> {
+    let header: &Regex = if language == "rust" { &RUST_FUNCTION } else { &C_FUNCTION };
+
+    let mut functions: BTreeMap<String, (u64, Vec<String>)> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    let mut nesting = 0i64;
+    for line in code.lines() {
+        let line = LITERAL.replace_all(line, "\"\"");
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("/*") || line.starts_with('*') {
+            continue;
+        }
+
+        let mut body = line;
+        if nesting == 0 || (language == "rust" && current.is_none()) {
+            if let Some(captures) = header.captures(line).filter(|captures| !KEYWORDS.contains(&&captures["name"])) {
+                let params = captures["params"].split(',').filter(|param| !param.trim().is_empty() && param.trim() != "void").count() as u64;
+                let name = captures["name"].to_string();
+                functions.insert(name.clone(), (params * SLOT, Vec::new()));
+                current = Some(name);
+                nesting = 0;
+                body = line.split_once(')').map_or("", |(_, rest)| rest);
+            }
+        }
+        if let Some((frame, calls)) = current.as_ref().and_then(|name| functions.get_mut(name)) {
+            if language == "rust" {
+                *frame = frame.saturating_add(line.matches("let ").count() as u64 * SLOT);
+                for captures in RUST_ARRAY.captures_iter(line) {
+                    // A count too large for u64 is as unbounded as one that overflows
+                    let count: u64 = captures["count"].parse().unwrap_or(u64::MAX);
+                    let size = captures.name("type").map_or(SLOT, |kind| element_size(kind.as_str()));
+                    *frame = frame.saturating_add(count.saturating_mul(size));
+                }
+            } else if nesting > 0 {
+                let arrays: Vec<_> = C_ARRAY.captures_iter(line).collect();
+                for captures in &arrays {
+                    let count = captures["dims"]
+                        .split(['[', ']'])
+                        .map(str::trim)
+                        .filter(|dim| !dim.is_empty())
+                        .map(|dim| dim.parse::<u64>().unwrap_or(u64::MAX))
+                        .fold(1u64, u64::saturating_mul);
+                    *frame = frame.saturating_add(count.saturating_mul(element_size(captures["type"].trim())));
+                }
+                if arrays.is_empty() && C_LOCAL.is_match(line) && !line.starts_with("static") {
+                    *frame = frame.saturating_add((line.matches(',').count() as u64 + 1) * SLOT);
+                }
+            }
+            calls.extend(CALL.captures_iter(body).map(|captures| captures["name"].to_string()));
+        }
+
+        nesting += line.matches('{').count() as i64 - line.matches('}').count() as i64;
+        if nesting <= 0 && line.contains('}') {
+            nesting = 0;
+            current = None;
+        }
+    }
+
+    let defined: BTreeSet<String> = functions.keys().cloned().collect();
+    functions
+        .into_iter()
+        .map(|(name, (frame, calls))| {
+            let frame = frame
+                .checked_add(CALL_OVERHEAD)
+                .and_then(|frame| frame.div_ceil(FRAME_ALIGNMENT).checked_mul(FRAME_ALIGNMENT))
+                .unwrap_or(u64::MAX);
+            let calls = calls.into_iter().filter(|callee| defined.contains(callee)).collect();
+            (name, Function { frame, calls })
+        })
+        .collect()
+}
+
+/// Bytes per element of a C or Rust array type; pointers and unknown types count as a slot
+fn element_size(kind: &str) -> u64 {
+    match kind {
+        "char" | "u8" | "i8" | "int8_t" | "uint8_t" => 1,
+        "short" | "u16" | "i16" | "int16_t" | "uint16_t" => 2,
+        "int" | "float" | "u32" | "i32" | "f32" | "int32_t" | "uint32_t" => 4,
+        _ => SLOT,
+    }
+}
+
+/// A C `main` definition, whose parameters say whether it takes the command line
+const C_MAIN_PATTERN: &str = r"(?m)^\s*int\s+main\s*\((?P<params>[^)]*)\)\s*(?:\{|$)";
+
+/// A Rust `main` definition without attributes such as `#[tokio::main]`
+const RUST_MAIN_PATTERN: &str = r"(?m)^(?P<indent>[ \t]*)fn\s+main\s*\(\s*\)";
+
+static C_MAIN: LazyLock<Regex> = LazyLock::new(|| Regex::new(C_MAIN_PATTERN).expect("C main pattern is valid"));
+static RUST_MAIN: LazyLock<Regex> = LazyLock::new(|| Regex::new(RUST_MAIN_PATTERN).expect("Rust main pattern is valid"));
+
+/// `code` with its `main` run on a thread whose stack is `bytes`, so the
+/// program carries the stack size wherever it runs
+///
+/// C programs get a `main` that starts the original one through
+/// `pthread_attr_setstacksize`, falling back to calling it directly when the
+/// thread can't be created; they must be linked with `-pthread`. Rust
+/// programs get one that starts it through `std::thread::Builder::stack_size`.
+/// Returns None when no `main` of a shape the wrapper can call is found.
+pub fn with_stack_size(code: &str, language: &str, bytes: u64) -> Option<String> {
+    match language {
+        "c" => {
+            let params = C_MAIN.captures(code)?.name("params").map_or("", |params| params.as_str().trim());
+            let args = match params.split(',').count() {
+                _ if params.is_empty() || params == "void" => "",
+                2 => "args->argc, args->argv",
+                _ => return None,
+            };
+            Some(format!(
+                "#define main nhlp_program_main\n{code}\n#undef main\n#include <pthread.h>\n\n\
+                 /* Runs the program on a thread with the stack given by --stack-size */\n\
+                 struct nhlp_program_args {{ int argc; char **argv; int status; }};\n\n\
+                 static void *nhlp_program_thread(void *data) {{\n\
+                 \x20   struct nhlp_program_args *args = data;\n\
+                 \x20   args->status = nhlp_program_main({args});\n\
+                 \x20   return NULL;\n\
+                 }}\n\n\
+                 int main(int argc, char **argv) {{\n\
+                 \x20   struct nhlp_program_args args = {{ argc, argv, 0 }};\n\
+                 \x20   pthread_attr_t attr;\n\
+                 \x20   pthread_t thread;\n\
+                 \x20   if (pthread_attr_init(&attr) == 0 && pthread_attr_setstacksize(&attr, (size_t){bytes}ULL) == 0\n\
+                 \x20       && pthread_create(&thread, &attr, nhlp_program_thread, &args) == 0) {{\n\
+                 \x20       pthread_join(thread, NULL);\n\
+                 \x20       return args.status;\n\
+                 \x20   }}\n\
+                 \x20   nhlp_program_thread(&args);\n\
+                 \x20   return args.status;\n\
+                 }}\n"
+            ))
+        }
+        "rust" => {
+            if !RUST_MAIN.is_match(code) {
+                return None;
+            }
+            let code = RUST_MAIN.replace(code, "${indent}fn nhlp_program_main()");
+            Some(format!(
+                "{code}\n\
+                 /// Runs the program on a thread with the stack given by --stack-size\n\
+                 fn main() -> std::process::ExitCode {{\n\
+                 \x20   std::thread::Builder::new()\n\
+                 \x20       .stack_size({bytes})\n\
+                 \x20       .spawn(|| std::process::Termination::report(nhlp_program_main()))\n\
+                 \x20       .expect(\"failed to start the program's thread\")\n\
+                 \x20       .join()\n\
+                 \x20       .unwrap_or_else(|panic| std::panic::resume_unwind(panic))\n\
+                 }}\n"
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A byte count the way people write stack sizes, e.g. `8M` or `512K`
+pub fn human_size(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1 << 20 && bytes % (1 << 20) == 0 => format!("{}M", bytes >> 20),
+        bytes if bytes >= 1 << 20 => format!("{:.1}M", bytes as f64 / (1u64 << 20) as f64),
+        bytes if bytes >= 1 << 10 => format!("{}K", bytes.div_ceil(1 << 10)),
+        bytes => format!("{} bytes", bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deepest_chain_counts_local_arrays() {
+        let code = "int helper(int n) {\n    char line[256];\n    return n;\n}\n\nint main(void) {\n    return helper(1);\n}\n";
+        let estimate = analyze(code, "c");
+        assert_eq!(estimate.path, ["main", "helper"]);
+        assert!(estimate.bytes >= 256 + 2 * CALL_OVERHEAD);
+        assert!(estimate.recursive.is_empty());
+    }
+
+    #[test]
+    fn self_calls_are_recursive() {
+        let code = "long fib(int n) {\n    return n < 2 ? n : fib(n - 1) + fib(n - 2);\n}\n\nint main(void) {\n    return (int)fib(10);\n}\n";
+        let estimate = analyze(code, "c");
+        assert_eq!(estimate.recursive, ["fib"]);
+        assert!(!estimate.is_unbounded());
+    }
+
+    #[test]
+    fn huge_c_arrays_are_unbounded_instead_of_overflowing() {
+        let code = "int main(void) {\n    double grid[4294967296][4294967296];\n    return 0;\n}\n";
+        assert!(analyze(code, "c").is_unbounded());
+        let code = "int main(void) {\n    char digits[99999999999999999999999];\n    return 0;\n}\n";
+        assert!(analyze(code, "c").is_unbounded());
+    }
+
+    #[test]
+    fn huge_rust_arrays_are_unbounded_instead_of_overflowing() {
+        let code = "fn main() {\n    let values = [0u64; 18446744073709551615];\n}\n";
+        assert!(analyze(code, "rust").is_unbounded());
+    }
+
+    #[test]
+    fn sizes_read_like_stack_sizes() {
+        assert_eq!(human_size(8 << 20), "8M");
+        assert_eq!(human_size(512 << 10), "512K");
+        assert_eq!(human_size(100), "100 bytes");
+    }
+
+    #[test]
+    fn c_main_runs_on_a_thread_with_the_stack_size() {
+        let code = "#include <stdio.h>\n\nint main(int argc, char *argv[]) {\n    return argc > 1;\n}\n";
+        let wrapped = with_stack_size(code, "c", 64 << 20).unwrap();
+        assert!(wrapped.starts_with("#define main nhlp_program_main\n"));
+        assert!(wrapped.contains("pthread_attr_setstacksize(&attr, (size_t)67108864ULL)"));
+        assert!(wrapped.contains("nhlp_program_main(args->argc, args->argv)"));
+
+        let wrapped = with_stack_size("int main(void) {\n    return 0;\n}\n", "c", 1 << 20).unwrap();
+        assert!(wrapped.contains("args->status = nhlp_program_main();"));
+        assert_eq!(with_stack_size("void run(void) {}\n", "c", 1 << 20), None);
+    }
+
+    #[test]
+    fn rust_main_runs_on_a_thread_with_the_stack_size() {
+        let code = "fn main() {\n    println!(\"hi\");\n}\n";
+        let wrapped = with_stack_size(code, "rust", 8 << 20).unwrap();
+        assert!(wrapped.starts_with("fn nhlp_program_main() {"));
+        assert!(wrapped.contains(".stack_size(8388608)"));
+        assert_eq!(wrapped.matches("fn main()").count(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::path::Path;
+
+/// Starting points for common program shapes
+///
+/// Each template lays a spec out in Purpose, Inputs, Outputs, Steps, Errors,
+/// and Acceptance sections, with a worked example in each, so the model gets
+/// an unambiguous description and `--check-acceptance` has tests to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Reads arguments from standard input, prints a result, and exits
+    CliTool,
+    /// Transforms standard input line by line until end of file
+    Filter,
+    /// Listens on a TCP port and answers clients
+    Server,
+}
+
+const CLI_TOOL: &str = r#"Purpose: Convert a temperature from degrees Celsius to degrees Fahrenheit.
+
+Inputs: Read one line from standard input containing a number of degrees Celsius, which may be negative or have a fractional part.
+
+Outputs: Print the temperature in Fahrenheit with one decimal place, formatted as "F: 98.6".
+
+Steps:
+1. Read the line and parse it as a decimal number.
+2. Multiply it by 9, divide by 5, and add 32.
+3. Print the result.
+
+Errors: If the input is not a number, print "Error: expected a number" to standard error and exit with status 1.
+
+Acceptance:
+When run with input 37 it prints "F: 98.6".
+When run with input -40 it prints "F: -40.0".
+"#;
+
+const FILTER: &str = r#"Purpose: Number the lines of text read from standard input.
+
+Inputs: Read standard input line by line until end of file. Lines may be empty and may be of any length.
+
+Outputs: For each line, print its line number starting at 1, a colon and a space, then the line unchanged, e.g. "1: hello".
+
+Steps:
+1. Keep a counter starting at 1.
+2. For each line read, print the counter and the line, then increase the counter.
+3. Stop at end of file.
+
+Errors: Empty input is valid and prints nothing.
+
+Acceptance:
+When run with input "hello\nworld" it prints "2: world".
+When run with input "only" it prints "1: only".
+"#;
+
+const SERVER: &str = r#"Purpose: A TCP echo server.
+
+Inputs: Read the port number to listen on from the first line of standard input.
+
+Outputs: Print "Listening on port N" once the server is ready. Send every line a client sends back to that client unchanged.
+
+Steps:
+1. Read and validate the port.
+2. Listen on 127.0.0.1 at that port.
+3. Accept one client at a time and echo each line it sends until it disconnects, then wait for the next client.
+
+Errors: If the port is not a whole number between 1 and 65535, print "Invalid port" and exit without listening. If the port is already in use, print "Port N is in use" to standard error and exit with status 1.
+
+Acceptance:
+When run with input "abc" it prints "Invalid port".
+"#;
+
+impl Template {
+    /// The spec text the template starts from
+    pub fn contents(self) -> &'static str {
+        match self {
+            Template::CliTool => CLI_TOOL,
+            Template::Filter => FILTER,
+            Template::Server => SERVER,
+        }
+    }
+    
+    /// Write the template to `path`, refusing to replace an existing file unless `force` is set
+    pub fn write(self, path: &Path, force: bool) -> Result<()> {
+        if path.exists() && !force {
+            anyhow::bail!("{:?} already exists; pass --force to overwrite it", path);
+        }
+        fs::write(path, self.contents())
+            .with_context(|| format!("Failed to write spec: {:?}", path))
+    }
+}
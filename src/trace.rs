@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::interpreter::Value;
+use crate::offline::Sentence;
+
+/// Current version of the trace format
+const FORMAT_VERSION: u32 = 1;
+
+/// A condition on a variable, e.g. `total < 0` or `name == Ada`
+const CONDITION: &str = r"^(?P<name>\w+)\s*(?P<op><=|>=|==|!=|<|>|=)\s*(?P<value>.*)$";
+
+static CONDITION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(CONDITION).expect("condition pattern is valid"));
+
+/// Every state transition of an interpreted run, for replaying it in either direction
+///
+/// Each step stores only what it changed, so the variables after step N are
+/// rebuilt by applying the first N steps' changes in order.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Trace {
+    pub format_version: u32,
+    /// nhlp version that recorded the run
+    pub nhlp_version: String,
+    /// The spec that was run
+    pub source: String,
+    pub sentences: Vec<TracedSentence>,
+    pub steps: Vec<Transition>,
+}
+
+/// A sentence of the traced spec
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TracedSentence {
+    pub line: usize,
+    pub text: String,
+}
+
+/// One step of a run: a sentence executed, or a value set from the debugger
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Transition {
+    /// Index of the sentence executed; None when the debugger set a value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<Change>,
+    /// Line the step read from standard input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// What the step printed, prompts included
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output: String,
+    /// The runtime error that ended the run at this step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A variable given a new value
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Change {
+    pub name: String,
+    pub value: Value,
+}
+
+impl Trace {
+    /// Record the steps a run of `source` took through `sentences`
+    pub fn new(source: &Path, sentences: &[Sentence], steps: Vec<Transition>) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            nhlp_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.display().to_string(),
+            sentences: sentences
+                .iter()
+                .map(|sentence| TracedSentence { line: sentence.line, text: sentence.text.clone() })
+                .collect(),
+            steps,
+        }
+    }
+    
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read trace: {:?}", path))?;
+        let trace: Self = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid trace: {:?}", path))?;
+        if trace.format_version > FORMAT_VERSION {
+            anyhow::bail!(
+                "{:?} was written by nhlp {} in a newer trace format; upgrade nhlp to open it",
+                path,
+                trace.nhlp_version
+            );
+        }
+        if let Some(step) = trace.steps.iter().find(|step| step.sentence.is_some_and(|index| index >= trace.sentences.len())) {
+            anyhow::bail!("Invalid trace: {:?}: step refers to sentence {:?}, which it doesn't have", path, step.sentence);
+        }
+        Ok(trace)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write trace: {:?}", path))
+    }
+    
+    /// The variables after the first `steps` steps
+    pub fn state(&self, steps: usize) -> BTreeMap<String, Value> {
+        let mut variables = BTreeMap::new();
+        for change in self.steps[..steps].iter().flat_map(|step| &step.changes) {
+            variables.insert(change.name.clone(), change.value.clone());
+        }
+        variables
+    }
+    
+    /// The first step after which `condition` holds when it didn't before, as
+    /// a count of steps taken, so "when did total become negative" is `total < 0`
+    pub fn first_time(&self, condition: &Condition) -> Option<usize> {
+        let mut variables = BTreeMap::new();
+        let mut held = false;
+        for (index, step) in self.steps.iter().enumerate() {
+            for change in &step.changes {
+                variables.insert(change.name.clone(), change.value.clone());
+            }
+            let holds = condition.holds(&variables);
+            if holds && !held {
+                return Some(index + 1);
+            }
+            held = holds;
+        }
+        None
+    }
+    
+    /// The first step after the first `steps` that changes `name`, as a count of steps taken
+    pub fn next_change(&self, steps: usize, name: &str) -> Option<usize> {
+        self.steps[steps..]
+            .iter()
+            .position(|step| step.changes.iter().any(|change| change.name == name))
+            .map(|offset| steps + offset + 1)
+    }
+}
+
+/// A comparison of a variable with a value, as typed after `when`
+pub struct Condition {
+    name: String,
+    op: String,
+    value: String,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Option<Self> {
+        let captures = CONDITION_REGEX.captures(text.trim())?;
+        Some(Self {
+            name: captures["name"].to_string(),
+            op: captures["op"].to_string(),
+            value: captures["value"].trim().trim_matches('"').to_string(),
+        })
+    }
+    
+    /// Numbers compare numerically, text by its characters; a variable
+    /// without a value satisfies nothing
+    fn holds(&self, variables: &BTreeMap<String, Value>) -> bool {
+        let ordering = match variables.get(&self.name) {
+            Some(Value::Number(number)) => match self.value.parse::<f64>() {
+                Ok(value) => number.partial_cmp(&value),
+                Err(_) => return false,
+            },
+            Some(Value::Text(text)) => Some(text.as_str().cmp(self.value.as_str())),
+            Some(Value::List(_)) | None => return false,
+        };
+        let Some(ordering) = ordering else {
+            return self.op == "!=";
+        };
+        match self.op.as_str() {
+            "<" => ordering == Ordering::Less,
+            "<=" => ordering != Ordering::Greater,
+            ">" => ordering == Ordering::Greater,
+            ">=" => ordering != Ordering::Less,
+            "!=" => ordering != Ordering::Equal,
+            _ => ordering == Ordering::Equal,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Writes every prompt sent to the model and every raw response to a directory
+///
+/// Each exchange gets a number, so `003-counterexamples.prompt.txt` and
+/// `003-counterexamples.response.txt` belong together. Prompts are logged
+/// after redaction and responses before secrets are restored, so the files
+/// hold exactly what crossed the wire.
+pub struct Transcript {
+    dir: PathBuf,
+    exchanges: AtomicUsize,
+}
+
+impl Transcript {
+    /// Start a transcript in `dir`, creating it if needed; numbering continues
+    /// after any exchanges already there so earlier runs aren't overwritten
+    pub fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create LLM debug directory: {:?}", dir))?;
+        let last = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str()?.split('-').next()?.parse::<usize>().ok()
+            })
+            .max()
+            .unwrap_or(0);
+        Ok(Self { dir: dir.to_path_buf(), exchanges: AtomicUsize::new(last) })
+    }
+    
+    /// Record a prompt for a pipeline stage, returning the exchange number for its response
+    pub fn prompt(&self, stage: &str, prompt: &str) -> Result<usize> {
+        let exchange = self.exchanges.fetch_add(1, Ordering::Relaxed) + 1;
+        self.write(exchange, stage, "prompt", prompt)?;
+        Ok(exchange)
+    }
+    
+    /// Record the model's raw response, or the error it failed with
+    pub fn response(&self, exchange: usize, stage: &str, response: Result<&str, &anyhow::Error>) -> Result<()> {
+        match response {
+            Ok(text) => self.write(exchange, stage, "response", text),
+            Err(e) => self.write(exchange, stage, "error", &format!("{:#}", e)),
+        }
+    }
+    
+    /// Record how the compilation ended when it ended before its exchanges did, such as `cancelled`
+    pub fn outcome(&self, outcome: &str, detail: &str) -> Result<()> {
+        let exchange = self.exchanges.fetch_add(1, Ordering::Relaxed) + 1;
+        self.write(exchange, "compilation", outcome, detail)
+    }
+    
+    fn write(&self, exchange: usize, stage: &str, kind: &str, text: &str) -> Result<()> {
+        let path = self.dir.join(format!("{:03}-{}.{}.txt", exchange, stage, kind));
+        fs::write(&path, text)
+            .with_context(|| format!("Failed to write LLM debug file: {:?}", path))
+    }
+}
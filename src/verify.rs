@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::offline::{Comparison, Expr, Kind, Op, Operand, Reduction, Sentence, Statement};
+
+/// Largest count a list can have: more items than this can't be held as doubles in memory
+const MAX_COUNT: f64 = 9_007_199_254_740_992.0;
+
+/// The values a number can hold: every number between two bounds, and NaN if `nan` is set
+///
+/// Rounding to the nearest double never reorders two results, so bounds
+/// computed in doubles hold every value the program computes in doubles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub low: f64,
+    pub high: f64,
+    pub nan: bool,
+}
+
+impl Interval {
+    /// Any number at all, as read from input, where `inf` and `nan` parse too
+    pub const ANY: Interval = Interval { low: f64::NEG_INFINITY, high: f64::INFINITY, nan: true };
+
+    pub fn exactly(value: f64) -> Self {
+        Self { low: value, high: value, nan: false }
+    }
+
+    /// Whether every value is a finite number
+    pub fn is_finite(&self) -> bool {
+        !self.nan && self.low.is_finite() && self.high.is_finite()
+    }
+
+    /// Whether the interval can hold zero, or a value between -1 and 1 that becomes zero as a whole number
+    fn holds_whole_zero(&self) -> bool {
+        self.nan || (self.low < 1.0 && self.high > -1.0)
+    }
+
+    /// The result of one arithmetic operation on a value from each interval
+    pub fn apply(self, op: Op, other: Interval) -> Self {
+        match op {
+            Op::Add => self.corners(other, |a, b| a + b),
+            Op::Subtract => self.corners(other, |a, b| a - b),
+            Op::Multiply => self.corners(other, |a, b| a * b),
+            Op::Divide if other.low <= 0.0 && other.high >= 0.0 => Self::ANY,
+            Op::Divide => self.corners(other, |a, b| a / b),
+            Op::Remainder => self.remainder(other),
+        }
+    }
+
+    /// Bounds from applying `f` to the ends of both intervals, which holds for
+    /// the four operations since each is monotonic in each operand on either side of zero
+    fn corners(self, other: Interval, f: impl Fn(f64, f64) -> f64) -> Self {
+        let values = [f(self.low, other.low), f(self.low, other.high), f(self.high, other.low), f(self.high, other.high)];
+        // inf - inf and 0 * inf are NaN; every other result lies between the corners that aren't
+        let nan = self.nan || other.nan || values.iter().any(|value| value.is_nan());
+        let numbers: Vec<f64> = values.into_iter().filter(|value| !value.is_nan()).collect();
+        if numbers.is_empty() {
+            return Self::ANY;
+        }
+        let low = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self { low, high, nan }
+    }
+
+    /// Bounds of `remainder_of`: smaller in size than both operands, with the sign of the dividend
+    fn remainder(self, divisor: Interval) -> Self {
+        let largest = |interval: Interval| if interval.nan { f64::INFINITY } else { interval.low.abs().max(interval.high.abs()) };
+        let size = (largest(divisor) - 1.0).min(largest(self)).max(0.0).floor();
+        let low = if !self.nan && self.low >= 0.0 { 0.0 } else { -size };
+        let high = if !self.nan && self.high <= 0.0 { 0.0 } else { size };
+        Self { low, high, nan: false }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.low == self.high {
+            write!(f, "{}", self.low)?;
+        } else {
+            write!(f, "[{}, {}]", self.low, self.high)?;
+        }
+        if self.nan {
+            write!(f, " or NaN")?;
+        }
+        Ok(())
+    }
+}
+
+/// What must hold for one operation of the program to be safe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// A quotient's divisor isn't zero, which would make it infinite or NaN
+    NonZeroDivisor,
+    /// A remainder's divisor isn't zero as a whole number, which stops the program
+    NonZeroRemainderDivisor,
+    /// The result of finite operands stays finite
+    NoOverflow,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Condition::NonZeroDivisor => "non-zero divisor",
+            Condition::NonZeroRemainderDivisor => "non-zero remainder divisor",
+            Condition::NoOverflow => "no overflow",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Whether a condition was shown to hold for every input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proof {
+    /// Holds for every input the program can be given
+    Proven,
+    /// Fails for every input: the operation is always wrong
+    Refuted,
+    /// Holds for some inputs and may fail for others
+    Unproven,
+}
+
+/// A condition one sentence of the program relies on, and whether it holds
+#[derive(Debug, Clone)]
+pub struct Obligation {
+    /// Line of the spec the sentence is on
+    pub line: usize,
+    pub sentence: String,
+    pub condition: Condition,
+    pub proof: Proof,
+    /// The values the operand in question can take
+    pub values: Interval,
+}
+
+impl Obligation {
+    /// Whether the lint about arithmetic safety should be raised for it
+    ///
+    /// An input read from the user can be anything, so arithmetic on one
+    /// can't be proven not to overflow; only an overflow that always happens
+    /// is worth raising, while every divisor not proven non-zero is.
+    pub fn is_reported(&self) -> bool {
+        match self.condition {
+            Condition::NoOverflow => self.proof == Proof::Refuted,
+            _ => self.proof != Proof::Proven,
+        }
+    }
+
+    /// What goes wrong when the condition fails
+    pub fn describe(&self) -> String {
+        let always = self.proof == Proof::Refuted;
+        match self.condition {
+            Condition::NonZeroDivisor if always => format!("\"{}\" always divides by zero", self.sentence),
+            Condition::NonZeroDivisor => format!("\"{}\" may divide by zero, since the divisor can be {}", self.sentence, self.values),
+            Condition::NonZeroRemainderDivisor if always => format!("\"{}\" always takes a remainder after dividing by zero, which stops the program", self.sentence),
+            Condition::NonZeroRemainderDivisor => format!(
+                "\"{}\" may take a remainder after dividing by zero, which stops the program, since the divisor can be {}",
+                self.sentence, self.values
+            ),
+            Condition::NoOverflow if always => format!("\"{}\" always overflows to {}", self.sentence, self.values),
+            Condition::NoOverflow => format!("\"{}\" may overflow, giving {}", self.sentence, self.values),
+        }
+    }
+}
+
+/// The arithmetic safety conditions of a program and which of them hold
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    pub obligations: Vec<Obligation>,
+    /// Sentences, by index, whose remainder can't divide by 0 or -1 and so needs no runtime check
+    unchecked_remainders: BTreeSet<usize>,
+}
+
+impl Verification {
+    /// Whether the remainder in the sentence at `index` can skip `remainder_of`'s checks
+    pub fn unchecked_remainder(&self, index: usize) -> bool {
+        self.unchecked_remainders.contains(&index)
+    }
+
+    pub fn count(&self, proof: Proof) -> usize {
+        self.obligations.iter().filter(|obligation| obligation.proof == proof).count()
+    }
+}
+
+/// Verify the arithmetic of a program in the offline vocabulary
+///
+/// Every quotient and remainder gets a condition on its divisor and every
+/// operation one on overflow. Each is decided by interval arithmetic: the
+/// values every variable can hold are worked out sentence by sentence, and a
+/// condition holding across a whole interval is proven, one failing across
+/// all of it refuted. This decides the linear, one-operation conditions the
+/// vocabulary produces without an external solver; conditions it can't
+/// decide are left unproven and keep their runtime checks.
+pub fn verify(sentences: &[Sentence]) -> Verification {
+    let mut values: BTreeMap<&str, Interval> = BTreeMap::new();
+    let mut verification = Verification::default();
+    for (index, sentence) in sentences.iter().enumerate() {
+        match &sentence.statement {
+            Statement::Ask { name, kind: Kind::Number, .. } => {
+                values.insert(name, Interval::ANY);
+            }
+            Statement::Set { name, value, .. } => {
+                let result = evaluate(value, &values);
+                obligations(index, sentence, value, &values, &mut verification);
+                values.insert(name, result);
+            }
+            Statement::PrintNumber(value) => obligations(index, sentence, value, &values, &mut verification),
+            _ => {}
+        }
+    }
+    verification
+}
+
+/// The values an expression can take
+pub fn evaluate(expr: &Expr, values: &BTreeMap<&str, Interval>) -> Interval {
+    match expr {
+        Expr::Value(operand) => value(operand, values),
+        Expr::Binary(a, op, b) => value(a, values).apply(*op, value(b, values)),
+        Expr::Reduce { reduction: Reduction::Count, .. } => Interval { low: 0.0, high: MAX_COUNT, nan: false },
+        Expr::Reduce { reduction: Reduction::Sum, .. } => Interval::ANY,
+        Expr::Reduce { filter: Some((comparison, bound)), .. } => {
+            // Items a filter lets through compare true, so none is NaN, and all
+            // are on the bound's side; their minimum, maximum, or average is too
+            let bound = value(bound, values);
+            match comparison {
+                Comparison::Above | Comparison::AtLeast => Interval { low: bound.low, high: f64::INFINITY, nan: false },
+                Comparison::Below | Comparison::AtMost => Interval { low: f64::NEG_INFINITY, high: bound.high, nan: false },
+                Comparison::Equal => Interval { nan: false, ..bound },
+                Comparison::NotEqual => Interval::ANY,
+            }
+        }
+        Expr::Reduce { .. } => Interval::ANY,
+    }
+}
+
+fn value(operand: &Operand, values: &BTreeMap<&str, Interval>) -> Interval {
+    match operand {
+        Operand::Literal(literal) => Interval::exactly(*literal),
+        Operand::Variable(name) => values.get(name.as_str()).copied().unwrap_or(Interval::ANY),
+    }
+}
+
+/// The conditions the operation in `expr` relies on, added to `verification`
+fn obligations(index: usize, sentence: &Sentence, expr: &Expr, values: &BTreeMap<&str, Interval>, verification: &mut Verification) {
+    let Expr::Binary(a, op, b) = expr else {
+        return;
+    };
+    let (a, b) = (value(a, values), value(b, values));
+    let obligation = |condition, proof, values| Obligation { line: sentence.line, sentence: sentence.text.clone(), condition, proof, values };
+    match op {
+        Op::Remainder => {
+            let proof = if !b.holds_whole_zero() {
+                Proof::Proven
+            } else if !b.nan && b.low > -1.0 && b.high < 1.0 {
+                Proof::Refuted
+            } else {
+                Proof::Unproven
+            };
+            verification.obligations.push(obligation(Condition::NonZeroRemainderDivisor, proof, b));
+            // -1 is the other divisor `remainder_of` handles, since LLONG_MIN % -1 overflows
+            if !b.nan && (b.low >= 2.0 || b.high <= -2.0) {
+                verification.unchecked_remainders.insert(index);
+            }
+        }
+        _ => {
+            if *op == Op::Divide {
+                let proof = if b.low > 0.0 || b.high < 0.0 {
+                    Proof::Proven
+                } else if !b.nan && b.low == 0.0 && b.high == 0.0 {
+                    Proof::Refuted
+                } else {
+                    Proof::Unproven
+                };
+                verification.obligations.push(obligation(Condition::NonZeroDivisor, proof, b));
+            }
+            let result = a.apply(*op, b);
+            let proof = if a.is_finite() && b.is_finite() && result.is_finite() {
+                Proof::Proven
+            } else if a.is_finite() && b.is_finite() && !result.nan && (result.low == f64::INFINITY || result.high == f64::NEG_INFINITY) {
+                Proof::Refuted
+            } else {
+                Proof::Unproven
+            };
+            verification.obligations.push(obligation(Condition::NoOverflow, proof, result));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::offline;
+
+    fn verified(program: &str) -> Verification {
+        verify(&offline::parse(program).unwrap())
+    }
+
+    fn proofs(verification: &Verification, condition: Condition) -> Vec<Proof> {
+        verification.obligations.iter().filter(|obligation| obligation.condition == condition).map(|obligation| obligation.proof).collect()
+    }
+
+    #[test]
+    fn literal_divisors_are_decided() {
+        let verification = verified("Set a to 10 modulo 3. Set b to 10 modulo 0. Set c to 10 divided by 0.");
+        assert_eq!(proofs(&verification, Condition::NonZeroRemainderDivisor), [Proof::Proven, Proof::Refuted]);
+        assert_eq!(proofs(&verification, Condition::NonZeroDivisor), [Proof::Refuted]);
+        assert!(verification.unchecked_remainder(0));
+        assert!(!verification.unchecked_remainder(1));
+    }
+
+    #[test]
+    fn input_divisors_are_unproven() {
+        let verification = verified("Ask for a number called n. Print 100 modulo n. Print 100 divided by n.");
+        assert_eq!(proofs(&verification, Condition::NonZeroRemainderDivisor), [Proof::Unproven]);
+        assert_eq!(proofs(&verification, Condition::NonZeroDivisor), [Proof::Unproven]);
+        assert!(!verification.unchecked_remainder(1));
+        assert!(verification.obligations.iter().filter(|obligation| obligation.is_reported()).count() == 2);
+    }
+
+    #[test]
+    fn values_flow_through_variables() {
+        let verification = verified(
+            "Ask for a list of numbers called xs. Set n to the count of xs. Set d to n plus 2. Print 7 modulo d. \
+             Set m to the minimum of xs where it is at least 3. Print 9 divided by m.",
+        );
+        assert_eq!(proofs(&verification, Condition::NonZeroRemainderDivisor), [Proof::Proven]);
+        assert_eq!(proofs(&verification, Condition::NonZeroDivisor), [Proof::Proven]);
+        assert!(verification.unchecked_remainder(3));
+    }
+
+    #[test]
+    fn a_divisor_of_minus_one_keeps_its_check() {
+        let verification = verified("Set a to 10 modulo -1.");
+        assert_eq!(proofs(&verification, Condition::NonZeroRemainderDivisor), [Proof::Proven]);
+        assert!(!verification.unchecked_remainder(0));
+    }
+
+    #[test]
+    fn overflow_of_literals_is_refuted() {
+        let verification = verified("Set big to 1e308 times 10. Set fine to 1e300 times 10.");
+        assert_eq!(proofs(&verification, Condition::NoOverflow), [Proof::Refuted, Proof::Proven]);
+    }
+
+    #[test]
+    fn interval_bounds_hold_every_result() {
+        let a = Interval { low: -3.0, high: 5.0, nan: false };
+        let b = Interval { low: 2.0, high: 4.0, nan: false };
+        for (op, x, y) in [(Op::Multiply, -3.0, 4.0), (Op::Subtract, 5.0, 2.0), (Op::Divide, -3.0, 2.0)] {
+            let result = a.apply(op, b);
+            let exact = match op {
+                Op::Multiply => x * y,
+                Op::Subtract => x - y,
+                _ => x / y,
+            };
+            assert!(result.low <= exact && exact <= result.high, "{:?} gave {}", op, result);
+        }
+        let remainder = a.apply(Op::Remainder, b);
+        assert_eq!((remainder.low, remainder.high), (-3.0, 3.0));
+        assert!(Interval::ANY.apply(Op::Add, Interval::exactly(1.0)).nan);
+    }
+}
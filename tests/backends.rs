@@ -0,0 +1,90 @@
+mod common;
+
+use std::fs;
+
+use common::Fixture;
+
+/// Stdout of the spec built natively and run, then of the spec run by the
+/// interpreter, both reading `stdin`
+fn outputs(source: &str, stdin: &str) -> (String, String) {
+    let fixture = Fixture::new("spec", source);
+    let stdin_file = fixture.path().join("stdin.txt");
+    fs::write(&stdin_file, stdin).expect("stdin written");
+    let stdin_file = stdin_file.to_str().expect("temporary path is UTF-8");
+
+    let native = fixture.run(&["--stdin-file", stdin_file]);
+    assert!(native.status.success(), "native build failed: {}", String::from_utf8_lossy(&native.stderr));
+    let interpreted = fixture.run(&["--backend", "interp", "--stdin-file", stdin_file]);
+    assert!(interpreted.status.success(), "interpreter failed: {}", String::from_utf8_lossy(&interpreted.stderr));
+    (String::from_utf8_lossy(&native.stdout).into_owned(), String::from_utf8_lossy(&interpreted.stdout).into_owned())
+}
+
+/// Literals mean the same number in both backends, however the spec spells them
+#[test]
+fn literals_agree_between_backends() {
+    let (native, interpreted) = outputs(
+        "Set x to 010 plus 1.\nPrint x.\nSet y to 09 plus 1.\nPrint y.\nSet z to 1e5 plus .5.\nPrint z.\nSet w to +2 times 0.25.\nPrint w.\n",
+        "",
+    );
+    assert_eq!(native, interpreted);
+    assert_eq!(native.lines().collect::<Vec<_>>(), ["11", "10", "100000", "0.5"]);
+}
+
+/// Remainders truncate both operands, keep the dividend's sign, saturate, and are 0 for a divisor of -1
+#[test]
+fn remainders_agree_between_backends() {
+    let (native, interpreted) = outputs(
+        "Ask for a number called n.\n\
+         Print the remainder of n and 3.\n\
+         Print 7.9 modulo 2.5.\n\
+         Print n modulo -1.\n\
+         Print 1e30 modulo 6.\n\
+         Print -1e30 modulo 6.\n",
+        "-7\n",
+    );
+    assert_eq!(native, interpreted);
+}
+
+/// Numbers print as `%g` writes them
+#[test]
+fn formatting_agrees_between_backends() {
+    let (native, interpreted) = outputs(
+        "Print 1 divided by 3.\n\
+         Print 1000000 times 10.\n\
+         Print 0.0001 divided by 10.\n\
+         Print 123456.7 plus 0.\n\
+         Print 999999.5 plus 0.\n\
+         Print 1 divided by 0.\n\
+         Print -1 divided by 0.\n\
+         Print 0 times -1.\n\
+         Print 1e-300 times 1e-300.\n\
+         Print 2.5e15 plus 1.\n",
+        "",
+    );
+    assert_eq!(native, interpreted);
+}
+
+/// Input numbers are read as strtod reads the start of a line
+#[test]
+fn number_input_agrees_between_backends() {
+    let source = "Ask for a number called a.\nAsk for a number called b.\nAsk for a number called c.\n\
+                  Ask for a number called d.\nAsk for a number called e.\nAsk for a number called f.\n\
+                  Print \"{a} {b} {c} {d} {e} {f}\".\n";
+    let (native, interpreted) = outputs(source, "  12abc\n0x1.8p3\n1e\n+.5\n-INF\n1e-2x\n");
+    assert_eq!(native, interpreted);
+}
+
+/// Reductions add in the same order and filter the same items
+#[test]
+fn reductions_agree_between_backends() {
+    let (native, interpreted) = outputs(
+        "Ask for a list of numbers called xs.\n\
+         Print the sum of xs.\n\
+         Print the average of xs.\n\
+         Print the count of xs where it is above 0.2.\n\
+         Print the minimum of xs.\n\
+         Print the maximum of xs.\n",
+        "0.1\n0.2\n0.3\n1e16\n-1e16\n0.4\n\n",
+    );
+    assert_eq!(native, interpreted);
+}
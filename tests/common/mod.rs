@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+/// A spec written to a temporary directory, built by nhlp with only the offline tier
+pub struct Fixture {
+    pub dir: TempDir,
+    pub spec: PathBuf,
+}
+
+impl Fixture {
+    /// Write `source` to `<name>.dshp` in a fresh temporary directory
+    pub fn new(name: &str, source: &str) -> Self {
+        let dir = tempfile::tempdir().expect("temporary directory");
+        let spec = dir.path().join(format!("{}.dshp", name));
+        fs::write(&spec, source).expect("spec written");
+        Self { dir, spec }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// nhlp on the spec with `args`, through a ladder holding only the offline tier
+    ///
+    /// Outputs go to the fixture's directory, and the response cache and
+    /// ladder settings from the environment are kept out.
+    pub fn nhlp(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_nhlp"));
+        command
+            .arg(&self.spec)
+            .args(["--model-ladder", "offline"])
+            .args(args)
+            .arg("--out-dir")
+            .arg(self.path())
+            .env("NHLP_CACHE_DIR", self.path().join("cache"))
+            .env_remove("DSHPC_DEMO_MODE")
+            .env_remove("NHLP_MODEL_LADDER");
+        command
+    }
+
+    /// Run nhlp on the spec with `args`
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.nhlp(args).output().expect("nhlp runs")
+    }
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::Fixture;
+
+/// A spec in the offline vocabulary builds and runs through a ladder holding only the offline tier
+#[test]
+fn offline_tier_translates_by_rule() {
+    let fixture = Fixture::new("sum", "Print \"Hello, World!\".\nSet total to 5 plus 10.\nPrint \"The sum is {total}\".\n");
+    let output = fixture.run(&[]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "nhlp failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.lines().any(|line| line == "Hello, World!"), "unexpected output: {}", stdout);
+    assert!(stdout.lines().any(|line| line == "The sum is 15"), "unexpected output: {}", stdout);
+}
+
+/// A spec outside the vocabulary fails the offline tier instead of building placeholder text
+#[test]
+fn offline_tier_rejects_sentences_outside_the_vocabulary() {
+    let fixture = Fixture::new("prose", "Create a program that greets the world warmly.\n");
+    let output = fixture.run(&["--no-run"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Offline mode can't translate"), "unexpected error: {}", stderr);
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::Fixture;
+
+/// `--stack-size` is built into the program, so it runs its code on that stack outside nhlp too
+#[test]
+fn stack_size_is_built_into_the_program() {
+    let fixture = Fixture::new("sum", "Set total to 5 plus 10.\nPrint \"The sum is {total}\".\n");
+    let output = fixture.run(&["--stack-size", "16M", "--no-run"]);
+    assert!(output.status.success(), "nhlp failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let executable = fixture.path().join("sum");
+    let binary = fs::read(&executable).expect("executable built");
+    assert!(binary.windows(b"nhlp_program_thread".len()).any(|window| window == b"nhlp_program_thread"));
+    let output = Command::new(&executable).output().expect("program runs standalone");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "The sum is 15");
+}